@@ -0,0 +1,205 @@
+use std::{
+    error::Error,
+    f64::consts::LN_2,
+    fs::{read, write},
+};
+
+/// Identifies files written by `BloomFilter::save`.
+const FILE_MAGIC: &[u8; 4] = b"KABF";
+
+/// A space-efficient set of ad IDs that only ever grows.
+///
+/// Unlike the pruned seen-ads list, this filter remembers every ID it was given
+/// in a fixed amount of memory. The price is a small, configurable chance of false
+/// positives: an ID it never saw may be reported as present, but an ID it saw is
+/// never reported as missing.
+pub struct BloomFilter {
+    /// The bit array, packed into 64-bit words.
+    bits: Vec<u64>,
+    /// The number of usable bits in `bits`.
+    num_bits: u64,
+    /// The number of bit positions set per item.
+    num_hashes: u32,
+    /// How many items were inserted so far (approximately, duplicates count again).
+    inserted: u64,
+}
+
+/// Hashes `item` with 64-bit FNV-1a, starting from `seed`.
+///
+/// A fixed algorithm is used instead of the standard library's hasher, whose
+/// output may change between Rust versions and would corrupt the saved filter.
+pub fn fnv1a(item: &str, seed: u64) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325 ^ seed;
+    for byte in item.as_bytes() {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+impl BloomFilter {
+    /// Creates an empty filter sized for `capacity` items at the given false-positive rate.
+    pub fn with_rate(capacity: usize, false_positive_rate: f64) -> Self {
+        let capacity = capacity.max(1) as f64;
+        let rate = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+
+        // Standard sizing: m = -n * ln(p) / ln(2)^2 bits and k = m / n * ln(2) hashes.
+        let num_bits = (-capacity * rate.ln() / (LN_2 * LN_2)).ceil().max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / capacity) * LN_2).round().max(1.0) as u32;
+
+        Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+            inserted: 0,
+        }
+    }
+
+    /// Returns the bit positions for `item` using double hashing.
+    fn positions(&self, item: &str) -> impl Iterator<Item = u64> + '_ {
+        let h1 = fnv1a(item, 0);
+        let h2 = fnv1a(item, 0x9e37_79b9_7f4a_7c15) | 1;
+        (0..u64::from(self.num_hashes))
+            .map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits)
+    }
+
+    /// Adds `item` to the filter.
+    pub fn insert(&mut self, item: &str) {
+        let positions: Vec<u64> = self.positions(item).collect();
+        for position in positions {
+            self.bits[(position / 64) as usize] |= 1 << (position % 64);
+        }
+        self.inserted += 1;
+    }
+
+    /// Checks whether `item` was probably inserted before.
+    pub fn contains(&self, item: &str) -> bool {
+        self.positions(item)
+            .all(|position| self.bits[(position / 64) as usize] & (1 << (position % 64)) != 0)
+    }
+
+    /// Returns how many items were inserted so far.
+    pub fn inserted(&self) -> u64 {
+        self.inserted
+    }
+
+    /// Loads a filter from `path`.
+    ///
+    /// If the file is missing, unreadable, or was created with different sizing
+    /// parameters, an empty filter with the requested sizing is returned instead.
+    pub fn load(path: &str, capacity: usize, false_positive_rate: f64) -> Self {
+        let empty = Self::with_rate(capacity, false_positive_rate);
+        let Ok(content) = read(path) else {
+            return empty;
+        };
+
+        match Self::decode(&content) {
+            Some(filter)
+                if filter.num_bits == empty.num_bits && filter.num_hashes == empty.num_hashes =>
+            {
+                filter
+            }
+            Some(_) => {
+                log!(
+                    "Die Größe des Filters in {} passt nicht zur Konfiguration. Er wird neu aufgebaut.",
+                    path
+                );
+                empty
+            }
+            None => {
+                log!(
+                    "Fehler beim Lesen der Datei {}: ungültiges Format. Der Filter wird neu aufgebaut.",
+                    path
+                );
+                empty
+            }
+        }
+    }
+
+    /// Parses the binary file format written by `save`.
+    fn decode(content: &[u8]) -> Option<Self> {
+        let rest = content.strip_prefix(FILE_MAGIC)?;
+        let (num_hashes, rest) = rest.split_at_checked(4)?;
+        let (num_bits, rest) = rest.split_at_checked(8)?;
+        let (inserted, rest) = rest.split_at_checked(8)?;
+        let num_hashes = u32::from_le_bytes(num_hashes.try_into().ok()?);
+        let num_bits = u64::from_le_bytes(num_bits.try_into().ok()?);
+        let inserted = u64::from_le_bytes(inserted.try_into().ok()?);
+
+        if num_bits == 0 || rest.len() as u64 != num_bits.div_ceil(64) * 8 {
+            return None;
+        }
+        let bits = rest
+            .chunks_exact(8)
+            .map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+
+        Some(Self {
+            bits,
+            num_bits,
+            num_hashes,
+            inserted,
+        })
+    }
+
+    /// Saves the filter to `path` in a compact binary format.
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let mut content = Vec::with_capacity(24 + self.bits.len() * 8);
+        content.extend_from_slice(FILE_MAGIC);
+        content.extend_from_slice(&self.num_hashes.to_le_bytes());
+        content.extend_from_slice(&self.num_bits.to_le_bytes());
+        content.extend_from_slice(&self.inserted.to_le_bytes());
+        for word in &self.bits {
+            content.extend_from_slice(&word.to_le_bytes());
+        }
+        write(path, content)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env::temp_dir, fs::remove_file, process};
+
+    #[test]
+    fn inserted_ids_are_never_missing() {
+        let mut filter = BloomFilter::with_rate(1000, 0.01);
+        let ids: Vec<String> = (0..1000)
+            .map(|n| (3_000_000_000u64 + n).to_string())
+            .collect();
+        for id in &ids {
+            filter.insert(id);
+        }
+        assert!(ids.iter().all(|id| filter.contains(id)));
+        assert_eq!(filter.inserted(), 1000);
+
+        // At the configured rate, only a few of a thousand other IDs show up as false positives.
+        let false_positives = (0..1000)
+            .map(|n| (4_000_000_000u64 + n).to_string())
+            .filter(|id| filter.contains(id))
+            .count();
+        assert!(false_positives < 50, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn a_saved_filter_loads_with_the_same_contents() {
+        let path = temp_dir().join(format!("bloom-round-trip-{}.bin", process::id()));
+        let path = path.to_str().unwrap();
+        let mut filter = BloomFilter::with_rate(100, 0.01);
+        filter.insert("3012345678");
+        filter.insert("3087654321");
+        filter.save(path).unwrap();
+
+        let loaded = BloomFilter::load(path, 100, 0.01);
+        // Other sizing parameters don't fit the saved bits, so the filter starts over.
+        let resized = BloomFilter::load(path, 10_000, 0.01);
+        remove_file(path).unwrap();
+
+        assert_eq!(loaded.bits, filter.bits);
+        assert_eq!(loaded.inserted(), 2);
+        assert!(loaded.contains("3012345678") && loaded.contains("3087654321"));
+        assert_eq!(resized.inserted(), 0);
+        assert!(!resized.contains("3012345678"));
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Stops sending once deliveries keep failing, e.g. while Telegram is down.
+///
+/// Every failed delivery increases a counter that any success resets. Once
+/// `threshold` deliveries failed in a row, the breaker opens and stays open for
+/// the rest of the run, so the remaining ads stay unseen and keep their order
+/// for the next run instead of each burning through its retries.
+pub struct CircuitBreaker {
+    /// The number of consecutive failures that opens the breaker. `0` disables it.
+    threshold: usize,
+    /// The failures since the last successful delivery.
+    consecutive_failures: AtomicUsize,
+    /// Whether the breaker has opened.
+    open: AtomicBool,
+}
+
+impl CircuitBreaker {
+    /// Creates a closed breaker opening after `threshold` consecutive failures.
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            threshold,
+            consecutive_failures: AtomicUsize::new(0),
+            open: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns whether sending has been stopped.
+    pub fn is_open(&self) -> bool {
+        self.open.load(Ordering::SeqCst)
+    }
+
+    /// Opens the breaker right away, e.g. once the chat turned out to be unreachable.
+    ///
+    /// # Returns
+    /// `true` if the breaker wasn't open before.
+    pub fn trip(&self) -> bool {
+        !self.open.swap(true, Ordering::SeqCst)
+    }
+
+    /// Records the outcome of a delivery.
+    ///
+    /// # Returns
+    /// `true` if this failure opened the breaker.
+    pub fn record(&self, success: bool) -> bool {
+        if success {
+            self.consecutive_failures.store(0, Ordering::SeqCst);
+            return false;
+        }
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::SeqCst) + 1;
+        self.threshold > 0 && failures >= self.threshold && !self.open.swap(true, Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_consecutive_failures_only() {
+        let breaker = CircuitBreaker::new(3);
+        assert!(!breaker.record(false));
+        assert!(!breaker.record(false));
+        assert!(!breaker.record(true));
+        assert!(!breaker.record(false));
+        assert!(!breaker.record(false));
+        assert!(!breaker.is_open());
+        assert!(breaker.record(false));
+        assert!(breaker.is_open());
+
+        // It reports opening only once.
+        assert!(!breaker.record(false));
+
+        let disabled = CircuitBreaker::new(0);
+        for _ in 0..10 {
+            assert!(!disabled.record(false));
+        }
+        assert!(!disabled.is_open());
+    }
+}
@@ -0,0 +1,80 @@
+use std::{env::args, error::Error};
+
+/// Command-line options controlling a single run of the bot.
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    /// Print the new ads as a JSON array to stdout instead of sending them.
+    pub emit_json: bool,
+    /// Keep running and scan periodically instead of exiting after one scan.
+    pub watch: bool,
+    /// Log the ID and title of every scraped ad without an image.
+    pub warn_missing_images: bool,
+    /// Record why skipped or failed ads weren't delivered in the diagnostics file.
+    pub diagnose: bool,
+    /// Scrape one page and send a silent test message, then exit with the result.
+    pub healthcheck: bool,
+    /// Check the configuration, print the result and exit without scraping or sending.
+    pub validate_config: bool,
+    /// Write the seen ads to this file and exit.
+    pub export_seen: Option<String>,
+    /// Merge the seen ads from this file into the seen list and exit.
+    pub import_seen: Option<String>,
+    /// Write the log output as JSON lines, regardless of `LOG_FORMAT`.
+    pub log_json: bool,
+    /// Send at most this many ads in this run, leaving the rest unseen.
+    pub limit: Option<usize>,
+    /// Only run the profile with this name.
+    pub profile: Option<String>,
+    /// Delete the recorded Telegram messages of the search with this name and exit.
+    pub delete_search: Option<String>,
+    /// Start over with an empty seen list if the seen-ads file is corrupt, instead of aborting.
+    pub force_reseed: bool,
+    /// Keep the seen ads in memory only, never reading or writing the state files.
+    pub no_persist: bool,
+    /// Fetch the listing at this URL, send it once and exit, leaving the seen ads untouched.
+    pub send_ad: Option<String>,
+}
+
+impl CliArgs {
+    /// Parses the options from the process arguments.
+    ///
+    /// # Returns
+    /// The parsed options, or an error naming the first unknown argument
+    /// or an option that is missing its value.
+    pub fn parse() -> Result<Self, Box<dyn Error>> {
+        let mut cli_args = CliArgs::default();
+        let mut args = args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--emit-json" => cli_args.emit_json = true,
+                "--watch" => cli_args.watch = true,
+                "--warn-missing-images" => cli_args.warn_missing_images = true,
+                "--diagnose" => cli_args.diagnose = true,
+                "--healthcheck" => cli_args.healthcheck = true,
+                "--validate-config" => cli_args.validate_config = true,
+                "--log-json" => cli_args.log_json = true,
+                "--force-reseed" => cli_args.force_reseed = true,
+                "--no-persist" => cli_args.no_persist = true,
+                "--export-seen" => cli_args.export_seen = Some(value_of(&arg, args.next())?),
+                "--import-seen" => cli_args.import_seen = Some(value_of(&arg, args.next())?),
+                "--profile" => cli_args.profile = Some(value_of(&arg, args.next())?),
+                "--delete-search" => cli_args.delete_search = Some(value_of(&arg, args.next())?),
+                "--send-ad" => cli_args.send_ad = Some(value_of(&arg, args.next())?),
+                "--limit" => {
+                    let value = value_of(&arg, args.next())?;
+                    let limit = value.parse().map_err(|_| {
+                        format!("Option {} erwartet eine Zahl, nicht '{}'", arg, value)
+                    })?;
+                    cli_args.limit = Some(limit);
+                }
+                other => return Err(format!("Unbekanntes Argument: {}", other).into()),
+            }
+        }
+        Ok(cli_args)
+    }
+}
+
+/// Returns the value following an option, or an error if it is missing.
+fn value_of(option: &str, value: Option<String>) -> Result<String, Box<dyn Error>> {
+    value.ok_or_else(|| format!("Option {} erwartet einen Wert", option).into())
+}
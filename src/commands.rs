@@ -0,0 +1,311 @@
+use std::{error::Error, time::Duration};
+
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::{sync::mpsc::Sender, time::sleep};
+
+use crate::ignored::add_ignored;
+
+/// How long a single `getUpdates` call waits for new messages before returning.
+const LONG_POLL_TIMEOUT_SECS: u64 = 30;
+
+/// A command sent to the bot from the configured chat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Run a scan right away instead of waiting for the next poll interval.
+    Scan,
+}
+
+impl Command {
+    /// Parses a message text into a command.
+    ///
+    /// Accepts both `/scan` and the group form `/scan@BotName`.
+    fn parse(text: &str) -> Option<Self> {
+        let word = text.split_whitespace().next()?;
+        let name = word.split('@').next()?;
+        match name {
+            "/scan" => Some(Command::Scan),
+            _ => None,
+        }
+    }
+}
+
+/// What a button below an ad asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdAction {
+    /// Mark the ad as interesting, which only updates its buttons.
+    Interested,
+    /// Add the ad to the ignore list, so it's never announced again.
+    Ignore,
+}
+
+impl AdAction {
+    /// The prefix of the callback data identifying this action.
+    fn prefix(self) -> &'static str {
+        match self {
+            AdAction::Interested => "interested:",
+            AdAction::Ignore => "ignore:",
+        }
+    }
+
+    /// Builds the callback data of the button for this action on the ad `ad_id`.
+    pub fn callback_data(self, ad_id: &str) -> String {
+        format!("{}{}", self.prefix(), ad_id)
+    }
+
+    /// Parses callback data into the action and the ID of the ad it belongs to.
+    fn parse(data: &str) -> Option<(Self, &str)> {
+        [AdAction::Interested, AdAction::Ignore]
+            .into_iter()
+            .find_map(|action| {
+                data.strip_prefix(action.prefix())
+                    .filter(|ad_id| !ad_id.is_empty())
+                    .map(|ad_id| (action, ad_id))
+            })
+    }
+
+    /// The notification shown to the user who tapped the button, and the button replacing both.
+    fn confirmation(self) -> (&'static str, &'static str) {
+        match self {
+            AdAction::Interested => ("Als interessant markiert.", "✅ Interessiert"),
+            AdAction::Ignore => ("Anzeige wird ignoriert.", "🔕 Ignoriert"),
+        }
+    }
+}
+
+/// The response of Telegram's `getUpdates` method.
+#[derive(Debug, Deserialize)]
+struct UpdatesResponse {
+    /// The received updates, oldest first.
+    result: Vec<Update>,
+}
+
+/// A single incoming update.
+#[derive(Debug, Deserialize)]
+struct Update {
+    /// The update's identifier, used to acknowledge it via the next offset.
+    update_id: i64,
+    /// The new message, if this update carries one.
+    message: Option<Message>,
+    /// The tapped inline button, if this update carries one.
+    callback_query: Option<CallbackQuery>,
+}
+
+/// A tap on an inline button below one of the bot's messages.
+#[derive(Debug, Deserialize)]
+struct CallbackQuery {
+    /// The identifier to answer the query with.
+    id: String,
+    /// The callback data of the tapped button.
+    data: Option<String>,
+    /// The message the button belongs to, if it's still available.
+    message: Option<Message>,
+}
+
+/// An incoming message.
+#[derive(Debug, Deserialize)]
+struct Message {
+    /// The identifier of the message within its chat.
+    message_id: i64,
+    /// The chat the message was sent in.
+    chat: Chat,
+    /// The text of the message, if any.
+    text: Option<String>,
+}
+
+/// The chat an incoming message belongs to.
+#[derive(Debug, Deserialize)]
+struct Chat {
+    /// The unique identifier of the chat.
+    id: i64,
+}
+
+/// Fetches the next batch of updates after `offset`, waiting up to the long-poll timeout.
+async fn get_updates(
+    client: &Client,
+    bot_token: &str,
+    offset: i64,
+) -> Result<Vec<Update>, Box<dyn Error + Send + Sync>> {
+    let url = format!("https://api.telegram.org/bot{}/getUpdates", bot_token);
+    let response = client
+        .get(&url)
+        .query(&[
+            ("offset", offset.to_string()),
+            ("timeout", LONG_POLL_TIMEOUT_SECS.to_string()),
+            (
+                "allowed_updates",
+                r#"["message","callback_query"]"#.to_string(),
+            ),
+        ])
+        .timeout(Duration::from_secs(LONG_POLL_TIMEOUT_SECS + 10))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_body = response.text().await.unwrap_or_default();
+        return Err(format!("Telegram API Fehler: {} - {}", status, error_body).into());
+    }
+
+    Ok(response.json::<UpdatesResponse>().await?.result)
+}
+
+/// Calls a Bot API method with a JSON body, failing on any unsuccessful response.
+async fn call_api(
+    client: &Client,
+    bot_token: &str,
+    method: &str,
+    body: Value,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let url = format!("https://api.telegram.org/bot{}/{}", bot_token, method);
+    let response = client.post(&url).json(&body).send().await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_body = response.text().await.unwrap_or_default();
+        return Err(format!("Telegram API Fehler: {} - {}", status, error_body).into());
+    }
+    Ok(())
+}
+
+/// Handles a tap on one of the buttons below an ad.
+///
+/// "Ignorieren" adds the ad to the ignore list in `ignored_file`. Either way, the query is answered
+/// and the buttons are replaced by one showing the choice.
+async fn handle_callback(
+    client: &Client,
+    bot_token: &str,
+    ignored_file: &str,
+    query: CallbackQuery,
+) {
+    let Some((action, ad_id)) = query.data.as_deref().and_then(AdAction::parse) else {
+        // Buttons that were already used carry no action, but the query still needs an answer.
+        if let Err(e) = call_api(
+            client,
+            bot_token,
+            "answerCallbackQuery",
+            json!({ "callback_query_id": query.id }),
+        )
+        .await
+        {
+            log!("Fehler beim Beantworten der Schaltfläche: {}", e);
+        }
+        return;
+    };
+
+    let mut notice = action.confirmation().0.to_string();
+    if action == AdAction::Ignore {
+        match add_ignored(ignored_file, ad_id) {
+            Ok(_) => log!("Anzeige {} wird ab jetzt ignoriert.", ad_id),
+            Err(e) => {
+                log!("Fehler beim Speichern der Datei {}: {}", ignored_file, e);
+                notice = "Fehler beim Speichern der Ignorierliste.".to_string();
+            }
+        }
+    }
+    if let Err(e) = call_api(
+        client,
+        bot_token,
+        "answerCallbackQuery",
+        json!({ "callback_query_id": query.id, "text": notice }),
+    )
+    .await
+    {
+        log!("Fehler beim Beantworten der Schaltfläche: {}", e);
+    }
+
+    // Mark the message as handled by replacing both buttons with the choice.
+    if let Some(message) = query.message {
+        let body = json!({
+            "chat_id": message.chat.id,
+            "message_id": message.message_id,
+            "reply_markup": {
+                "inline_keyboard": [[
+                    { "text": action.confirmation().1, "callback_data": "done" }
+                ]]
+            },
+        });
+        if let Err(e) = call_api(client, bot_token, "editMessageReplyMarkup", body).await {
+            log!("Fehler beim Aktualisieren der Nachricht: {}", e);
+        }
+    }
+}
+
+/// Long-polls Telegram for commands and button taps, forwarding commands to the scan loop.
+///
+/// Only messages and taps from `chat_id` are accepted, so other chats the bot is part of
+/// cannot trigger scans. Runs until the receiving side of `commands` is dropped.
+///
+/// # Arguments
+/// * `client` - The `reqwest::Client` to use for the API calls.
+/// * `bot_token` - The token of the bot receiving the commands.
+/// * `chat_id` - The only chat commands are accepted from.
+/// * `ignored_file` - The ignore list "Ignorieren" taps are added to.
+/// * `profile` - The index of the profile the bot belongs to, sent along with every command.
+/// * `commands` - The channel parsed commands are sent to.
+pub async fn listen_for_commands(
+    client: Client,
+    bot_token: String,
+    chat_id: String,
+    ignored_file: String,
+    profile: usize,
+    commands: Sender<(usize, Command)>,
+) {
+    let mut offset = 0;
+    loop {
+        let updates = match get_updates(&client, &bot_token, offset).await {
+            Ok(updates) => updates,
+            Err(e) => {
+                log!("Fehler beim Abrufen der Telegram-Befehle: {}", e);
+
+                // Back off before polling again, e.g. when the network is down.
+                sleep(Duration::from_secs(10)).await;
+                continue;
+            }
+        };
+
+        for update in updates {
+            // Acknowledge the update so Telegram doesn't deliver it again.
+            offset = offset.max(update.update_id + 1);
+
+            if let Some(query) = update.callback_query {
+                if query
+                    .message
+                    .as_ref()
+                    .is_some_and(|message| message.chat.id.to_string() == chat_id)
+                {
+                    handle_callback(&client, &bot_token, &ignored_file, query).await;
+                }
+                continue;
+            }
+
+            let Some(message) = update.message else {
+                continue;
+            };
+            if message.chat.id.to_string() != chat_id {
+                continue;
+            }
+            if let Some(command) = message.text.as_deref().and_then(Command::parse)
+                && commands.send((profile, command)).await.is_err()
+            {
+                // The scan loop has stopped, so there's nobody left to serve.
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn callback_data_round_trips() {
+        for action in [AdAction::Interested, AdAction::Ignore] {
+            let data = action.callback_data("3170997111");
+            assert_eq!(AdAction::parse(&data), Some((action, "3170997111")));
+        }
+        assert_eq!(AdAction::parse("done"), None);
+        assert_eq!(AdAction::parse("ignore:"), None);
+    }
+}
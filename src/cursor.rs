@@ -0,0 +1,27 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs::{read_to_string, write},
+};
+
+use serde_json::{from_str, to_string_pretty};
+
+/// Loads the ID of the top ad each search showed in its last run, keyed by search name.
+///
+/// If the file does not exist or contains invalid data, it returns an empty map,
+/// and every search falls back to stopping at the first already-seen ad.
+pub fn load_cursors(path: &str) -> BTreeMap<String, String> {
+    match read_to_string(path) {
+        Ok(content) => from_str(&content).unwrap_or_else(|e| {
+            log!("Fehler beim Parsen der Datei {}: {}", path, e);
+            BTreeMap::new()
+        }),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+/// Saves the top ad IDs of all searches to a JSON file.
+pub fn save_cursors(path: &str, cursors: &BTreeMap<String, String>) -> Result<(), Box<dyn Error>> {
+    write(path, to_string_pretty(cursors)?)?;
+    Ok(())
+}
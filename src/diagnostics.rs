@@ -0,0 +1,109 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+};
+
+use serde::Serialize;
+use serde_json::to_string;
+
+use crate::{Ad, seen::unix_now};
+
+/// Why a new ad was not delivered in this run.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub enum SkipReason {
+    /// The title matched a scam keyword and `ScamHandling::Skip` is configured.
+    FilteredKeyword,
+    /// The ad didn't pass the shipping filter.
+    FilteredShipping,
+    /// The ad is a paid promotion and `SKIP_PROMOTED` is set.
+    FilteredPromoted,
+    /// The ad was viewed more often than `MAX_VIEWS`.
+    FilteredViews,
+    /// The condition isn't one of `ALLOWED_CONDITIONS`.
+    FilteredCondition,
+    /// The price is negotiable and only fixed-price ads are wanted.
+    FilteredNegotiable,
+    /// The photo was already announced with another ad and `DEDUP_BY_IMAGE` is set.
+    DuplicateImage,
+    /// The title is near-identical to that of another ad of this run and `COLLAPSE_SIMILAR_TITLES`
+    /// is set.
+    SimilarTitle,
+    /// The seller has fewer or more active listings than allowed.
+    FilteredSeller,
+    /// The seller is on `BLOCKED_SELLERS`.
+    BlockedSeller,
+    /// The seller was notified about less than `SELLER_MIN_INTERVAL_SECS` ago, so the ad waits
+    /// in the seller queue.
+    SellerQueued,
+    /// The title didn't match the wishlist closely enough.
+    FilteredWishlist,
+    /// The first-run limit or `--limit` was reached before the ad's turn.
+    CapReached,
+    /// Every notifier failed to deliver the ad.
+    SendFailed,
+    /// Sending was stopped after too many failed deliveries in a row.
+    CircuitOpen,
+}
+
+/// One line of the diagnostics file.
+#[derive(Serialize)]
+struct DiagnosticsEntry<'a> {
+    /// When the decision was made, in seconds since the Unix epoch.
+    time: u64,
+    /// The ID of the ad.
+    id: &'a str,
+    /// The title of the ad.
+    title: &'a str,
+    /// Why the ad was not delivered.
+    reason: SkipReason,
+    /// Further details, such as the matching keyword.
+    detail: &'a str,
+}
+
+/// Records why ads were skipped or failed, one JSON object per line.
+///
+/// Disabled unless `--diagnose` is given, in which case nothing is written at all.
+pub struct Diagnostics {
+    /// The file entries are appended to, if enabled.
+    file: Option<File>,
+}
+
+impl Diagnostics {
+    /// Opens the diagnostics file for appending, or creates a disabled recorder if `path` is `None`.
+    pub fn open(path: Option<&str>) -> Self {
+        let file =
+            path.and_then(
+                |path| match OpenOptions::new().create(true).append(true).open(path) {
+                    Ok(file) => {
+                        log!("Diagnose aktiv, Gründe werden in {} protokolliert.", path);
+                        Some(file)
+                    }
+                    Err(e) => {
+                        log!("Fehler beim Öffnen der Datei {}: {}", path, e);
+                        None
+                    }
+                },
+            );
+        Self { file }
+    }
+
+    /// Records why an ad was not delivered.
+    pub fn record(&mut self, ad: &Ad, reason: SkipReason, detail: &str) {
+        let Some(file) = &mut self.file else {
+            return;
+        };
+        let entry = DiagnosticsEntry {
+            time: unix_now(),
+            id: &ad.id,
+            title: &ad.title,
+            reason,
+            detail,
+        };
+        let result = to_string(&entry)
+            .map_err(|e| e.to_string())
+            .and_then(|line| writeln!(file, "{}", line).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            log!("Fehler beim Schreiben der Diagnose: {}", e);
+        }
+    }
+}
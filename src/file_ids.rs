@@ -0,0 +1,135 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fs::{read_to_string, write},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string_pretty};
+
+/// A photo Telegram already has, stored under the image URL it was sent from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct StoredFileId {
+    /// The image URL the photo was sent or uploaded from.
+    url: String,
+    /// The `file_id` Telegram returned for it.
+    file_id: String,
+    /// When Telegram returned it.
+    stored_at: DateTime<Utc>,
+}
+
+/// The Telegram `file_id`s of recently sent photos, oldest first, see `REUSE_FILE_IDS`.
+///
+/// A `file_id` only works for the bot it was issued to, so every profile keeps its own.
+#[derive(Debug, Default)]
+pub struct FileIds {
+    /// The stored photos, oldest first.
+    entries: VecDeque<StoredFileId>,
+    /// Whether entries were stored or forgotten since loading or the last save.
+    changed: bool,
+}
+
+impl FileIds {
+    /// Loads the stored `file_id`s from `path`.
+    ///
+    /// If the file does not exist or contains invalid data, it starts with an empty list.
+    pub fn load(path: &str) -> Self {
+        let entries = match read_to_string(path) {
+            Ok(content) => from_str(&content).unwrap_or_else(|e| {
+                log!("Fehler beim Parsen der Datei {}: {}", path, e);
+                VecDeque::new()
+            }),
+            Err(_) => VecDeque::new(),
+        };
+        Self {
+            entries,
+            changed: false,
+        }
+    }
+
+    /// Saves the stored `file_id`s to a JSON file, unless nothing changed since the last save.
+    pub fn save(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+        if !self.changed {
+            return Ok(());
+        }
+        write(path, to_string_pretty(&self.entries)?)?;
+        self.changed = false;
+        Ok(())
+    }
+
+    /// Looks up the `file_id` of a photo sent from this URL.
+    ///
+    /// # Returns
+    /// The `file_id`, or `None` if the image wasn't sent before or its entry is older than `ttl`.
+    pub fn get(&self, url: &str, now: DateTime<Utc>, ttl: Duration) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| entry.url == url && now - entry.stored_at < ttl)
+            .map(|entry| entry.file_id.as_str())
+    }
+
+    /// Stores the `file_id` of a sent photo, dropping expired entries and the oldest ones
+    /// beyond `max_entries`.
+    pub fn insert(
+        &mut self,
+        url: &str,
+        file_id: &str,
+        now: DateTime<Utc>,
+        ttl: Duration,
+        max_entries: usize,
+    ) {
+        self.entries
+            .retain(|entry| entry.url != url && now - entry.stored_at < ttl);
+        self.entries.push_back(StoredFileId {
+            url: url.to_string(),
+            file_id: file_id.to_string(),
+            stored_at: now,
+        });
+        while self.entries.len() > max_entries {
+            self.entries.pop_front();
+        }
+        self.changed = true;
+    }
+
+    /// Forgets the `file_id` of a photo, e.g. after Telegram rejected it.
+    ///
+    /// # Returns
+    /// `true` if there was one.
+    pub fn remove(&mut self, url: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.url != url);
+        let removed = self.entries.len() != before;
+        self.changed |= removed;
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_current_file_ids_are_reused() {
+        let sent_at = Utc::now();
+        let ttl = Duration::days(30);
+        let url = "https://img.example.de/a.jpg";
+        let mut file_ids = FileIds::default();
+        file_ids.insert(url, "A1", sent_at, ttl, 10);
+        assert_eq!(
+            file_ids.get(url, sent_at + Duration::days(29), ttl),
+            Some("A1")
+        );
+        assert_eq!(file_ids.get(url, sent_at + Duration::days(30), ttl), None);
+
+        // Sending the photo again replaces its file_id.
+        file_ids.insert(url, "A2", sent_at, ttl, 10);
+        assert_eq!(file_ids.get(url, sent_at, ttl), Some("A2"));
+        assert_eq!(file_ids.entries.len(), 1);
+
+        // A file_id Telegram rejected is forgotten, so the next send uploads the photo again.
+        assert!(file_ids.remove(url));
+        assert_eq!(file_ids.get(url, sent_at, ttl), None);
+        assert!(!file_ids.remove(url));
+    }
+}
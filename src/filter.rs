@@ -0,0 +1,152 @@
+use strsim::jaro_winkler;
+
+/// Checks whether a title contains any of the given keywords.
+///
+/// Matching is case-insensitive and substring-based, so "iphone" matches
+/// "Neues iPhone 15 zu verschenken".
+///
+/// # Returns
+/// The first matching keyword, or `None` if none of them occurs in the title.
+pub fn matches_any_keyword<'a>(title: &str, keywords: &[&'a str]) -> Option<&'a str> {
+    let title = title.to_lowercase();
+    keywords
+        .iter()
+        .copied()
+        .find(|keyword| !keyword.is_empty() && title.contains(&keyword.to_lowercase()))
+}
+
+/// What happens to ads whose title matches a scam keyword.
+// Only the variant chosen in the configuration is ever constructed.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScamHandling {
+    /// Don't send the ad at all, but mark it as seen so it doesn't come back.
+    Skip,
+    /// Send the ad with a warning label.
+    Warn,
+}
+
+/// What happens to ads whose price is marked "VB" (Verhandlungsbasis, negotiable).
+// Only the variant chosen in the configuration is ever constructed.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NegotiableHandling {
+    /// Send the ad like any other.
+    Send,
+    /// Send the ad with a "verhandelbar" label.
+    Label,
+    /// Only send fixed-price ads; negotiable ones are marked as seen without being sent.
+    FixedPriceOnly,
+}
+
+/// Which ads are sent depending on whether they can be shipped.
+// Only the variant chosen in the configuration is ever constructed.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShippingFilter {
+    /// Send every ad.
+    Any,
+    /// Skip ads marked "Nur Abholung", but keep those without a shipping indicator.
+    ShippableOrUnknown,
+    /// Only send ads marked "Versand möglich".
+    ShippableOnly,
+}
+
+impl ShippingFilter {
+    /// Checks whether an ad with the given shipping availability passes the filter.
+    pub fn allows(self, shipping: Option<bool>) -> bool {
+        match self {
+            ShippingFilter::Any => true,
+            ShippingFilter::ShippableOrUnknown => shipping != Some(false),
+            ShippingFilter::ShippableOnly => shipping == Some(true),
+        }
+    }
+}
+
+/// Scores a title against every wishlist entry and returns the best match.
+///
+/// Each entry is compared with every run of the same number of consecutive words in
+/// the title using the Jaro-Winkler similarity, so "kinderfahrad" still matches
+/// "Kinderfahrrad 20 Zoll" and multi-word entries match anywhere in the title.
+///
+/// # Returns
+/// The best-matching entry together with its score between 0.0 and 1.0,
+/// or `None` if the wishlist is empty.
+pub fn best_wishlist_match<'a>(title: &str, wishlist: &[&'a str]) -> Option<(&'a str, f64)> {
+    let title = title.to_lowercase();
+    let words: Vec<&str> = title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    wishlist
+        .iter()
+        .copied()
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            let entry_lower = entry.to_lowercase();
+            let entry_words = entry_lower.split_whitespace().count().max(1);
+            let score = words
+                .windows(entry_words.min(words.len()).max(1))
+                .map(|window| jaro_winkler(&entry_lower, &window.join(" ")))
+                .fold(0.0, f64::max);
+            (entry, score)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Checks whether two titles are near-identical, e.g. the same item posted several times.
+///
+/// Both titles are compared case-insensitively with the Jaro-Winkler similarity, ignoring
+/// punctuation and extra spaces, so "Umzugskartons!" and "umzugskartons" are the same.
+///
+/// # Arguments
+/// * `threshold` - The least similarity between 0.0 and 1.0 that counts as similar.
+pub fn titles_similar(a: &str, b: &str, threshold: f64) -> bool {
+    let normalize = |title: &str| {
+        title
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    jaro_winkler(&normalize(a), &normalize(b)) >= threshold
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wishlist_matches_misspelled_words() {
+        let (entry, score) =
+            best_wishlist_match("Kinderfahrrad 20 Zoll", &["sofa", "kinderfahrad"]).unwrap();
+        assert_eq!(entry, "kinderfahrad");
+        assert!(score > 0.9);
+    }
+
+    #[test]
+    fn wishlist_matches_multi_word_entries() {
+        let (entry, score) =
+            best_wishlist_match("Verschenke Ikea Billy Regal weiß", &["billy regal"]).unwrap();
+        assert_eq!(entry, "billy regal");
+        assert!(score > 0.99);
+    }
+
+    #[test]
+    fn bulk_postings_have_similar_titles() {
+        assert!(titles_similar("Umzugskartons", "  umzugskartons!", 0.95));
+        assert!(titles_similar(
+            "Umzugskartons zu verschenken",
+            "Umzugskarton zu verschenken",
+            0.95
+        ));
+        assert!(!titles_similar("Umzugskartons", "Sofa 3-Sitzer", 0.8));
+    }
+
+    #[test]
+    fn empty_wishlist_has_no_match() {
+        assert!(best_wishlist_match("Sofa", &[]).is_none());
+    }
+}
@@ -0,0 +1,33 @@
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    fs::{read_to_string, write},
+};
+
+use serde_json::{from_str, to_string_pretty};
+
+/// Loads the IDs of ads dismissed with the "Ignorieren" button.
+///
+/// If the file does not exist or contains invalid data, it returns an empty set.
+pub fn load_ignored(path: &str) -> BTreeSet<String> {
+    match read_to_string(path) {
+        Ok(content) => from_str(&content).unwrap_or_else(|e| {
+            log!("Fehler beim Parsen der Datei {}: {}", path, e);
+            BTreeSet::new()
+        }),
+        Err(_) => BTreeSet::new(),
+    }
+}
+
+/// Adds an ad to the ignore list in `path`.
+///
+/// # Returns
+/// `true` if the ad wasn't ignored before, or an error if the file couldn't be written.
+pub fn add_ignored(path: &str, ad_id: &str) -> Result<bool, Box<dyn Error>> {
+    let mut ignored = load_ignored(path);
+    if !ignored.insert(ad_id.to_string()) {
+        return Ok(false);
+    }
+    write(path, to_string_pretty(&ignored)?)?;
+    Ok(true)
+}
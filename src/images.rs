@@ -0,0 +1,147 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fs::{read_to_string, write},
+};
+
+use serde_json::{from_str, to_string_pretty};
+
+use crate::scrape::ImageResolution;
+
+/// The images of recently notified ads, oldest first, to catch the same item relisted under another title.
+///
+/// Only the base URL is kept, without the `?rule=` resolution suffix.
+#[derive(Debug, Default)]
+pub struct RecentImages {
+    /// The base image URLs, oldest first.
+    urls: VecDeque<String>,
+}
+
+impl RecentImages {
+    /// Loads the recent images from `path`.
+    ///
+    /// If the file does not exist or contains invalid data, it starts with an empty list.
+    pub fn load(path: &str) -> Self {
+        let urls = match read_to_string(path) {
+            Ok(content) => from_str(&content).unwrap_or_else(|e| {
+                log!("Fehler beim Parsen der Datei {}: {}", path, e);
+                VecDeque::new()
+            }),
+            Err(_) => VecDeque::new(),
+        };
+        Self { urls }
+    }
+
+    /// Saves the recent images to a JSON file.
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        write(path, to_string_pretty(&self.urls)?)?;
+        Ok(())
+    }
+
+    /// Checks whether an ad with this image was notified about recently.
+    pub fn contains(&self, image_url: &str) -> bool {
+        let base_url = base_image_url(image_url);
+        self.urls.iter().any(|url| url == base_url)
+    }
+
+    /// Remembers the image of a notified ad, dropping the oldest ones beyond `max_entries`.
+    pub fn insert(&mut self, image_url: &str, max_entries: usize) {
+        let base_url = base_image_url(image_url);
+        // Move a known image to the back, so it counts as recent again.
+        self.urls.retain(|url| url != base_url);
+        self.urls.push_back(base_url.to_string());
+        while self.urls.len() > max_entries {
+            self.urls.pop_front();
+        }
+    }
+}
+
+/// Strips the resolution rule from an image URL, so all sizes of a photo compare equal.
+pub fn base_image_url(image_url: &str) -> &str {
+    image_url.split('?').next().unwrap_or(image_url)
+}
+
+/// Ranks the resolution of an image URL by its rule, higher for larger images.
+///
+/// URLs without a known rule rank lowest, so any sized variant is preferred over them.
+fn resolution_rank(image_url: &str) -> usize {
+    let rule = image_url.split_once("?rule=").map_or("", |(_, rule)| rule);
+    [
+        ImageResolution::Thumbnail,
+        ImageResolution::Medium,
+        ImageResolution::Full,
+    ]
+    .iter()
+    .position(|resolution| resolution.rule() == rule)
+    .map_or(0, |position| position + 1)
+}
+
+/// Drops the images of a gallery that show the same photo as an earlier one in another size,
+/// before they are sent as one media group.
+///
+/// # Returns
+/// One URL per photo, in the order the photos first appear, each in the highest resolution listed.
+// Not called yet: the notifiers send one photo per ad until galleries are sent as media groups.
+#[allow(dead_code)]
+pub fn dedup_gallery(image_urls: &[String]) -> Vec<String> {
+    let mut gallery: Vec<&str> = Vec::new();
+    for url in image_urls {
+        match gallery
+            .iter_mut()
+            .find(|kept| base_image_url(kept) == base_image_url(url))
+        {
+            Some(kept) if resolution_rank(url) > resolution_rank(kept) => *kept = url,
+            Some(_) => {}
+            None => gallery.push(url),
+        }
+    }
+    gallery.into_iter().map(str::to_string).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_resolutions_of_a_photo_match() {
+        assert_eq!(
+            base_image_url("https://img.example.de/a.jpg?rule=$_59.AUTO"),
+            "https://img.example.de/a.jpg"
+        );
+        assert_eq!(
+            base_image_url("https://img.example.de/a.jpg"),
+            "https://img.example.de/a.jpg"
+        );
+
+        let mut images = RecentImages::default();
+        images.insert("https://img.example.de/a.jpg?rule=$_59.AUTO", 10);
+        assert!(images.contains("https://img.example.de/a.jpg?rule=$_2.AUTO"));
+        assert!(images.contains("https://img.example.de/a.jpg"));
+        assert!(!images.contains("https://img.example.de/b.jpg?rule=$_59.AUTO"));
+
+        // The same photo in another resolution is one entry, not two.
+        images.insert("https://img.example.de/a.jpg?rule=$_57.AUTO", 10);
+        assert_eq!(images.urls.len(), 1);
+    }
+
+    #[test]
+    fn a_gallery_keeps_the_largest_size_of_each_photo() {
+        let gallery = [
+            "https://img.example.de/a.jpg?rule=$_57.AUTO",
+            "https://img.example.de/b.jpg?rule=$_2.AUTO",
+            "https://img.example.de/a.jpg?rule=$_59.AUTO",
+            "https://img.example.de/c.jpg",
+            "https://img.example.de/a.jpg?rule=$_2.AUTO",
+            "https://img.example.de/c.jpg?rule=$_57.AUTO",
+        ]
+        .map(str::to_string);
+        assert_eq!(
+            dedup_gallery(&gallery),
+            [
+                "https://img.example.de/a.jpg?rule=$_59.AUTO",
+                "https://img.example.de/b.jpg?rule=$_2.AUTO",
+                "https://img.example.de/c.jpg?rule=$_57.AUTO",
+            ]
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use crate::POSTAL_CODE_COORDINATES;
+
+/// Extracts the five-digit German postal code from a location text like "04105 Leipzig".
+pub fn postal_code(location: &str) -> Option<&str> {
+    location
+        .split(|c: char| !c.is_ascii_digit())
+        .find(|part| part.len() == 5)
+}
+
+/// The parts of a location text like "04105 Leipzig - Zentrum-Nord (3 km)".
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Place<'a> {
+    /// The postal code, e.g. "04105".
+    pub postal_code: Option<&'a str>,
+    /// The town, e.g. "Leipzig".
+    pub town: Option<&'a str>,
+    /// The district (Ortsteil), e.g. "Zentrum-Nord", if the ad names one.
+    pub district: Option<&'a str>,
+}
+
+/// Splits a location text into postal code, town and district.
+///
+/// Kleinanzeigen puts the district after the town, separated by " - ", and result cards may
+/// add the distance in parentheses, which is dropped. Any of the parts may be missing.
+pub fn parse_place(location: &str) -> Place<'_> {
+    let location = match location.rfind(" (") {
+        Some(start) if location.trim_end().ends_with("km)") => &location[..start],
+        _ => location,
+    };
+    let (main, district) = match location.split_once(" - ") {
+        Some((main, district)) => (main, Some(district.trim())),
+        None => (location, None),
+    };
+    let main = main.trim();
+    let (postal_code, town) = match main.split_once(' ') {
+        Some((code, town)) if is_postal_code(code) => (Some(code), Some(town.trim())),
+        _ if is_postal_code(main) => (Some(main), None),
+        _ => (None, Some(main)),
+    };
+    Place {
+        postal_code,
+        town: town.filter(|town| !town.is_empty()),
+        district: district.filter(|district| !district.is_empty()),
+    }
+}
+
+/// Checks whether a text is a five-digit postal code.
+fn is_postal_code(text: &str) -> bool {
+    text.len() == 5 && text.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Looks up approximate coordinates for an ad's location via its postal code.
+///
+/// # Returns
+/// The `(latitude, longitude)` pair from `POSTAL_CODE_COORDINATES`, or `None`
+/// if the location has no postal code or it isn't in the table.
+pub fn coordinates_for(location: &str) -> Option<(f64, f64)> {
+    let code = postal_code(location)?;
+    POSTAL_CODE_COORDINATES
+        .iter()
+        .find(|(known_code, _, _)| *known_code == code)
+        .map(|&(_, latitude, longitude)| (latitude, longitude))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locations_are_split_into_their_parts() {
+        assert_eq!(
+            parse_place("04105 Leipzig - Zentrum-Nord (3 km)"),
+            Place {
+                postal_code: Some("04105"),
+                town: Some("Leipzig"),
+                district: Some("Zentrum-Nord"),
+            }
+        );
+        assert_eq!(
+            parse_place("04105 Leipzig"),
+            Place {
+                postal_code: Some("04105"),
+                town: Some("Leipzig"),
+                district: None,
+            }
+        );
+        assert_eq!(
+            parse_place("04105"),
+            Place {
+                postal_code: Some("04105"),
+                ..Place::default()
+            }
+        );
+        assert_eq!(
+            parse_place("Leipzig"),
+            Place {
+                town: Some("Leipzig"),
+                ..Place::default()
+            }
+        );
+    }
+}
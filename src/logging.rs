@@ -0,0 +1,171 @@
+use std::{
+    fmt::Arguments,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+};
+
+use serde::Serialize;
+use serde_json::to_string;
+
+use crate::seen::unix_now;
+
+/// Writes a log line to stderr, as plain text or as JSON depending on the configured format.
+///
+/// Prefix the message with `ad = <id>;` to attach the ID of the ad it is about.
+macro_rules! log {
+    (ad = $ad_id:expr; $($arg:tt)*) => {
+        $crate::logging::write(Some($ad_id), format_args!($($arg)*))
+    };
+    ($($arg:tt)*) => {
+        $crate::logging::write(None, format_args!($($arg)*))
+    };
+}
+
+/// Like `log!`, but only writes the line if debug output is enabled, see `set_debug`.
+macro_rules! debug {
+    (ad = $ad_id:expr; $($arg:tt)*) => {
+        $crate::logging::write_debug(Some($ad_id), format_args!($($arg)*))
+    };
+    ($($arg:tt)*) => {
+        $crate::logging::write_debug(None, format_args!($($arg)*))
+    };
+}
+
+/// How log lines are written to stderr.
+// Only the variant chosen in the configuration is ever constructed.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// The message text only, for reading in a terminal or the journal.
+    Text,
+    /// One JSON object per line with the message and its context, for log collectors.
+    Json,
+}
+
+/// Whether `LogFormat::Json` is active.
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Whether lines written with `debug!` are shown.
+static DEBUG: AtomicBool = AtomicBool::new(false);
+
+/// The number of scans started so far, to tell several scans in the same second apart.
+static RUN_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// What the bot is working on, attached to every JSON log line.
+static CONTEXT: Mutex<Context> = Mutex::new(Context {
+    run_id: None,
+    search: None,
+});
+
+/// The context of the current log lines.
+struct Context {
+    /// Identifies the current scan, e.g. "1718000000-0".
+    run_id: Option<String>,
+    /// The name of the search being scanned.
+    search: Option<&'static str>,
+}
+
+/// One line of JSON log output.
+#[derive(Serialize)]
+struct LogLine<'a> {
+    /// When the line was written, in seconds since the Unix epoch.
+    time: u64,
+    /// "error", "warn" or "info", derived from how the message starts, or "debug".
+    level: &'static str,
+    /// The log message.
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    search: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ad_id: Option<&'a str>,
+}
+
+/// Selects the format of all following log lines.
+pub fn set_format(format: LogFormat) {
+    JSON.store(format == LogFormat::Json, Ordering::Relaxed);
+}
+
+/// Shows or hides the lines written with `debug!`.
+pub fn set_debug(enabled: bool) {
+    DEBUG.store(enabled, Ordering::Relaxed);
+}
+
+/// Starts a new scan, giving the following log lines a fresh run ID.
+pub fn start_run() {
+    let run_id = format!(
+        "{}-{}",
+        unix_now(),
+        RUN_COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let mut context = CONTEXT.lock().unwrap_or_else(|e| e.into_inner());
+    context.run_id = Some(run_id);
+    context.search = None;
+}
+
+/// Sets the search the following log lines are about, or `None` once it's done.
+pub fn set_search(search: Option<&'static str>) {
+    CONTEXT.lock().unwrap_or_else(|e| e.into_inner()).search = search;
+}
+
+/// Maps a message to a log level by its German prefix, e.g. "Fehler beim ..." or "Warnung: ...".
+fn level(message: &str) -> &'static str {
+    if message.starts_with("Fehler") {
+        "error"
+    } else if message.starts_with("Warnung") || message.starts_with('⚠') {
+        "warn"
+    } else {
+        "info"
+    }
+}
+
+/// Writes a log line to stderr. Use the `log!` macro instead of calling this directly.
+pub fn write(ad_id: Option<&str>, message: Arguments) {
+    emit(None, ad_id, message);
+}
+
+/// Writes a debug line to stderr if enabled. Use the `debug!` macro instead of calling this
+/// directly.
+pub fn write_debug(ad_id: Option<&str>, message: Arguments) {
+    if DEBUG.load(Ordering::Relaxed) {
+        emit(Some("debug"), ad_id, message);
+    }
+}
+
+/// Writes a line at `level`, or at the level derived from the message if `None`.
+fn emit(level_override: Option<&'static str>, ad_id: Option<&str>, message: Arguments) {
+    if !JSON.load(Ordering::Relaxed) {
+        eprintln!("{}", message);
+        return;
+    }
+
+    let message = message.to_string();
+    let context = CONTEXT.lock().unwrap_or_else(|e| e.into_inner());
+    let line = LogLine {
+        time: unix_now(),
+        level: level_override.unwrap_or_else(|| level(&message)),
+        message: &message,
+        run_id: context.run_id.as_deref(),
+        search: context.search,
+        ad_id,
+    };
+    match to_string(&line) {
+        Ok(json) => eprintln!("{}", json),
+        Err(_) => eprintln!("{}", message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_follow_the_message_prefix() {
+        assert_eq!(level("Fehler beim Senden: timeout"), "error");
+        assert_eq!(level("Warnung: Layoutänderung vermutet"), "warn");
+        assert_eq!(level("3 neue Anzeigen gefunden."), "info");
+    }
+}
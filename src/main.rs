@@ -1,32 +1,581 @@
+// Declared first, so the `log!` macro is available in every other module.
+#[macro_use]
+mod logging;
+
+mod bloom;
+mod breaker;
+mod cli;
+mod commands;
+mod cursor;
+mod diagnostics;
+mod file_ids;
+mod filter;
+mod ignored;
+mod images;
+mod location;
+mod messages;
+mod notifier;
+mod order;
+mod price_watch;
+mod profile;
+mod recovery;
+mod schedule;
+mod scrape;
+mod search;
+mod seeding;
+mod seen;
+mod sellers;
+mod store;
+mod summary;
+mod text;
+mod throttle;
+mod timezone;
+mod validate;
+
 use std::{
-    collections::{HashSet, VecDeque},
+    cell::Cell,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     error::Error,
-    fs::{read_to_string, write},
-    time::Duration,
+    fs::{remove_file, write},
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
-use reqwest::Client;
-use scraper::{Html, Selector};
+use chrono::{DateTime, NaiveTime, Utc};
+use futures::{StreamExt, stream};
+use reqwest::{
+    Client,
+    header::{HeaderMap, HeaderName, HeaderValue},
+};
 use serde::{Deserialize, Serialize};
-use serde_json::{from_slice, from_str, to_string_pretty};
-use tokio::{main, time::sleep};
+use serde_json::to_string_pretty;
+use tokio::{main, select, spawn, sync::mpsc, time::sleep};
+
+use crate::{
+    bloom::BloomFilter,
+    breaker::CircuitBreaker,
+    cli::CliArgs,
+    commands::{Command, listen_for_commands},
+    cursor::{load_cursors, save_cursors},
+    diagnostics::{Diagnostics, SkipReason},
+    filter::{
+        NegotiableHandling, ScamHandling, ShippingFilter, best_wishlist_match, matches_any_keyword,
+        titles_similar,
+    },
+    ignored::load_ignored,
+    images::{RecentImages, base_image_url},
+    logging::{LogFormat, set_debug, set_format, set_search, start_run},
+    messages::MessageIndex,
+    notifier::{
+        AnyNotifier, ChatUnreachable, Delivery, DiscordWebhookNotifier, ImageMode, MatrixNotifier,
+        Notifier, NtfyNotifier, PostedMessage, TelegramNotifier,
+    },
+    order::{NotificationOrder, sort_ads},
+    price_watch::check_price_drops,
+    profile::Profile,
+    recovery::{RunState, load_run_state, recovered, save_run_state},
+    schedule::Schedule,
+    scrape::{
+        BlockedError, ImageResolution, PageSource, Seller, scrape_ad, scrape_kleinanzeigen_page,
+    },
+    search::{Search, SortOrder},
+    seeding::{FirstRun, FirstRunMode, is_seeding, load_first_run, save_first_run},
+    seen::{
+        CorruptSeenFile, DedupStrategy, PruneMode, load_seen_ads, merge_seen_ads, prune_seen_ads,
+        read_seen_ads, save_seen_ads, unix_now,
+    },
+    sellers::{QueuedAd, SellerQueue},
+    store::{JsonFileStore, MemoryStore, RedisStore, SeenBackend, SeenStore},
+    summary::{DailyStats, load_daily_stats, save_daily_stats},
+    throttle::{DelayRamp, HostBudget, RateGovernor, StartupThrottle, Throttle},
+    timezone::TimeZone,
+    validate::validate_config,
+};
 
 // --- Configuration ---
 // IMPORTANT: Replace these with your actual token and chat ID
 const TELEGRAM_BOT_TOKEN: &str = "YOUR_TELEGRAM_BOT_TOKEN";
 const TELEGRAM_CHAT_ID: &str = "YOUR_GROUP_CHAT_ID";
 
-// The URL is now split to allow inserting the page number
-const KLEINANZEIGEN_BASE_URL: &str = "https://www.kleinanzeigen.de/s-zu-verschenken-tauschen";
-const KLEINANZEIGEN_URL_SUFFIX: &str = "/04105/c272l4257r10";
+// Optional: A Telegram chat for ads that none of their chats took, e.g. Some("123456789") for your own
+// private chat with the bot. It's tried once the regular sends have failed after their retries;
+// the ad counts as delivered and is marked as seen if the fallback chat receives it.
+const FALLBACK_CHAT_ID: Option<&str> = None;
+
+// Telegram allows roughly 20 messages per minute to the same group.
+// Sends beyond this ceiling wait until the oldest message leaves the one-minute window.
+const TELEGRAM_MAX_MESSAGES_PER_MINUTE: usize = 20;
+
+// Text messages (ads without a photo, replies) link to the ad, which Telegram expands into a preview.
+// Set to true to send them without the preview.
+const DISABLE_LINK_PREVIEW: bool = false;
+
+// Send a map pin after each ad whose postal code is listed in POSTAL_CODE_COORDINATES.
+// Ads with unknown coordinates are sent without a pin.
+const SEND_LOCATION_PIN: bool = false;
+// Approximate coordinates per postal code as (postal code, latitude, longitude).
+// Add the postal codes around your search area here.
+const POSTAL_CODE_COORDINATES: &[(&str, f64, f64)] = &[
+    ("04103", 51.3406, 12.3883),
+    ("04105", 51.3503, 12.3672),
+    ("04107", 51.3301, 12.3724),
+    ("04109", 51.3397, 12.3646),
+];
+
+// How many ads are sent at the same time. All sends still share the per-chat rate limit above.
+// With more than 1, an ad whose send is retried may appear in the chat after the one below it;
+// 1 keeps the chat in strict NOTIFICATION_ORDER.
+const SEND_CONCURRENCY: usize = 1;
+
+// After this many ads in a row that couldn't be delivered on any channel (e.g. Telegram is down),
+// stop sending for the rest of the run. The remaining ads stay unseen and are sent in order next run.
+// `0` keeps trying every ad.
+const MAX_CONSECUTIVE_SEND_FAILURES: usize = 3;
+
+// Request budgets per host, shared by scraping and sending. Requests to a host start at least
+// `min_interval` apart, and at most `max_per_minute` of them per minute (`0` = no cap).
+// Hosts not listed here aren't limited. The budgets are logged at startup to help tuning them.
+const TELEGRAM_API_HOST: &str = "api.telegram.org";
+const HOST_BUDGETS: &[HostBudget] = &[
+    HostBudget {
+        host: "www.kleinanzeigen.de",
+        min_interval: Duration::from_secs(1),
+        max_per_minute: 30,
+    },
+    HostBudget {
+        host: TELEGRAM_API_HOST,
+        min_interval: Duration::from_secs(2),
+        max_per_minute: 0,
+    },
+];
+
+// Slows down large bursts of new ads: the first `fast_sends` follow the Telegram budget above,
+// then every further ad waits `step` longer than the previous one, up to `max_extra` on top.
+// A `step` of zero keeps the fixed interval.
+const SEND_DELAY_RAMP: DelayRamp = DelayRamp {
+    fast_sends: 5,
+    step: Duration::ZERO,
+    max_extra: Duration::from_secs(30),
+};
+
+// Drips ads out slowly for a while after the bot starts, e.g. when a restart after downtime finds a
+// backlog: right at startup every ad waits `extra` on top of the pace above, and the extra delay
+// shrinks evenly until it is gone after `window`. A `window` of zero disables it.
+const STARTUP_THROTTLE: StartupThrottle = StartupThrottle {
+    window: Duration::ZERO,
+    extra: Duration::from_secs(20),
+};
+
+// Upper bounds for honoring Telegram's `retry_after` on rate limits: a single wait may not exceed
+// MAX_RETRY_AFTER_SECS, and all waits for one message together may not exceed MAX_TOTAL_RETRY_WAIT_SECS.
+// If either is exceeded, the message counts as failed and the ad is retried on the next run.
+const MAX_RETRY_AFTER_SECS: u64 = 300;
+const MAX_TOTAL_RETRY_WAIT_SECS: u64 = 600;
+
+// A random extra wait of up to RETRY_JITTER on top of every `retry_after` of Telegram, Discord,
+// Matrix and ntfy, so several instances or chats limited at once don't all retry together.
+// `Duration::ZERO` retries exactly when asked.
+const RETRY_JITTER: Duration = Duration::from_millis(500);
+
+// How often a Telegram send is repeated after a rate limit (429) before the message counts as failed.
+// Photos that still fail for another reason fall back to a text message.
+const TELEGRAM_MAX_RETRIES: u32 = 2;
+
+// Optional: Route all Telegram API calls through a SOCKS5 proxy, e.g. Some("socks5h://127.0.0.1:9050"),
+// for networks where Telegram is blocked. Scraping Kleinanzeigen is not affected.
+// Requires building with `cargo build --release --features socks`.
+const TELEGRAM_SOCKS5_PROXY: Option<&str> = None;
+
+// Optional: Also post every new ad to a Discord channel via a webhook URL,
+// e.g. Some("https://discord.com/api/webhooks/<id>/<token>")
+const DISCORD_WEBHOOK_URL: Option<&str> = None;
+
+// Optional: Also post every new ad to a Matrix room. Set the homeserver, e.g. Some("https://matrix.org"),
+// the access token of the posting account and the room ID, e.g. "!abc123:matrix.org".
+// The account must already have joined the room.
+const MATRIX_HOMESERVER: Option<&str> = None;
+const MATRIX_ACCESS_TOKEN: &str = "YOUR_MATRIX_ACCESS_TOKEN";
+const MATRIX_ROOM_ID: &str = "YOUR_MATRIX_ROOM_ID";
+
+// Optional: Also push every new ad to an ntfy topic, e.g. Some("kleinanzeigen-leipzig-4711").
+// Anyone who knows the topic name can read it, so pick one that's hard to guess.
+// With ntfy (or another channel) configured, the Telegram placeholders may be left as they are.
+const NTFY_SERVER: &str = "https://ntfy.sh";
+const NTFY_TOPIC: Option<&str> = None;
+
+// The searches to scan. Each URL is split to allow inserting the page number. With an empty
+// `url_suffix`, `base_url` holds the whole search URL and the bot finds the split itself, e.g. for
+// "https://www.kleinanzeigen.de/s-04105/c0l4257r10" (all categories) or ".../s-fahrrad/k0" (keyword).
+// Alternatively, `keyword: Some(search::KeywordSearch { keyword: "fahrrad", postal_code: Some("04105"),
+// location_id: Some(4257), radius_km: Some(10) })` builds the URL from a search term; `base_url` and
+// `url_suffix` stay empty then. The radius needs the location ID, the number after "l" in a copied
+// search URL like ".../c0l4257r10".
+// `chat_ids` routes a search's ads to specific Telegram chats, e.g. &["-1001234567890"];
+// leave it empty to use TELEGRAM_CHAT_ID. An ad found by several searches is sent once to all their chats.
+// `max_pages: Some(n)` overrides MAX_PAGES_TO_SCAN for a search.
+// `prefix: Some("🛋️")` puts an emoji or short text in front of the caption to tell the searches apart.
+// `poll_interval_secs: Some(120)` scans a busy search more often in watch mode, `Some(1800)` a quiet
+// one less often, instead of every POLL_INTERVAL_SECS.
+const SEARCHES: &[Search] = &[Search {
+    name: "Zu verschenken",
+    base_url: "https://www.kleinanzeigen.de/s-zu-verschenken-tauschen",
+    url_suffix: "/04105/c272l4257r10",
+    keyword: None,
+    chat_ids: &[],
+    max_pages: None,
+    prefix: None,
+    poll_interval_secs: None,
+}];
+
+// Several independent bots run from this one process, e.g. for friends with their own token, chat and
+// searches. The profiles are scanned one after another. Each profile's state files get its name in
+// front, e.g. "anna_seen_ads.json"; the profile with an empty name keeps the plain file names.
+// Discord, Matrix and ntfy only receive the ads of the first profile.
+// `--profile NAME` restricts a run, `--export-seen` and `--import-seen` to one of them.
+const PROFILES: &[Profile] = &[Profile {
+    name: "",
+    telegram_bot_token: TELEGRAM_BOT_TOKEN,
+    telegram_chat_id: TELEGRAM_CHAT_ID,
+    searches: SEARCHES,
+    watched_ads: WATCHED_ADS,
+}];
+
+// The caption prefix for searches without their own `prefix`, e.g. Some("🎁").
+const CAPTION_PREFIX: Option<&str> = None;
+
+// Cut titles longer than this many characters in the caption, with an ellipsis, e.g. Some(80), so
+// very long ones don't wrap over many lines. The full title is still on the linked page.
+const MAX_TITLE_CHARS: Option<usize> = None;
+
+// Show how long ago an ad was posted in the caption, e.g. "Eingestellt: vor 12 Minuten", computed
+// from the posting time. Ads whose time can't be read, or that only show a date, get the text from
+// the page instead, e.g. "Gestern, 18:05" or "12.10.2026".
+const SHOW_FRESHNESS: bool = false;
+
+// A safety limit on how many result pages are scanned per search and run.
+// A search's `max_pages` overrides it, e.g. more for busy searches on the first run, fewer for quiet ones.
+const MAX_PAGES_TO_SCAN: u32 = 10;
+
+// A result page that arrives cut off or with invalid UTF-8 is fetched again this many times,
+// waiting MALFORMED_PAGE_RETRY_DELAY_SECS seconds in between, before the search fails for this run.
+const MALFORMED_PAGE_RETRIES: u32 = 2;
+const MALFORMED_PAGE_RETRY_DELAY_SECS: u64 = 5;
+
+// With a name ending in ".gz", e.g. "seen_ads.json.gz", the file is stored gzip-compressed, which
+// shrinks it to a fraction for many large searches. A file under the other name is picked up once
+// after switching. COMPACT_SEEN_ADS drops the indentation of the uncompressed file.
 const SEEN_ADS_FILE: &str = "seen_ads.json";
+const COMPACT_SEEN_ADS: bool = false;
+
+// If SEEN_ADS_FILE exists but can't be parsed, the run is aborted instead of announcing every live
+// ad again; `--force-reseed` backs it up and starts over. With this set, the first aborted run
+// also posts an alert to the chat. CORRUPT_ALERT_FILE marks it as sent until the file loads again,
+// so later runs and watch polls don't repeat it.
+const NOTIFY_CORRUPT_SEEN_FILE: bool = false;
+const CORRUPT_ALERT_FILE: &str = "corrupt_alert_sent";
+// The top ad of each search is stored here, so the next run knows where the last one began.
+// Paging stops at that ad even if its entry was pruned from the seen list.
+const CURSOR_FILE: &str = "cursors.json";
+
+// Skip ads whose photo was already notified about recently, which catches items relisted
+// under a slightly different title. The last MAX_RECENT_IMAGES photos are kept in RECENT_IMAGES_FILE.
+const DEDUP_BY_IMAGE: bool = false;
+const RECENT_IMAGES_FILE: &str = "recent_images.json";
+const MAX_RECENT_IMAGES: usize = 500;
+
+// Remember the Telegram file_id of every photo sent, so an image sent again (relists, retries) is
+// passed by reference instead of Telegram fetching or the bot uploading it once more. The newest
+// MAX_FILE_IDS entries younger than FILE_ID_TTL_DAYS are kept in FILE_ID_FILE, one per profile.
+// If Telegram rejects a stored file_id, it is forgotten and the photo is sent by its URL.
+const REUSE_FILE_IDS: bool = false;
+const FILE_ID_FILE: &str = "file_ids.json";
+const MAX_FILE_IDS: usize = 2000;
+const FILE_ID_TTL_DAYS: i64 = 30;
+
+// Collapse ads of one run whose titles are near-identical, e.g. "Umzugskartons" posted five times:
+// only the first is sent, labelled "(+N ähnliche)", and the others are marked as seen once it was
+// delivered. If it isn't, they stay unseen for the next run.
+// Titles are compared with the Jaro-Winkler similarity (0.0 to 1.0) ignoring case and punctuation,
+// e.g. Some(0.95). Only ads going to the same chats are collapsed. `None` sends every ad.
+const COLLAPSE_SIMILAR_TITLES: Option<f64> = None;
+
+// What makes two ads the same: `DedupStrategy::IdOnly` compares ad IDs, `IdAndUrl` the ID together
+// with the URL, and `ContentHash` the normalized title and location, which also catches reposts
+// under a new ID. After switching, the seen list is rebuilt by marking the live ads as seen without
+// sending them, like `FirstRunMode::StartLive` (the old file is kept as `.bak`).
+const DEDUP_STRATEGY: DedupStrategy = DedupStrategy::IdOnly;
+
+// Where the seen ads are kept: `SeenBackend::JsonFile` uses SEEN_ADS_FILE, `SeenBackend::Redis`
+// a shared Redis instance at REDIS_URL, so several instances with overlapping searches don't
+// notify about each other's finds. In Redis every ad is its own key under REDIS_KEY_PREFIX and
+// expires after REDIS_SEEN_TTL_DAYS days instead of being pruned, so PRUNE_MODE, MAX_SEEN_ADS,
+// MAX_SEEN_AGE_DAYS and SEEN_TTL_DAYS don't apply (`--validate-config` rejects `PruneMode::MaxAge`
+// and SEEN_TTL_DAYS with it). If Redis is unreachable, the searches fail and keep their cursors,
+// so nothing is sent until it is back.
+// `--export-seen`/`--import-seen` always use SEEN_ADS_FILE.
+const SEEN_BACKEND: SeenBackend = SeenBackend::JsonFile;
+const REDIS_URL: &str = "redis://127.0.0.1:6379/0";
+const REDIS_KEY_PREFIX: &str = "kleinanzeigen:seen:";
+const REDIS_SEEN_TTL_DAYS: u64 = 30;
+
+// How the seen-ads list is pruned: `PruneMode::Count` keeps the newest MAX_SEEN_ADS entries,
+// `PruneMode::MaxAge` drops entries older than MAX_SEEN_AGE_DAYS regardless of their number.
+const PRUNE_MODE: PruneMode = PruneMode::Count;
 const MAX_SEEN_ADS: usize = 1000;
+const MAX_SEEN_AGE_DAYS: u64 = 30;
+
+// On top of PRUNE_MODE, drop entries older than SEEN_TTL_DAYS even while the list is below
+// MAX_SEEN_ADS, e.g. `Some(90)`, which keeps the file small with many large searches. Entries of
+// ads still on the scanned result pages are kept, so expiry never announces a live ad again.
+// Entries only expire in runs that scanned every search of the profile without an error.
+const SEEN_TTL_DAYS: Option<u64> = None;
+
+// IDs pruned from the seen list are still remembered in a compact long-lived filter (EVER_SEEN_FILE),
+// so ads that stay online for months don't get re-sent. The filter is sized for EVER_SEEN_CAPACITY IDs;
+// in return, roughly one in 1/EVER_SEEN_FALSE_POSITIVE_RATE genuinely new ads is wrongly treated as seen.
+// Changing the sizing rebuilds the filter from the current seen list.
+// Off by default, as the false positives silently swallow new ads; skips caused by the filter alone
+// are logged when LOG_DEBUG is on.
+const ENABLE_EVER_SEEN_FILTER: bool = false;
+const EVER_SEEN_FILE: &str = "ever_seen.bloom";
+const EVER_SEEN_CAPACITY: usize = 100_000;
+const EVER_SEEN_FALSE_POSITIVE_RATE: f64 = 0.001;
+
+// What the first run does: `FirstRunMode::Backfill` sends the newest FIRST_RUN_LIMIT ads, silently
+// with SILENT_FIRST_RUN. `StartLive` only marks the ads on the first result page of every search and
+// the EXTRA_PAGES_AFTER_SEEN pages after it as seen and sends nothing, so later runs only notify
+// about ads posted after deployment.
+const FIRST_RUN_MODE: FirstRunMode = FirstRunMode::Backfill;
 const FIRST_RUN_LIMIT: usize = 25;
 
+// Marks the first run as started and, once every search got through at least once, as complete.
+// A first run that crashed or lost a search midway is continued by the next run, with its limit
+// and silence for the searches not seeded yet, instead of that run notifying about the rest of the
+// backlog. After FIRST_RUN_MAX_ATTEMPTS runs it completes anyway, so a search that keeps failing
+// doesn't keep the others seeding.
+const FIRST_RUN_FILE: &str = "first_run.json";
+const FIRST_RUN_MAX_ATTEMPTS: u32 = 5;
+
+// With `--diagnose`, the reason for every skipped or failed ad is appended here, one JSON object per line.
+const DIAGNOSTICS_FILE: &str = "diagnostics.jsonl";
+
+// Deliver the first-run backfill without notification sounds, so seeding the seen list
+// doesn't set off a burst of alerts. Later runs notify normally, unless ALWAYS_SILENT is set.
+const SILENT_FIRST_RUN: bool = true;
+const ALWAYS_SILENT: bool = false;
+
+// Scanning stops once a page contains an already-seen ad. New ads posted while scraping push older
+// ones down, so an unseen ad can slip from page N to page N+1 between two requests. Scanning
+// EXTRA_PAGES_AFTER_SEEN more pages catches such ads, at the cost of that many extra requests per run.
+// `0` stops right at the first page with a seen ad.
+const EXTRA_PAGES_AFTER_SEEN: u32 = 1;
+
+// The order search results are requested in. Stopping at the first seen ad is only reliable with
+// `SortOrder::Newest`; with `PriceAscending`, `PriceDescending` or `Unchanged` (the URL as configured)
+// new ads can sit behind seen ones and be skipped, so a warning is logged every run.
+// A "sortierung:" already contained in a search's base_url always wins.
+const SEARCH_SORT: SortOrder = SortOrder::Newest;
+
+// The headers every request to Kleinanzeigen carries. A browser-like User-Agent avoids being blocked,
+// and Accept-Language asks for German listings; adjust it for another locale, e.g. "en-US".
+// Further headers can be added as ("Name", "Wert").
+const SCRAPE_HEADERS: &[(&str, &str)] = &[
+    (
+        "User-Agent",
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36",
+    ),
+    ("Accept-Language", "de-DE"),
+    (
+        "Accept",
+        "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+    ),
+];
+
+// Clean up scraped titles: decode stray HTML entities (e.g. "&amp;"), strip control characters
+// and collapse whitespace. Titles are always HTML-escaped for Telegram afterwards.
+const SANITIZE_TITLES: bool = true;
+
+// Result cards occasionally lack the title link. With this set, such an ad is linked by its ID
+// (`/s-anzeige/<slug>/<id>`) instead of being dropped, taking its title from the heading or image.
+// Cards without an ID are always dropped, with a warning counting them.
+const FALLBACK_AD_LINKS: bool = true;
+
+// The image size scraped ads link to: `ImageResolution::Full`, `Medium`, or `Thumbnail` for
+// metered connections.
+const IMAGE_RESOLUTION: ImageResolution = ImageResolution::Full;
+// Fallback resolution rules: if Telegram can't use an image, these are tried in order
+// before falling back to a text message.
+const IMAGE_RESOLUTION_RULES: &[&str] = &["$_59.AUTO", "$_57.AUTO", "$_2.AUTO"];
+
+// Fetch the detail page of every ad about to be sent and show the start of its description,
+// which often tells the condition ("defekt", "neuwertig"). This costs one extra request per ad,
+// counted against HOST_BUDGETS. Ads whose page can't be fetched are sent without a description.
+const FETCH_DESCRIPTIONS: bool = false;
+// The maximum length of the description snippet, in characters.
+const DESCRIPTION_SNIPPET_CHARS: usize = 200;
+
+// A note appended to text messages sent without a photo, e.g. Some("(Kein Bild verfügbar)").
+// `None` keeps the caption identical to the one of photo messages.
+const NO_IMAGE_MARKER: Option<&str> = None;
+
+// Send the details as a text message first, followed by the photo without a caption, instead of
+// a single photo with caption. Screen readers handle the separate text message more reliably.
+const TEXT_BEFORE_PHOTO: bool = false;
+
+// How the image of an ad is posted: `Photo` lets Telegram fetch it by URL, which downscales large
+// images. `Document` sends it as a file in its original dimensions; since Telegram doesn't fetch
+// images as documents by URL, it is downloaded within the limits below and uploaded.
+const IMAGE_MODE: ImageMode = ImageMode::Photo;
+
+// Check every image URL with a HEAD request before sending it, and send the ad as text if it
+// doesn't answer with 2xx and an image content type, e.g. because a redirect ends on an error page.
+// Costs one extra request per ad, but avoids failed sendPhoto calls.
+const VALIDATE_IMAGE_URLS: bool = false;
+
+// If Telegram can't use an image in any resolution, download it and upload its bytes instead of
+// falling back to a text message. At most MAX_CONCURRENT_IMAGE_DOWNLOADS images are held in memory
+// at once, and a download is aborted once it exceeds MAX_IMAGE_DOWNLOAD_BYTES (Telegram takes up to 10 MB).
+const UPLOAD_PHOTO_FALLBACK: bool = false;
+const MAX_CONCURRENT_IMAGE_DOWNLOADS: usize = 2;
+const MAX_IMAGE_DOWNLOAD_BYTES: usize = 5 * 1024 * 1024;
+
+// Before such an upload, swap a WebP image for the JPEG rendition of Kleinanzeigen's image server,
+// since some older Telegram clients render WebP poorly. Costs one more download per WebP image.
+const CONVERT_WEBP_UPLOADS: bool = false;
+
+// Titles containing any of these keywords (case-insensitive) are treated as likely scam bait,
+// e.g. &["iphone", "playstation"]. With `ScamHandling::Skip` such ads are silently marked as seen,
+// with `ScamHandling::Warn` they are sent with a "⚠️ Möglicher Betrug" label.
+const SCAM_KEYWORDS: &[&str] = &[];
+const SCAM_HANDLING: ScamHandling = ScamHandling::Warn;
+
+// Only send ads whose title resembles an entry of this wishlist, e.g. &["kinderfahrrad", "billy regal"].
+// Titles are scored with the Jaro-Winkler similarity (0.0 to 1.0, misspellings are tolerated);
+// ads whose best score is below WISHLIST_MIN_SCORE are marked as seen without being sent.
+// The matching entry and score are shown in the message. An empty wishlist sends every ad.
+const WISHLIST: &[&str] = &[];
+const WISHLIST_MIN_SCORE: f64 = 0.85;
+
+// Wishlist matches scoring at least this much, e.g. Some(0.95), are top matches: their caption starts
+// with "🔥 TOP-TREFFER", and with PIN_TOP_MATCHES the bot silently pins their Telegram message in the
+// chat, which needs the bot to be allowed to pin messages there. `None` treats every match the same.
+const TOP_MATCH_SCORE: Option<f64> = None;
+const PIN_TOP_MATCHES: bool = false;
+
+// Which ads are sent depending on shipping: `ShippingFilter::Any` sends all of them,
+// `ShippableOrUnknown` skips ads marked "Nur Abholung", `ShippableOnly` only sends ads marked
+// "Versand möglich". Skipped ads are marked as seen.
+const SHIPPING_FILTER: ShippingFilter = ShippingFilter::Any;
+
+// What happens to ads whose price is marked "VB" (negotiable): `NegotiableHandling::Send` sends them
+// like any other, `Label` adds a "🤝 Preis verhandelbar" label, `FixedPriceOnly` skips them and marks
+// them as seen. Free items and ads without a price count as fixed price.
+const NEGOTIABLE_HANDLING: NegotiableHandling = NegotiableHandling::Send;
+
+// Skip ads with a "Top" or "Highlight" badge. These paid promotions are mostly commercial
+// and show up again and again; skipped ads are marked as seen.
+const SKIP_PROMOTED: bool = false;
+
+// Skip ads that were already viewed more than this many times, e.g. Some(200), since popular
+// items are usually gone by the time you ask. Ads whose card shows no view count are always sent.
+// To send the popular ones first instead, use NotificationOrder::MostViewedFirst.
+const MAX_VIEWS: Option<u32> = None;
+
+// Only send ads in one of these conditions, e.g. &["Neu", "Sehr gut"]. Known conditions are "Neu",
+// "Neuwertig", "Sehr gut", "Gut", "In Ordnung", "Gebraucht" and "Defekt"; case doesn't matter.
+// Many cards don't show a condition, those are always sent. Leave empty to send all.
+const ALLOWED_CONDITIONS: &[&str] = &[];
+
+// Skip ads of sellers with fewer than MIN_SELLER_LISTINGS or more than MAX_SELLER_LISTINGS active
+// listings, e.g. commercial relisters. Costs two extra requests per seller (ad page and profile),
+// counted against HOST_BUDGETS and cached for the run. `None` disables a bound.
+const MIN_SELLER_LISTINGS: Option<usize> = None;
+const MAX_SELLER_LISTINGS: Option<usize> = None;
+
+// Never send the listings of these sellers, e.g. &["12345678", "Trödel Paul"]. An entry matches the
+// user ID in the seller's profile link exactly, or the name shown on the ad page regardless of case.
+// Costs one extra request per ad (its page), shared with the seller settings around it.
+const BLOCKED_SELLERS: &[&str] = &[];
+
+// Space out the ads of one seller by at least this many seconds, e.g. when a private seller lists
+// their whole household at once. Later ads wait in SELLER_QUEUE_FILE and are sent by the following
+// runs, one per seller and run. Costs one extra request per ad (its page), cached together with
+// the seller filter above. `None` sends everything right away.
+const SELLER_MIN_INTERVAL_SECS: Option<u64> = None;
+const SELLER_QUEUE_FILE: &str = "seller_queue.json";
+
+// Listings whose price is re-checked every run, e.g. &["https://www.kleinanzeigen.de/s-anzeige/..."].
+// In watch mode that's every POLL_INTERVAL_SECS, even if single searches are scanned more often.
+// A "💸 Preis gesunken" message is sent when a price decreases; increases are ignored.
+// The last known prices are kept in WATCHED_FILE, separate from the seen ads.
+const WATCHED_ADS: &[&str] = &[];
+const WATCHED_FILE: &str = "watched.json";
+
+// The order new ads are sent in: `PageOrder` keeps the order of the search results,
+// `NewestFirst`/`OldestFirst` sort by posting time, `IdAscending`/`IdDescending` by ad ID.
+// The searches always follow each other in the order of SEARCHES, and ads that tie keep their page order.
+const NOTIFICATION_ORDER: NotificationOrder = NotificationOrder::PageOrder;
+
+// The time zone relative posting times like "Heute, 14:32" are read in. Kleinanzeigen shows German
+// local time, so keep EuropeBerlin unless the site changes; it is right even on a server set to UTC.
+const TIMEZONE: TimeZone = TimeZone::EuropeBerlin;
+
+// Log how many scraped ads had no image after each scan. A sudden jump usually means the
+// image selector no longer matches; `--warn-missing-images` lists the affected ads.
+const LOG_MISSING_IMAGE_SUMMARY: bool = true;
+
+// Each scraped page is checked for signs that our selectors no longer match
+// (e.g. a large page without any ads). Such pages are always logged as "Layoutänderung vermutet";
+// set to true to also send a warning to the chat.
+const NOTIFY_LAYOUT_CHANGE: bool = false;
+
+// After a run with failed deliveries, send "✅ Wieder normal, X Anzeigen nachgeliefert" once a run
+// delivers everything again. The failed ads of the last run are kept in RUN_STATE_FILE.
+const NOTIFY_RECOVERY: bool = false;
+const RUN_STATE_FILE: &str = "run_state.json";
+
+// Watch mode (`--watch`): the bot keeps running and scans every POLL_INTERVAL_SECS seconds.
+const POLL_INTERVAL_SECS: u64 = 300;
+// Optional: Only scan in watch mode while this cron expression matches in TIMEZONE, e.g.
+// Some("0 * 9-17 * * Mon-Fri") for weekdays from 9:00 to 17:59. The fields are second, minute,
+// hour, day of month, month, day of week (1 for Sunday to 7 for Saturday, or names like `Mon`) and
+// an optional year, as read by the `cron` crate. Outside the schedule the bot idles, but `/scan`
+// still works and the daily summary still goes out. `None` scans around the clock.
+const ACTIVE_SCHEDULE: Option<&str> = None;
+// If Kleinanzeigen answers with a CAPTCHA or blocking page, watch mode waits this long before
+// the next scan instead of POLL_INTERVAL_SECS, and sends one alert per blocked streak.
+const BLOCKED_BACKOFF_SECS: u64 = 1800;
+// In watch mode, send "📊 Heute: X neue Anzeige(n) gesehen, Y gesendet." once a day at this time
+// of day in TIMEZONE, e.g. Some((20, 0)) for 20:00. `None` disables the summary.
+// The counts since the last summary are kept in SUMMARY_FILE, so restarts don't lose them.
+const DAILY_SUMMARY_AT: Option<(u32, u32)> = None;
+const SUMMARY_FILE: &str = "daily_summary.json";
+// The format of the log output on stderr: `LogFormat::Text` for reading, `LogFormat::Json` for
+// log collectors such as Loki or ELK (one object per line with run_id, search and ad_id where known).
+// The `--log-json` flag selects JSON for a single run.
+const LOG_FORMAT: LogFormat = LogFormat::Text;
+// Also log details that are only of interest when looking into a problem, such as ads skipped
+// because of the ever-seen filter alone. They carry the level "debug" in JSON output.
+const LOG_DEBUG: bool = false;
+// In watch mode, listen for commands such as `/scan` in the configured chat.
+const ENABLE_TELEGRAM_COMMANDS: bool = true;
+// Attach "✅ Interessiert" and "🔕 Ignorieren" buttons to every Telegram ad. Taps are handled
+// by the command listener, so this needs watch mode with ENABLE_TELEGRAM_COMMANDS.
+// Ignored ads are kept in IGNORED_FILE and never announced again.
+const ENABLE_AD_BUTTONS: bool = false;
+const IGNORED_FILE: &str = "ignored.json";
+// Remember which Telegram messages each ad was posted as, so `--delete-search NAME` can remove all
+// notifications of a search from the chat later, e.g. after changing its filters. The newest
+// MAX_INDEXED_MESSAGES are kept in MESSAGE_INDEX_FILE. Telegram only deletes messages younger than 48 hours.
+const RECORD_MESSAGE_IDS: bool = false;
+const MESSAGE_INDEX_FILE: &str = "messages.json";
+const MAX_INDEXED_MESSAGES: usize = 5000;
+
 /// Represents a single advertisement listing from Kleinanzeigen.
 ///
 /// This struct holds the essential information scraped from the website for each ad.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct Ad {
     /// The unique identifier for the ad (e.g., "3170997111").
     id: String,
@@ -34,505 +583,3016 @@ struct Ad {
     title: String,
     /// The full URL to the ad's page.
     link: String,
-    /// The URL of the ad's main image, if available.
+    /// The URL of the ad's main image in `IMAGE_RESOLUTION`, which is what gets sent, if available.
     image_url: Option<String>,
+    /// The URL of the same image in full resolution, linked in the caption.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    full_image_url: Option<String>,
+    /// The posting time as shown on the page (e.g., "Heute, 14:32"), if available.
+    posted: Option<String>,
+    /// The posting time read from `posted` in TIMEZONE, if it has a known format. Ads that only
+    /// show a date get midnight of that day.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    posted_at: Option<DateTime<Utc>>,
+    /// The location as shown on the page (e.g., "04105 Leipzig"), if available.
+    location: Option<String>,
+    /// Whether the item can be shipped ("Versand möglich") or is pickup only ("Nur Abholung"),
+    /// or `None` if the ad doesn't say.
+    #[serde(default)]
+    shipping: Option<bool>,
+    /// Whether the price is marked "VB" (negotiable). Free items and ads without a price are not.
+    #[serde(default)]
+    negotiable: bool,
+    /// Whether the ad is a paid "Top" or "Highlight" promotion.
+    #[serde(default)]
+    promoted: bool,
+    /// How often the ad was viewed, if the card shows it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    views: Option<u32>,
+    /// The item condition, e.g. "Gebraucht", if the card shows it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    condition: Option<String>,
+    /// Short markers shown above the caption, such as a scam warning.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    labels: Vec<String>,
+    /// An emoji or short text put in front of the caption header, identifying the search it came from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prefix: Option<String>,
+    /// The start of the ad's description, if fetched with `FETCH_DESCRIPTIONS`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    /// Whether the ad matched the wishlist with at least `TOP_MATCH_SCORE`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    top_match: bool,
+    /// The name of the search that found the ad first, set once the scan collects it.
+    #[serde(skip)]
+    search: &'static str,
 }
 
-/// Represents a Telegram API error response.
-#[derive(Debug, Deserialize)]
-struct TelegramError {
-    /// The error code.
-    error_code: Option<i32>,
-    /// Additional parameters for the error.
-    parameters: Option<TelegramErrorParameters>,
+/// Diagnostics collected while scraping, to tell odd listings from broken selectors.
+#[derive(Debug, Default)]
+struct ScrapeStats {
+    /// The number of ads scraped in this run.
+    scraped: usize,
+    /// The number of those ads without an image.
+    missing_images: usize,
+    /// Pages where the layout seems to have changed, as (URL, warning).
+    layout_warnings: Vec<(String, String)>,
+    /// The dedup keys of every ad on the scanned pages, whose seen entries mustn't expire.
+    live_keys: HashSet<String>,
 }
 
-/// Additional parameters for Telegram API errors.
-#[derive(Debug, Deserialize)]
-struct TelegramErrorParameters {
-    /// Time to wait before retrying (for rate limiting).
-    retry_after: Option<i64>,
+/// What became of an ad picked up for sending.
+enum SendOutcome {
+    /// Sent, with the messages it was posted as, or `None` if no channel delivered it.
+    Sent(Option<Vec<PostedMessage>>),
+    /// Not delivered because a chat can't be posted to at all, with the reason.
+    ChatUnreachable(String),
+    /// Not sent because too many deliveries failed in a row.
+    CircuitOpen,
+    /// Not sent because the first run already delivered `FIRST_RUN_LIMIT` ads.
+    CapReached,
 }
 
-// --- Functions ---
-/// Loads the queue of already-seen ad IDs from a JSON file.
-///
-/// If the file does not exist or contains invalid data, it returns an empty queue.
-/// A VecDeque is used to efficiently remove old items from the front.
-fn load_seen_ads() -> VecDeque<String> {
-    match read_to_string(SEEN_ADS_FILE) {
-        Ok(content) => match from_str(&content) {
-            Ok(queue) => queue,
-            Err(e) => {
-                eprintln!("Fehler beim Parsen der Datei {}: {}", SEEN_ADS_FILE, e);
-                VecDeque::new()
-            }
-        },
-        Err(e) => {
-            eprintln!("Fehler beim Lesen der Datei {}: {}", SEEN_ADS_FILE, e);
-            VecDeque::new()
+/// The outcome of one scan across all searches.
+#[derive(Debug, Default)]
+struct ScanReport {
+    /// The number of new ads found.
+    new_ads: usize,
+    /// The searches that were scraped without errors, by name.
+    succeeded: Vec<&'static str>,
+    /// The searches that failed, as (name, error message).
+    failed: Vec<(&'static str, String)>,
+    /// The IDs of the ads delivered in this scan.
+    delivered: Vec<String>,
+    /// The IDs of the ads that couldn't be delivered and are retried next time.
+    undelivered: Vec<String>,
+    /// Why sending was stopped early because the chat can't be posted to, if it was.
+    chat_unreachable: Option<String>,
+}
+
+impl ScanReport {
+    /// Summarizes the report in one line, naming the failed searches if there are any.
+    fn summary(&self) -> String {
+        let mut summary = format!("{} neue Anzeige(n) gefunden.", self.new_ads);
+        if !self.failed.is_empty() {
+            let failed: Vec<String> = self
+                .failed
+                .iter()
+                .map(|(name, e)| format!("'{}' ({})", name, e))
+                .collect();
+            summary.push_str(&format!(
+                " {} von {} Suche(n) fehlgeschlagen: {}",
+                self.failed.len(),
+                self.failed.len() + self.succeeded.len(),
+                failed.join(", ")
+            ));
         }
+        summary
     }
 }
 
-/// Saves the provided queue of seen ad IDs to a JSON file.
-///
-/// The data is pretty-printed for human readability.
-fn save_seen_ads(ad_ids: &VecDeque<String>) -> Result<(), Box<dyn Error>> {
-    let content = to_string_pretty(ad_ids)?;
-    write(SEEN_ADS_FILE, content)?;
-    Ok(())
+// --- Functions ---
+/// Whether an ad was already handled, and how sure that is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Known {
+    /// The ad is new.
+    No,
+    /// The ad was dismissed or is in the seen store.
+    Seen,
+    /// Only the long-lived filter knows the ad, which may be one of its false positives.
+    Filtered,
 }
 
-/// Scrapes a specific Kleinanzeigen page for free listings.
-///
-/// # Arguments
-/// * `client` - The `reqwest::Client` to use for the HTTP request.
-/// * `url` - The exact URL of the Kleinanzeigen page to scrape.
+/// Checks whether an ad was already handled, either dismissed, in the seen store or in the
+/// long-lived filter.
 ///
 /// # Returns
-/// A `Vec<Ad>` containing all ads found on the page, or an error if the request fails.
-async fn scrape_kleinanzeigen_page(client: &Client, url: &str) -> Result<Vec<Ad>, Box<dyn Error>> {
-    println!("Scrape URL: {}", url);
-    let response = client.get(url).send().await?.text().await?;
-    let document = Html::parse_document(&response);
-
-    // Define CSS selectors to find the necessary elements on the page.
-    let ad_selector = Selector::parse("article.aditem").unwrap();
-    let title_link_selector = Selector::parse("a.ellipsis").unwrap();
-    let image_selector = Selector::parse(".aditem-image img").unwrap();
-    let mut listings = Vec::new();
-
-    // Iterate over each ad container found on the page.
-    for article in document.select(&ad_selector) {
-        // Extract the unique ad ID from the 'data-adid' attribute.
-        if let Some(ad_id) = article.value().attr("data-adid") {
-            // Find the primary link within the ad, which contains the title.
-            if let Some(link_element) = article.select(&title_link_selector).next() {
-                if let Some(href) = link_element.value().attr("href") {
-                    // We only care about actual ad links, not other miscellaneous links.
-                    if href.starts_with("/s-anzeige/") {
-                        let title = link_element.text().collect::<String>().trim().to_string();
-                        let full_link = format!("https://www.kleinanzeigen.de{}", href);
-
-                        // --- IMPROVED IMAGE QUALITY FIX ---
-                        // Prioritize `srcset` for the best quality image, then fall back to `src`.
-                        let image_url = article
-                            .select(&image_selector)
-                            .next()
-                            .and_then(|img| {
-                                // `srcset` provides multiple image sizes. We take the last one, which is usually the highest resolution.
-                                if let Some(srcset) = img.value().attr("srcset") {
-                                    srcset
-                                        .split(',')
-                                        .last()
-                                        .and_then(|s| s.split_whitespace().next())
-                                        .map(String::from)
-                                } else {
-                                    // Fallback to the `src` attribute if `srcset` is not available.
-                                    img.value().attr("src").map(String::from)
-                                }
-                            })
-                            .map(|src| {
-                                // Get the base URL by splitting at the '?' and taking the first part.
-                                if let Some(base_url) = src.split('?').next() {
-                                    // Append the high-resolution rule.
-                                    format!("{}?rule=$_59.AUTO", base_url)
-                                } else {
-                                    // If splitting fails for some reason, return the original src.
-                                    src
-                                }
-                            });
-                        listings.push(Ad {
-                            id: ad_id.to_string(),
-                            title,
-                            link: full_link,
-                            image_url,
-                        });
-                    }
-                }
-            }
-        }
+/// Whether it was, or an error if the seen store couldn't be asked.
+async fn is_known_ad(
+    ad: &Ad,
+    store: &impl SeenStore,
+    ever_seen: Option<&BloomFilter>,
+    ignored: &BTreeSet<String>,
+) -> Result<Known, Box<dyn Error>> {
+    let key = DEDUP_STRATEGY.key(ad);
+    if ignored.contains(&ad.id) || store.contains(&key).await? {
+        return Ok(Known::Seen);
     }
+    if ever_seen.is_some_and(|filter| filter.contains(&key)) {
+        debug!(ad = &ad.id; "Anzeige {} wird nur wegen des Langzeitfilters übersprungen.", ad.id);
+        return Ok(Known::Filtered);
+    }
+    Ok(Known::No)
+}
 
-    // Return the vector of scraped ads
-    Ok(listings)
+/// Remembers an ad as handled, in the seen store and the long-lived filter if enabled.
+async fn mark_seen(store: &mut impl SeenStore, ever_seen: Option<&mut BloomFilter>, ad: &Ad) {
+    let key = DEDUP_STRATEGY.key(ad);
+    if let Some(filter) = ever_seen {
+        filter.insert(&key);
+    }
+    store.insert(&key).await;
 }
 
-/// Sends a photo with a caption to the configured Telegram group.
+/// Scrapes the result pages of one search until the first page with an already-seen ad,
+/// or with the top ad of the last run.
 ///
 /// # Arguments
-/// * `client` - The `reqwest::Client` to use for the API call.
-/// * `photo_url` - The URL of the image to send.
-/// * `caption` - The HTML-formatted caption for the photo.
-/// Sends a photo with a caption to the configured Telegram group.
+/// * `source` - Where the result pages come from.
+/// * `governor` - The request budgets the page requests count against.
+/// * `search` - The search to scan.
+/// * `cursor` - The ID of the top ad of the last run, replaced with the top ad of this one unless
+///   an ad above the old one is only known to the long-lived filter.
+/// * `store` - The ads already handled.
+/// * `ever_seen` - The long-lived filter of pruned IDs, if enabled.
+/// * `ignored` - The IDs of ads dismissed with the "Ignorieren" button, which count as seen for good.
+/// * `stats` - Collects diagnostics such as ads without an image.
+/// * `warn_missing_images` - Whether to log every ad without an image.
+/// * `seed_only` - Whether to stop after the first page and the `EXTRA_PAGES_AFTER_SEEN` pages
+///   after it, which a later run scans too, for `FirstRunMode::StartLive`.
 ///
-/// # Arguments
-/// * `client` - The `reqwest::Client` to use for the API call.
-/// * `photo_url` - The URL of the image to send.
-/// * `caption` - The HTML-formatted caption for the photo.
-async fn send_photo_message(
-    client: &Client,
-    photo_url: &str,
-    caption: &str,
-) -> Result<Option<i64>, Box<dyn Error>> {
-    let url = format!(
-        "https://api.telegram.org/bot{}/sendPhoto",
-        TELEGRAM_BOT_TOKEN
-    );
+/// # Returns
+/// The new ads of this search, or an error if scraping failed. They come in page order and, within
+/// a page, in the order the page lists them, then stably sorted by `NOTIFICATION_ORDER`.
+#[allow(clippy::too_many_arguments)]
+async fn collect_new_ads(
+    source: &impl PageSource,
+    governor: &RateGovernor,
+    search: &Search,
+    cursor: &mut Option<String>,
+    store: &impl SeenStore,
+    ever_seen: Option<&BloomFilter>,
+    ignored: &BTreeSet<String>,
+    stats: &mut ScrapeStats,
+    warn_missing_images: bool,
+    seed_only: bool,
+) -> Result<Vec<Ad>, Box<dyn Error>> {
+    // --- HYBRID LOGIC IMPLEMENTATION ---
+    // Collect all ads from pages first before processing, each with whether it was handled before
+    let mut all_ads: Vec<(Ad, Known)> = Vec::new();
+    // The last page to scan, known once a page with an already-seen ad was found.
+    let mut last_page: Option<u32> = None;
+    let previous_cursor = cursor.clone();
+    // The index of the top ad of the last run in `all_ads`, once reached.
+    let mut cursor_at: Option<usize> = None;
 
-    // Use form data to match what curl is doing, with HTML formatting
-    let params = [
-        ("chat_id", TELEGRAM_CHAT_ID),
-        ("photo", photo_url),
-        ("caption", caption),
-        ("parse_mode", "HTML"),
-    ];
-
-    // Send the POST request to the Telegram API with the photo and caption
-    let response = client.post(&url).form(&params).send().await?;
-
-    // Check if the response is successful
-    if response.status().is_success() {
-        println!("Fotonachricht erfolgreich gesendet.");
-        return Ok(None);
-    }
-
-    // Handle error response
-    let status = response.status();
-    let error_bytes = response.bytes().await?;
-
-    // Try to parse the error response as JSON
-    if let Ok(telegram_error) = from_slice::<TelegramError>(&error_bytes) {
-        if telegram_error.error_code == Some(429) {
-            // Rate limiting error
-            if let Some(params) = telegram_error.parameters {
-                if let Some(retry_after) = params.retry_after {
-                    return Ok(Some(retry_after));
-                }
-            }
+    // Loop through the pages of the search results.
+    // A safety limit to prevent excessive requests.
+    let max_pages = search.max_pages.unwrap_or(MAX_PAGES_TO_SCAN);
+    let max_pages = match seed_only {
+        true => max_pages.min(1 + EXTRA_PAGES_AFTER_SEEN),
+        false => max_pages,
+    };
+    for page in 1..=max_pages {
+        let current_url = search.page_url(page);
 
-            // Default retry after 30 seconds if not specified
-            return Ok(Some(30));
+        // Scrape all ads from the current page, once the host's budget allows another request.
+        governor.acquire_url(&current_url).await;
+        let scraped_page = source.fetch_page(&current_url).await?;
+        if let Some(warning) = scraped_page.layout_warning {
+            stats.layout_warnings.push((current_url.clone(), warning));
         }
-    }
+        let current_ads = scraped_page.ads;
 
-    // Construct and return a detailed error message with status code and response body
-    let error_body = String::from_utf8_lossy(&error_bytes);
-    let error_message = format!("Telegram API Fehler: {} - {}", status, error_body);
-    Err(error_message.into())
-}
+        // If a page has no ads, we've reached the end of the results.
+        if current_ads.is_empty() {
+            log!(
+                "Keine Anzeigen auf Seite {} gefunden. Suche wird beendet.",
+                page
+            );
+            break;
+        }
 
-/// Sends a text-only message to the configured Telegram group.
-///
-/// # Arguments
-/// * `client` - The `reqwest::Client` to use for the API call.
-/// * `message` - The HTML-formatted message string to send.
-async fn send_text_message(client: &Client, message: &str) -> Result<Option<i64>, Box<dyn Error>> {
-    let url = format!(
-        "https://api.telegram.org/bot{}/sendMessage",
-        TELEGRAM_BOT_TOKEN
-    );
+        // Remember the top ad for the next run. Promotions are skipped, since they rotate
+        // and stay on top regardless of their age.
+        if page == 1
+            && let Some(top_ad) = current_ads.iter().find(|ad| !ad.promoted)
+        {
+            *cursor = Some(top_ad.id.clone());
+        }
 
-    // Use form data to match what curl is doing, with HTML formatting
-    let params = [
-        ("chat_id", TELEGRAM_CHAT_ID),
-        ("text", message),
-        ("parse_mode", "HTML"),
-    ];
-
-    // Send the POST request to the Telegram API with the text message
-    let response = client.post(&url).form(&params).send().await?;
-
-    // Check if the response is successful
-    if response.status().is_success() {
-        println!("Textnachricht erfolgreich gesendet.");
-        return Ok(None);
-    }
-
-    // Handle error response
-    let status = response.status();
-    let error_bytes = response.bytes().await?;
-
-    // Try to parse the error response as JSON
-    if let Ok(telegram_error) = from_slice::<TelegramError>(&error_bytes) {
-        if telegram_error.error_code == Some(429) {
-            // Rate limiting error
-            if let Some(params) = telegram_error.parameters {
-                if let Some(retry_after) = params.retry_after {
-                    return Ok(Some(retry_after));
-                }
+        // Reaching the top ad of the last run means it and everything below it were handled back
+        // then, even if their seen entries have been pruned since, so the scan ends on this page.
+        if cursor_at.is_none()
+            && let Some(position) = previous_cursor
+                .as_deref()
+                .and_then(|cursor| current_ads.iter().position(|ad| ad.id == cursor))
+        {
+            log!(
+                "Oberste Anzeige des letzten Durchlaufs auf Seite {} erreicht.",
+                page
+            );
+            cursor_at = Some(all_ads.len() + position);
+            last_page = Some(page);
+        }
+
+        // Check if any ads on this page were already seen
+        let mut known = Vec::with_capacity(current_ads.len());
+        for ad in &current_ads {
+            known.push(is_known_ad(ad, store, ever_seen, ignored).await?);
+        }
+        if last_page.is_none() && known.iter().any(|known| *known != Known::No) {
+            // Scan the overlap pages, then stop
+            if EXTRA_PAGES_AFTER_SEEN > 0 {
+                log!(
+                    "Bereits gesehene Anzeige auf Seite {} gefunden. Scanne noch {} weitere Seite(n), um verschobene Anzeigen zu erfassen.",
+                    page,
+                    EXTRA_PAGES_AFTER_SEEN
+                );
             }
+            last_page = Some(page + EXTRA_PAGES_AFTER_SEEN);
+        }
 
-            // Default retry after 30 seconds if not specified
-            return Ok(Some(30));
+        // Keep track of ads without an image, which may point to selector drift
+        for ad in current_ads.iter().filter(|ad| ad.image_url.is_none()) {
+            stats.missing_images += 1;
+            if warn_missing_images {
+                log!(ad = &ad.id; "Anzeige ohne Bild: {} ({})", ad.id, ad.title);
+            }
         }
-    }
+        stats.scraped += current_ads.len();
+        stats
+            .live_keys
+            .extend(current_ads.iter().map(|ad| DEDUP_STRATEGY.key(ad)));
 
-    // Construct and return a detailed error message with status code and response body
-    let error_body = String::from_utf8_lossy(&error_bytes);
-    let error_message = format!("Telegram API Fehler: {} - {}", status, error_body);
-    Err(error_message.into())
-}
+        // Add all ads from this page to our master list
+        all_ads.extend(current_ads.into_iter().zip(known));
 
-// --- Main Program ---
-#[main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    // A simple guard to prevent running with placeholder credentials.
-    if TELEGRAM_BOT_TOKEN == "YOUR_TELEGRAM_BOT_TOKEN" || TELEGRAM_CHAT_ID == "YOUR_GROUP_CHAT_ID" {
-        eprintln!(
-            "FEHLER: Bitte ersetze die Platzhalter für TELEGRAM_BOT_TOKEN und TELEGRAM_CHAT_ID im Skript."
-        );
-        return Ok(());
+        // Once the overlap after the first page with old ads is done, we can stop crawling further pages.
+        if last_page.is_some_and(|last_page| page >= last_page) {
+            log!("Scan wird nach Seite {} beendet.", page);
+            break;
+        }
     }
 
-    // Initialize an HTTP client with a browser-like User-Agent to avoid being blocked.
-    let client = Client::builder()
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36")
-        .build()?;
+    // Keep only the new ads above the cursor and bring them into the configured order.
+    all_ads.truncate(cursor_at.unwrap_or(all_ads.len()));
+    // An ad only the long-lived filter knows may be new after all, so the cursor isn't moved past
+    // it; the next run checks it again instead of stopping above it.
+    if all_ads.iter().any(|(_, known)| *known == Known::Filtered) {
+        *cursor = previous_cursor;
+    }
+    let mut new_ads: Vec<Ad> = all_ads
+        .into_iter()
+        .filter(|(_, known)| *known == Known::No)
+        .map(|(ad, _)| ad)
+        .collect();
+    sort_ads(&mut new_ads, NOTIFICATION_ORDER);
 
-    // Load the IDs of ads we've already notified about.
-    let mut seen_ads_queue = load_seen_ads();
-    let is_first_run = seen_ads_queue.is_empty();
-    println!(
-        "{} bereits gesehene Anzeigen geladen.",
-        seen_ads_queue.len()
-    );
+    Ok(new_ads)
+}
 
-    // Debug print the first few seen ad IDs
-    let first_few: Vec<&String> = seen_ads_queue.iter().take(5).collect();
-    println!("Erste gesehene IDs: {:?}", first_few);
+/// Announces an ad on every configured channel.
+///
+/// # Returns
+/// The messages the ad was posted as once any of the channels delivered it, `None` if none did,
+/// or the `ChatUnreachable` error if none did and a chat can't be posted to at all.
+async fn deliver<N: Notifier>(
+    notifiers: &[N],
+    ad: &Ad,
+    delivery: &Delivery<'_>,
+) -> Result<Option<Vec<PostedMessage>>, Box<dyn Error>> {
+    let mut posted: Option<Vec<PostedMessage>> = None;
+    let mut unreachable = None;
+    for notifier in notifiers {
+        match notifier.send_ad(ad, delivery).await {
+            Ok(messages) => posted.get_or_insert_default().extend(messages),
+            Err(e) if e.is::<ChatUnreachable>() => unreachable = Some(e),
+            Err(e) => log!(
+                ad = &ad.id;
+                "Fehler beim Senden der Anzeige '{}' über {}: {}",
+                ad.title,
+                notifier.name(),
+                e
+            ),
+        }
+    }
+    match unreachable {
+        Some(e) if posted.is_none() => Err(e),
+        _ => Ok(posted),
+    }
+}
 
-    // For fast lookups, create a HashSet from the queue.
-    let seen_ads_set: HashSet<_> = seen_ads_queue.iter().cloned().collect();
-    let mut new_ads_found_total = 0;
+/// Looks up the seller of an ad on its detail page.
+///
+/// # Returns
+/// The seller, or `None` if they couldn't be determined, in which case the ad is sent anyway.
+async fn seller_of(source: &impl PageSource, governor: &RateGovernor, ad: &Ad) -> Option<Seller> {
+    governor.acquire_url(&ad.link).await;
+    source.fetch_seller(&ad.link).await.unwrap_or_else(|e| {
+        log!(
+            ad = &ad.id;
+            "Fehler beim Abrufen des Verkäufers von {}: {}",
+            ad.link, e
+        );
+        None
+    })
+}
 
-    // Track how many ads we've sent on first run
-    let mut first_run_sent_count = 0;
+/// Looks up how many active listings a seller has, fetching each seller only once per run.
+///
+/// # Returns
+/// The count, or `None` if it couldn't be determined, in which case the ad is sent anyway.
+async fn seller_listings(
+    source: &impl PageSource,
+    governor: &RateGovernor,
+    seller_url: &str,
+    listings_by_seller: &mut HashMap<String, Option<usize>>,
+) -> Option<usize> {
+    if let Some(&listings) = listings_by_seller.get(seller_url) {
+        return listings;
+    }
 
-    // A safety limit to prevent excessive requests.
-    const MAX_PAGES_TO_SCAN: u32 = 10;
+    governor.acquire_url(seller_url).await;
+    let listings = source
+        .fetch_seller_listing_count(seller_url)
+        .await
+        .unwrap_or_else(|e| {
+            log!("Fehler beim Abrufen des Verkäufers {}: {}", seller_url, e);
+            None
+        });
+    listings_by_seller.insert(seller_url.to_string(), listings);
+    listings
+}
 
-    // --- HYBRID LOGIC IMPLEMENTATION ---
-    // 1. Collect all ads from pages first before processing
-    let mut all_ads: Vec<Ad> = Vec::new();
-    let mut stop_paging = false;
+/// The seen ads a scan checks against and adds to.
+struct SeenState<S: SeenStore> {
+    /// The dedup keys of the ads already handled.
+    store: S,
+    /// The long-lived filter of keys pruned from the store, if enabled.
+    ever_seen: Option<BloomFilter>,
+    /// The IDs of ads dismissed with the "Ignorieren" button.
+    ignored: BTreeSet<String>,
+    /// The ID of the top ad of each search in the last run, keyed by search name.
+    cursors: BTreeMap<String, String>,
+    /// The photos of recently notified ads, if `DEDUP_BY_IMAGE` is enabled.
+    recent_images: Option<RecentImages>,
+    /// The messages ads were posted as, if `RECORD_MESSAGE_IDS` is enabled.
+    messages: Option<MessageIndex>,
+    /// The ads held back per seller, if `SELLER_MIN_INTERVAL_SECS` is set.
+    sellers: Option<SellerQueue>,
+    /// Whether the first run got through, `None` if there is no marker yet.
+    first_run: Option<FirstRun>,
+    /// Whether the seen list starts over because `DEDUP_STRATEGY` changed, in which case the
+    /// ads listed now are seeded like with `FirstRunMode::StartLive`.
+    strategy_changed: bool,
+}
 
-    // Loop through the pages of the search results.
-    for page in 1..=MAX_PAGES_TO_SCAN {
-        let current_url = if page == 1 {
-            // The first page has a slightly different URL format.
-            format!("{}{}", KLEINANZEIGEN_BASE_URL, KLEINANZEIGEN_URL_SUFFIX)
-        } else {
-            format!(
-                "{}/seite:{}{}",
-                KLEINANZEIGEN_BASE_URL, page, KLEINANZEIGEN_URL_SUFFIX
-            )
-        };
+impl SeenState<JsonFileStore> {
+    /// Loads the profile's seen list from SEEN_ADS_FILE, together with the other state files.
+    ///
+    /// # Returns
+    /// The state, or `CorruptSeenFile` if the seen list can't be parsed and `force_reseed` isn't set.
+    fn load_local(profile: &Profile, force_reseed: bool) -> Result<Self, CorruptSeenFile> {
+        // Load the IDs of ads we've already notified about.
+        let store = JsonFileStore::load(
+            profile.state_file(SEEN_ADS_FILE),
+            DEDUP_STRATEGY,
+            force_reseed,
+        )?;
+        log!(
+            "{} bereits gesehene Anzeigen geladen.",
+            store.entries().len()
+        );
 
-        // Scrape all ads from the current page.
-        let current_ads = scrape_kleinanzeigen_page(&client, &current_url).await?;
+        // Debug print the first few seen ad IDs
+        let first_few: Vec<&String> = store
+            .entries()
+            .iter()
+            .take(5)
+            .map(|entry| &entry.id)
+            .collect();
+        log!("Erste gesehene IDs: {:?}", first_few);
 
-        // If a page has no ads, we've reached the end of the results.
-        if current_ads.is_empty() {
-            println!(
-                "Keine Anzeigen auf Seite {} gefunden. Suche wird beendet.",
-                page
-            );
-            break;
+        let mut state = SeenState::load(store, profile);
+        state.strategy_changed = state.store.strategy_changed();
+
+        // Cover every entry of the seen list, e.g. after upgrading or resizing the filter.
+        if let Some(filter) = &mut state.ever_seen {
+            for entry in state.store.entries() {
+                if !filter.contains(&entry.id) {
+                    filter.insert(&entry.id);
+                }
+            }
         }
+        Ok(state)
+    }
+}
 
-        // Check if any ads on this page were already seen
-        if current_ads.iter().any(|ad| seen_ads_set.contains(&ad.id)) {
-            // Set flag to stop after finishing this page
-            stop_paging = true;
+impl SeenState<MemoryStore> {
+    /// Creates an empty state that lives in memory only, for `--no-persist`.
+    ///
+    /// The optional parts are kept in memory too, so they work for as long as the state lives.
+    /// Only the message index is left out, as `--delete-search` reads it from its file.
+    fn in_memory() -> Self {
+        Self {
+            store: MemoryStore::default(),
+            ever_seen: ENABLE_EVER_SEEN_FILTER
+                .then(|| BloomFilter::with_rate(EVER_SEEN_CAPACITY, EVER_SEEN_FALSE_POSITIVE_RATE)),
+            ignored: BTreeSet::new(),
+            cursors: BTreeMap::new(),
+            recent_images: DEDUP_BY_IMAGE.then(RecentImages::default),
+            messages: None,
+            sellers: SELLER_MIN_INTERVAL_SECS.map(|_| SellerQueue::default()),
+            first_run: None,
+            strategy_changed: false,
         }
+    }
+}
 
-        // Add all ads from this page to our master list
-        all_ads.extend(current_ads);
+impl<S: SeenStore> SeenState<S> {
+    /// Wraps a seen store together with the profile's long-lived filter, ignore list and search cursors.
+    fn load(store: S, profile: &Profile) -> Self {
+        // IDs that dropped out of the pruned store are still remembered here, so live ads don't come back.
+        let ever_seen = ENABLE_EVER_SEEN_FILTER.then(|| {
+            BloomFilter::load(
+                &profile.state_file(EVER_SEEN_FILE),
+                EVER_SEEN_CAPACITY,
+                EVER_SEEN_FALSE_POSITIVE_RATE,
+            )
+        });
 
-        // If we found any old ads on this page, we can stop crawling further pages.
-        if stop_paging {
-            println!(
-                "Bereits gesehene Anzeige auf Seite {} gefunden. Scan wird nach dieser Seite beendet.",
-                page
-            );
-            break;
+        Self {
+            store,
+            ever_seen,
+            ignored: load_ignored(&profile.state_file(IGNORED_FILE)),
+            cursors: load_cursors(&profile.state_file(CURSOR_FILE)),
+            recent_images: DEDUP_BY_IMAGE
+                .then(|| RecentImages::load(&profile.state_file(RECENT_IMAGES_FILE))),
+            messages: RECORD_MESSAGE_IDS
+                .then(|| MessageIndex::load(&profile.state_file(MESSAGE_INDEX_FILE))),
+            sellers: SELLER_MIN_INTERVAL_SECS
+                .map(|_| SellerQueue::load(&profile.state_file(SELLER_QUEUE_FILE))),
+            first_run: load_first_run(&profile.state_file(FIRST_RUN_FILE)),
+            strategy_changed: false,
         }
-
-        // Be polite and wait a moment before scraping the next page.
-        // Wait for 1 second before scraping the next page to be respectful to the server
-        sleep(Duration::from_secs(1)).await;
     }
 
-    // 2. Process all collected ads afterward, sending notifications only for new ones
-    for ad in all_ads {
-        // For first run, limit the number of ads sent
-        if is_first_run && first_run_sent_count >= FIRST_RUN_LIMIT {
-            break;
+    /// Saves the seen ads, the long-lived filter, the search cursors, the recent images, the
+    /// message index and the seller queue for the next run.
+    fn save(&self, profile: &Profile) {
+        // Persist the updated seen ads for the next run.
+        if let Err(e) = self.store.persist() {
+            log!("Fehler beim Speichern der gesehenen Anzeigen: {}", e);
         }
 
-        if !seen_ads_set.contains(&ad.id) {
-            // This is a new ad.
-            new_ads_found_total += 1;
-            println!("Neue Anzeige gefunden: {}", ad.title);
-            let caption = format!(
-                "<b>Neuer kostenloser Artikel gefunden!</b>\n<b>Titel:</b> {}\n<a href='{}'>Anzeige ansehen</a>",
-                ad.title, ad.link
-            );
+        // Save the long-lived filter alongside it.
+        if let Some(filter) = &self.ever_seen {
+            let path = profile.state_file(EVER_SEEN_FILE);
+            if filter.inserted() > EVER_SEEN_CAPACITY as u64 {
+                log!(
+                    "Warnung: Der Filter {} enthält mehr als {} IDs, die Fehlerquote steigt. Bitte EVER_SEEN_CAPACITY erhöhen.",
+                    path,
+                    EVER_SEEN_CAPACITY
+                );
+            }
+            if let Err(e) = filter.save(&path) {
+                log!("Fehler beim Speichern der Datei {}: {}", path, e);
+            }
+        }
 
-            // If the ad has an image, send a photo message. Otherwise, send a text message.
-            let mut send_success = false;
-            if let Some(image_url) = &ad.image_url {
-                match send_photo_message(&client, image_url, &caption).await {
-                    Ok(None) => {
-                        // Success
-                        send_success = true;
-                    }
-                    Ok(Some(retry_after)) => {
-                        // Rate limiting, wait and retry
-                        eprintln!(
-                            "Rate limiting erkannt. Warte {} Sekunden vor erneutem Versuch.",
-                            retry_after
-                        );
+        // Save where each search stopped alongside it.
+        let path = profile.state_file(CURSOR_FILE);
+        if let Err(e) = save_cursors(&path, &self.cursors) {
+            log!("Fehler beim Speichern der Datei {}: {}", path, e);
+        }
 
-                        // Wait for the specified duration before retrying
-                        sleep(Duration::from_secs(retry_after as u64)).await;
-
-                        // Retry once
-                        match send_photo_message(&client, image_url, &caption).await {
-                            Ok(None) => {
-                                // Success on retry
-                                send_success = true;
-                            }
-                            Ok(Some(retry_after)) => {
-                                eprintln!("Erneute Rate Limiting. Warte {} Sekunden.", retry_after);
-
-                                // Wait for the specified duration before final retry
-                                sleep(Duration::from_secs(retry_after as u64)).await;
-
-                                // Final retry
-                                if send_photo_message(&client, image_url, &caption)
-                                    .await
-                                    .is_ok()
-                                {
-                                    send_success = true;
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("Fehler beim erneuten Senden der Fotonachricht: {}", e);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!(
-                            "Fehler beim Senden der Fotonachricht: {}. Fallback auf Textnachricht.",
-                            e
-                        );
+        if let Some(images) = &self.recent_images {
+            let path = profile.state_file(RECENT_IMAGES_FILE);
+            if let Err(e) = images.save(&path) {
+                log!("Fehler beim Speichern der Datei {}: {}", path, e);
+            }
+        }
 
-                        // If sending the photo fails, try sending a text message instead.
-                        match send_text_message(&client, &caption).await {
-                            Ok(None) => {
-                                // Success
-                                send_success = true;
-                            }
-                            Ok(Some(retry_after)) => {
-                                // Rate limiting, wait and retry
-                                eprintln!(
-                                    "Rate limiting erkannt. Warte {} Sekunden vor erneutem Versuch der Textnachricht.",
-                                    retry_after
-                                );
-                                sleep(Duration::from_secs(retry_after as u64)).await;
-
-                                // Retry once
-                                if send_text_message(&client, &caption).await.is_ok() {
-                                    send_success = true;
-                                }
-                            }
-                            Err(e_text) => {
-                                eprintln!("Fehler beim Senden der Textnachricht: {}", e_text);
-                            }
-                        }
-                    }
-                }
-            } else {
-                match send_text_message(&client, &caption).await {
-                    Ok(None) => {
-                        // Success
-                        send_success = true;
-                    }
-                    Ok(Some(retry_after)) => {
-                        // Rate limiting, wait and retry
-                        eprintln!(
-                            "Rate limiting erkannt. Warte {} Sekunden vor erneutem Versuch der Textnachricht.",
-                            retry_after
-                        );
+        if let Some(messages) = &self.messages {
+            let path = profile.state_file(MESSAGE_INDEX_FILE);
+            if let Err(e) = messages.save(&path) {
+                log!("Fehler beim Speichern der Datei {}: {}", path, e);
+            }
+        }
 
-                        // Wait for the specified duration before retrying
-                        sleep(Duration::from_secs(retry_after as u64)).await;
+        if let Some(sellers) = &self.sellers {
+            let path = profile.state_file(SELLER_QUEUE_FILE);
+            if let Err(e) = sellers.save(&path) {
+                log!("Fehler beim Speichern der Datei {}: {}", path, e);
+            }
+        }
+    }
+}
 
-                        // Retry once
-                        if send_text_message(&client, &caption).await.is_ok() {
-                            send_success = true;
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Fehler beim Senden der Textnachricht: {}", e);
+/// Runs one full scan of a profile: scrapes the pages of every search, notifies about new ads and saves the seen list.
+///
+/// A search that fails to scrape is logged and skipped, so the others still get notified about and saved.
+///
+/// # Arguments
+/// * `client` - The `reqwest::Client` used for scraping.
+/// * `governor` - The request budgets shared by scraping and sending.
+/// * `notifiers` - All channels the profile's ads are announced on.
+/// * `profile` - The profile whose searches and state files are used.
+/// * `cli_args` - The command-line options of this run.
+/// * `memory` - The state kept in memory with `--no-persist`, used instead of the state files.
+/// * `due_searches` - The names of the searches to scan, or `None` for all of them.
+///
+/// # Returns
+/// A report of the new ads and the searches that failed, or the first error if every search failed.
+async fn run_scan(
+    client: &Client,
+    governor: &RateGovernor,
+    notifiers: &[AnyNotifier],
+    profile: &'static Profile,
+    cli_args: &CliArgs,
+    memory: Option<&mut SeenState<MemoryStore>>,
+    due_searches: Option<&HashSet<&'static str>>,
+) -> Result<ScanReport, Box<dyn Error>> {
+    start_run();
+    if !profile.name.is_empty() {
+        log!("Profil '{}'.", profile.name);
+    }
+    if let Some(state) = memory {
+        return scan_with_state(
+            state,
+            client,
+            governor,
+            notifiers,
+            profile,
+            cli_args,
+            due_searches,
+        )
+        .await;
+    }
+    match SEEN_BACKEND {
+        SeenBackend::JsonFile => {
+            let alert_marker = profile.state_file(CORRUPT_ALERT_FILE);
+            let mut state = match SeenState::load_local(profile, cli_args.force_reseed) {
+                Ok(state) => {
+                    if Path::new(&alert_marker).exists() {
+                        let _ = remove_file(&alert_marker);
                     }
+                    state
                 }
-            }
-
-            // Only add the ad to seen_ads_queue if sending was successful
-            if send_success {
-                // Add the new ad's ID to our queue to preserve order.
-                seen_ads_queue.push_back(ad.id.clone());
-
-                // Increment counter for first run
-                if is_first_run {
-                    first_run_sent_count += 1;
+                Err(e) => {
+                    if NOTIFY_CORRUPT_SEEN_FILE
+                        && !Path::new(&alert_marker).exists()
+                        && broadcast_notice(profile, notifiers, &format!("⚠️ {}.", e)).await
+                        && let Err(e) = write(&alert_marker, "")
+                    {
+                        log!("Fehler beim Speichern der Datei {}: {}", alert_marker, e);
+                    }
+                    return Err(e.into());
                 }
-            } else {
-                eprintln!(
-                    "Nachricht für Anzeige '{}' wurde nicht erfolgreich gesendet und wird erneut versucht beim nächsten Durchlauf.",
-                    ad.title
-                );
-            }
+            };
+            scan_with_state(
+                &mut state,
+                client,
+                governor,
+                notifiers,
+                profile,
+                cli_args,
+                due_searches,
+            )
+            .await
+        }
+        SeenBackend::Redis => {
+            // Without Redis nothing is sent at all, rather than risking duplicates.
+            let prefix = match profile.name {
+                "" => REDIS_KEY_PREFIX.to_string(),
+                name => format!("{}{}:", REDIS_KEY_PREFIX, name),
+            };
+            let store = RedisStore::connect(REDIS_URL, &prefix, REDIS_SEEN_TTL_DAYS).await?;
+            scan_with_state(
+                &mut SeenState::load(store, profile),
+                client,
+                governor,
+                notifiers,
+                profile,
+                cli_args,
+                due_searches,
+            )
+            .await
+        }
+    }
+}
 
-            // Pause briefly to avoid hitting Telegram's rate limits.
-            // Wait for 2 seconds between sending messages to avoid rate limiting
-            sleep(Duration::from_secs(2)).await;
+/// Runs one full scan against the given seen state, then saves it unless `--no-persist` is given.
+async fn scan_with_state<S: SeenStore>(
+    state: &mut SeenState<S>,
+    client: &Client,
+    governor: &RateGovernor,
+    notifiers: &[AnyNotifier],
+    profile: &'static Profile,
+    cli_args: &CliArgs,
+    due_searches: Option<&HashSet<&'static str>>,
+) -> Result<ScanReport, Box<dyn Error>> {
+    // Record the start of the first run before anything is sent, so a crash leaves it unfinished.
+    // A first run that was interrupted is continued, any other starts over.
+    let seeding =
+        !cli_args.emit_json && is_seeding(state.first_run.as_ref(), state.store.is_empty().await);
+    if seeding
+        && state
+            .first_run
+            .as_ref()
+            .is_none_or(|first_run| first_run.complete)
+    {
+        let started = FirstRun::default();
+        if !cli_args.no_persist {
+            save_first_run_marker(profile, &started);
         }
+        state.first_run = Some(started);
     }
 
-    // After scanning, check if we found any new ads.
-    if new_ads_found_total > 0 {
-        println!(
-            "Verarbeitung abgeschlossen. Insgesamt {} neue Anzeige(n) gefunden.",
-            new_ads_found_total
-        );
+    let report = process_scan(
+        client,
+        governor,
+        notifiers,
+        profile,
+        cli_args,
+        state,
+        due_searches,
+    )
+    .await?;
 
-        // --- PRUNING LOGIC ---
-        // If the queue is now larger than the limit, remove the oldest items from the front.
-        while seen_ads_queue.len() > MAX_SEEN_ADS {
-            seen_ads_queue.pop_front();
-        }
-        println!(
-            "Die Liste der gesehenen Anzeigen wurde auf {} Einträge gekürzt.",
-            seen_ads_queue.len()
-        );
+    // In JSON mode nothing was sent, so the seen list stays as it was.
+    if cli_args.emit_json {
+        return Ok(report);
+    }
 
-        // Save the updated list of seen ads to the file for the next run.
-        if let Err(e) = save_seen_ads(&seen_ads_queue) {
-            eprintln!(
-                "Fehler beim Speichern der Datei mit gesehenen Anzeigen: {}",
-                e
+    // A search only counts as seeded once it got through while the chat was reachable.
+    if seeding && let Some(first_run) = &mut state.first_run {
+        let seeded: &[&str] = match report.chat_unreachable {
+            Some(_) => &[],
+            None => &report.succeeded,
+        };
+        let searches: Vec<&str> = profile.searches.iter().map(|search| search.name).collect();
+        first_run.record(seeded, &searches, FIRST_RUN_MAX_ATTEMPTS);
+        if !cli_args.no_persist {
+            save_first_run_marker(profile, first_run);
+        }
+        if !first_run.complete {
+            log!(
+                "Der erste Durchlauf ist unvollständig und wird beim nächsten Durchlauf fortgesetzt."
             );
         }
-    } else {
-        println!("Keine neuen Anzeigen auf den gescannten Seiten gefunden.");
     }
 
-    // Print final message and return success
-    println!("Skript beendet.");
-    Ok(())
+    // Ads released from the seller queue are delivered even when no new ones turned up.
+    if !cli_args.no_persist && (report.new_ads > 0 || !report.delivered.is_empty()) {
+        state.save(profile);
+    }
+    if !cli_args.no_persist {
+        for notifier in notifiers {
+            notifier.save_state();
+        }
+    }
+
+    // The ads delivered so far are saved; the misconfigured chat fails the run.
+    if let Some(e) = &report.chat_unreachable {
+        return Err(e.clone().into());
+    }
+
+    // Let the chat know once the backlog of an outage has been delivered.
+    if NOTIFY_RECOVERY {
+        let path = profile.state_file(RUN_STATE_FILE);
+        let previous = load_run_state(&path);
+        if let Some(count) = recovered(&previous, &report.delivered, &report.undelivered) {
+            let notice = format!("✅ Wieder normal, {} Anzeigen nachgeliefert", count);
+            let delivery = Delivery {
+                chat_ids: profile.default_chats(),
+                silent: false,
+            };
+            for notifier in notifiers {
+                if let Err(e) = notifier.send_notice(&notice, &delivery).await {
+                    log!(
+                        "Fehler beim Senden der Entwarnung über {}: {}",
+                        notifier.name(),
+                        e
+                    );
+                }
+            }
+        }
+        let run_state = RunState {
+            undelivered: report.undelivered.iter().cloned().collect(),
+        };
+        if !cli_args.no_persist
+            && let Err(e) = save_run_state(&path, &run_state)
+        {
+            log!("Fehler beim Speichern der Datei {}: {}", path, e);
+        }
+    }
+    Ok(report)
+}
+
+/// Re-checks the prices of the profile's watched listings, see `check_price_drops`.
+///
+/// Runs once per full scan, not with every search that is due on its own interval.
+async fn check_watched_ads(
+    client: &Client,
+    governor: &RateGovernor,
+    notifiers: &[AnyNotifier],
+    profile: &'static Profile,
+    cli_args: &CliArgs,
+) {
+    if cli_args.emit_json {
+        return;
+    }
+    check_price_drops(
+        client,
+        governor,
+        notifiers,
+        profile.watched_ads,
+        &profile.state_file(WATCHED_FILE),
+        profile.default_chats(),
+        !cli_args.no_persist,
+    )
+    .await;
+}
+
+/// Picks the searches whose interval has passed and schedules their next scan.
+///
+/// # Arguments
+/// * `searches` - The searches of a profile.
+/// * `next_due` - When each search is due next, updated for the picked ones.
+/// * `now` - The start of this scan.
+/// * `every_search` - Whether to pick every search regardless, e.g. for `/scan`.
+///
+/// # Returns
+/// The names of the picked searches.
+fn take_due_searches(
+    searches: &'static [Search],
+    next_due: &mut [Instant],
+    now: Instant,
+    every_search: bool,
+) -> HashSet<&'static str> {
+    let mut due = HashSet::new();
+    for (search, due_at) in searches.iter().zip(next_due.iter_mut()) {
+        if every_search || *due_at <= now {
+            due.insert(search.name);
+            *due_at = now + search.poll_interval();
+        }
+    }
+    due
+}
+
+/// Saves the profile's first-run marker, logging a failure.
+fn save_first_run_marker(profile: &Profile, first_run: &FirstRun) {
+    let path = profile.state_file(FIRST_RUN_FILE);
+    if let Err(e) = save_first_run(&path, first_run) {
+        log!("Fehler beim Speichern der Datei {}: {}", path, e);
+    }
+}
+
+/// Finds the new ads of every search, notifies about them and updates the seen state.
+///
+/// Nothing here touches the files, so the scan can run against scripted pages and notifiers.
+/// A search that fails to scrape is logged and skipped, so the others still get notified about.
+///
+/// With `SEND_CONCURRENCY = 1`, the default, the same pages always give the same notification
+/// sequence in the chat: ads released from the seller queue first, then the searches in their
+/// configured order, each in the order of `collect_new_ads`. An ad found by several searches
+/// keeps the place of the first one.
+///
+/// # Arguments
+/// * `source` - Where the result pages come from.
+/// * `governor` - The request budgets shared by scraping and sending.
+/// * `notifiers` - All channels new ads are announced on.
+/// * `profile` - The profile whose searches are scanned.
+/// * `cli_args` - The command-line options of this run.
+/// * `state` - The seen ads, updated with the handled ones and pruned.
+/// * `due_searches` - The names of the searches to scan, or `None` for all of them.
+///
+/// # Returns
+/// A report of the new ads and the searches that failed, or the first error if every search failed.
+async fn process_scan<N: Notifier, S: SeenStore>(
+    source: &impl PageSource,
+    governor: &RateGovernor,
+    notifiers: &[N],
+    profile: &'static Profile,
+    cli_args: &CliArgs,
+    state: &mut SeenState<S>,
+    due_searches: Option<&HashSet<&'static str>>,
+) -> Result<ScanReport, Box<dyn Error>> {
+    let SeenState {
+        store,
+        ever_seen,
+        ignored,
+        cursors,
+        recent_images,
+        messages,
+        sellers,
+        first_run,
+        strategy_changed,
+    } = state;
+    // Searches seeded by an earlier attempt of an interrupted first run are scanned normally.
+    let is_first_run = is_seeding(first_run.as_ref(), store.is_empty().await);
+    let first_run = &*first_run;
+    let seeds = &|search: &str| {
+        is_first_run
+            && first_run
+                .as_ref()
+                .is_none_or(|first_run| first_run.is_seeding(search))
+    };
+    // After switching DEDUP_STRATEGY, the live ads were all announced under their old keys.
+    let start_live =
+        is_first_run && (FIRST_RUN_MODE == FirstRunMode::StartLive || *strategy_changed);
+
+    let mut new_ads_found_total = 0;
+
+    // 1. Collect the new ads of every search. An ad found by several searches is
+    // only sent once, to the chats of all of them.
+    let mut new_ads: Vec<(Ad, Vec<&str>)> = Vec::new();
+    let mut index_by_key: HashMap<String, usize> = HashMap::new();
+    // The cursors before this run, and the keys of the new ads found by each search.
+    let mut previous_cursors: HashMap<&str, Option<String>> = HashMap::new();
+    let mut collected_keys: Vec<(String, &str)> = Vec::new();
+    let mut stats = ScrapeStats::default();
+    let mut report = ScanReport::default();
+    let mut first_error = None;
+    if SEARCH_SORT != SortOrder::Newest {
+        log!(
+            "Warnung: Die Suchergebnisse werden nicht nach Datum sortiert ({:?}). Da der Scan bei der ersten bekannten Anzeige endet, können neue Anzeigen übersprungen werden.",
+            SEARCH_SORT
+        );
+    }
+    for search in profile.searches {
+        // In watch mode, searches with a longer interval sit out some scans.
+        if due_searches.is_some_and(|due| !due.contains(search.name)) {
+            continue;
+        }
+        set_search(Some(search.name));
+        log!("Durchsuche '{}'.", search.name);
+        if let Some(sort) = search.fixed_sort_order() {
+            log!(
+                "Warnung: Die URL der Suche '{}' legt die Sortierung fest ({}), die Ergebnisse werden nicht nach Datum sortiert. Da der Scan bei der ersten bekannten Anzeige endet, können neue Anzeigen übersprungen werden.",
+                search.name,
+                sort
+            );
+        }
+        let mut cursor = cursors.get(search.name).cloned();
+        previous_cursors.insert(search.name, cursor.clone());
+        let search_ads = match collect_new_ads(
+            source,
+            governor,
+            search,
+            &mut cursor,
+            &*store,
+            ever_seen.as_ref(),
+            ignored,
+            &mut stats,
+            cli_args.warn_missing_images,
+            start_live && seeds(search.name),
+        )
+        .await
+        {
+            Ok(search_ads) => {
+                report.succeeded.push(search.name);
+                if let Some(cursor) = cursor {
+                    cursors.insert(search.name.to_string(), cursor);
+                }
+                search_ads
+            }
+            Err(e) => {
+                log!("Fehler bei der Suche '{}': {}", search.name, e);
+                report.failed.push((search.name, e.to_string()));
+                first_error.get_or_insert(e);
+                continue;
+            }
+        };
+        for ad in search_ads {
+            let key = DEDUP_STRATEGY.key(&ad);
+            collected_keys.push((key.clone(), search.name));
+            match index_by_key.get(&key) {
+                Some(&index) => {
+                    let chat_ids = &mut new_ads[index].1;
+                    for chat_id in search.target_chats(profile.default_chats()) {
+                        if !chat_ids.contains(chat_id) {
+                            chat_ids.push(chat_id);
+                        }
+                    }
+                }
+                None => {
+                    // An ad found by several searches keeps the prefix of the first one.
+                    let mut ad = ad;
+                    ad.prefix = search.prefix.or(CAPTION_PREFIX).map(str::to_string);
+                    ad.search = search.name;
+                    index_by_key.insert(key, new_ads.len());
+                    new_ads.push((ad, search.target_chats(profile.default_chats()).to_vec()));
+                }
+            }
+        }
+    }
+    set_search(None);
+
+    // Only give up if no search got through, which keeps e.g. a block detectable by the caller.
+    if report.succeeded.is_empty()
+        && let Some(e) = first_error
+    {
+        return Err(e);
+    }
+
+    if LOG_MISSING_IMAGE_SUMMARY && stats.missing_images > 0 {
+        log!(
+            "{} von {} gescannten Anzeigen ohne Bild.",
+            stats.missing_images,
+            stats.scraped
+        );
+    }
+
+    // Let the chat know if our selectors seem to miss, since that silently hides new ads.
+    if NOTIFY_LAYOUT_CHANGE
+        && !cli_args.emit_json
+        && let Some((url, warning)) = stats.layout_warnings.first()
+    {
+        let notice = format!(
+            "⚠️ Layoutänderung bei Kleinanzeigen vermutet ({} Seite(n) betroffen): {}\n{}",
+            stats.layout_warnings.len(),
+            warning,
+            url
+        );
+        let delivery = Delivery {
+            chat_ids: profile.default_chats(),
+            silent: false,
+        };
+        for notifier in notifiers {
+            if let Err(e) = notifier.send_notice(&notice, &delivery).await {
+                log!(
+                    "Fehler beim Senden der Warnung über {}: {}",
+                    notifier.name(),
+                    e
+                );
+            }
+        }
+    }
+
+    // In JSON mode, print the new ads for downstream tools and skip sending entirely.
+    // The seen list is left untouched, so a later normal run still notifies about them.
+    if cli_args.emit_json {
+        let ads: Vec<&Ad> = new_ads.iter().map(|(ad, _)| ad).collect();
+        println!("{}", to_string_pretty(&ads)?);
+        report.new_ads = new_ads.len();
+        return Ok(report);
+    }
+
+    // Starting live, the freshest ads are only marked as seen, so later runs notify about newer ones.
+    // The overlap pages are seeded too, since the next run scans them after its first known ad.
+    if start_live {
+        let (seeded, rest): (Vec<_>, Vec<_>) =
+            new_ads.into_iter().partition(|(ad, _)| seeds(ad.search));
+        for (ad, _) in &seeded {
+            mark_seen(store, ever_seen.as_mut(), ad).await;
+        }
+        *strategy_changed = false;
+        log!(
+            "Erster Durchlauf: {} Anzeigen der ersten Seite(n) als gesehen markiert, es wird nichts gesendet.",
+            seeded.len()
+        );
+        if rest.is_empty() {
+            report.new_ads = seeded.len();
+            return Ok(report);
+        }
+        new_ads_found_total += seeded.len();
+        new_ads = rest;
+    }
+
+    // With `--diagnose`, record why each skipped or failed ad wasn't delivered.
+    let diagnostics_file = profile.state_file(DIAGNOSTICS_FILE);
+    let mut diagnostics = Diagnostics::open(cli_args.diagnose.then_some(&diagnostics_file));
+
+    // 2. Decide which ads to send, marking skipped ones as seen right away
+    let mut ads_to_send: Vec<(Ad, Vec<&str>)> = Vec::new();
+    let mut images_this_run = HashSet::new();
+    // The ads collapsed into each kept one, marked as seen once it was delivered.
+    let mut collapsed: HashMap<String, Vec<Ad>> = HashMap::new();
+    for (mut ad, chat_ids) in new_ads {
+        new_ads_found_total += 1;
+        log!(ad = &ad.id; "Neue Anzeige gefunden: {}", ad.title);
+
+        // Skip ads that can't be shipped if only shippable items are wanted.
+        if !SHIPPING_FILTER.allows(ad.shipping) {
+            log!(
+                ad = &ad.id;
+                "Anzeige '{}' wird wegen des Versandfilters übersprungen.",
+                ad.title
+            );
+            diagnostics.record(
+                &ad,
+                SkipReason::FilteredShipping,
+                &format!("{:?}, Versand: {:?}", SHIPPING_FILTER, ad.shipping),
+            );
+            mark_seen(store, ever_seen.as_mut(), &ad).await;
+            continue;
+        }
+
+        // Skip paid promotions if only organic posts are wanted.
+        if SKIP_PROMOTED && ad.promoted {
+            log!(
+                ad = &ad.id;
+                "Anzeige '{}' ist eine bezahlte Hervorhebung und wird übersprungen.",
+                ad.title
+            );
+            diagnostics.record(&ad, SkipReason::FilteredPromoted, "SKIP_PROMOTED");
+            mark_seen(store, ever_seen.as_mut(), &ad).await;
+            continue;
+        }
+
+        // Skip ads that already drew a lot of attention.
+        if let Some(max_views) = MAX_VIEWS
+            && let Some(views) = ad.views
+            && views > max_views
+        {
+            log!(
+                ad = &ad.id;
+                "Anzeige '{}' wurde bereits {} Mal aufgerufen und wird übersprungen.",
+                ad.title,
+                views
+            );
+            diagnostics.record(
+                &ad,
+                SkipReason::FilteredViews,
+                &format!("{} Aufrufe", views),
+            );
+            mark_seen(store, ever_seen.as_mut(), &ad).await;
+            continue;
+        }
+
+        // Skip ads in a condition that isn't wanted.
+        if !ALLOWED_CONDITIONS.is_empty()
+            && let Some(condition) = &ad.condition
+            && !ALLOWED_CONDITIONS
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(condition))
+        {
+            log!(
+                ad = &ad.id;
+                "Anzeige '{}' ist im Zustand '{}' und wird übersprungen.",
+                ad.title,
+                condition
+            );
+            diagnostics.record(&ad, SkipReason::FilteredCondition, condition);
+            mark_seen(store, ever_seen.as_mut(), &ad).await;
+            continue;
+        }
+
+        // Skip or label ads with a negotiable price, as configured.
+        if ad.negotiable {
+            match NEGOTIABLE_HANDLING {
+                NegotiableHandling::Send => {}
+                NegotiableHandling::Label => ad.labels.push("🤝 Preis verhandelbar".to_string()),
+                NegotiableHandling::FixedPriceOnly => {
+                    log!(
+                        ad = &ad.id;
+                        "Anzeige '{}' hat keinen Festpreis (VB) und wird übersprungen.",
+                        ad.title
+                    );
+                    diagnostics.record(&ad, SkipReason::FilteredNegotiable, "VB");
+                    mark_seen(store, ever_seen.as_mut(), &ad).await;
+                    continue;
+                }
+            }
+        }
+
+        // Skip relisted items whose photo was already announced, recently or earlier in this run.
+        if let Some(images) = recent_images.as_ref()
+            && let Some(image_url) = &ad.image_url
+            && (images.contains(image_url)
+                || !images_this_run.insert(base_image_url(image_url).to_string()))
+        {
+            log!(
+                ad = &ad.id;
+                "Anzeige '{}' hat dasselbe Foto wie eine kürzlich gemeldete Anzeige und wird übersprungen.",
+                ad.title
+            );
+            diagnostics.record(&ad, SkipReason::DuplicateImage, base_image_url(image_url));
+            mark_seen(store, ever_seen.as_mut(), &ad).await;
+            continue;
+        }
+
+        // Skip ads that don't resemble anything on the wishlist.
+        if let Some((entry, score)) = best_wishlist_match(&ad.title, WISHLIST) {
+            if score < WISHLIST_MIN_SCORE {
+                log!(
+                    ad = &ad.id;
+                    "Anzeige '{}' passt nicht zur Wunschliste (beste Übereinstimmung '{}' mit {:.0} %) und wird übersprungen.",
+                    ad.title,
+                    entry,
+                    score * 100.0
+                );
+                diagnostics.record(
+                    &ad,
+                    SkipReason::FilteredWishlist,
+                    &format!("'{}' mit {:.2}", entry, score),
+                );
+                mark_seen(store, ever_seen.as_mut(), &ad).await;
+                continue;
+            }
+            ad.labels.push(format!(
+                "🎯 Wunschliste: {} ({:.0} %)",
+                entry,
+                score * 100.0
+            ));
+            mark_top_match(&mut ad, score, TOP_MATCH_SCORE);
+        }
+
+        // Handle likely scam bait according to the configuration.
+        if let Some(keyword) = matches_any_keyword(&ad.title, SCAM_KEYWORDS) {
+            match SCAM_HANDLING {
+                ScamHandling::Skip => {
+                    log!(
+                        ad = &ad.id;
+                        "Anzeige '{}' enthält das Betrugs-Stichwort '{}' und wird übersprungen.",
+                        ad.title, keyword
+                    );
+                    diagnostics.record(&ad, SkipReason::FilteredKeyword, keyword);
+
+                    // Mark it as seen so it doesn't come back on the next run.
+                    mark_seen(store, ever_seen.as_mut(), &ad).await;
+                    continue;
+                }
+                ScamHandling::Warn => ad.labels.push("⚠️ Möglicher Betrug".to_string()),
+            }
+        }
+
+        // Collapse bulk postings into the first of them.
+        if let Some(threshold) = COLLAPSE_SIMILAR_TITLES
+            && let Some(index) = ads_to_send.iter().position(|(kept, kept_chats)| {
+                *kept_chats == chat_ids && titles_similar(&kept.title, &ad.title, threshold)
+            })
+        {
+            let kept = &ads_to_send[index].0;
+            log!(
+                ad = &ad.id;
+                "Anzeige '{}' ähnelt '{}' und wird mit ihr zusammengefasst.",
+                ad.title,
+                kept.title
+            );
+            diagnostics.record(&ad, SkipReason::SimilarTitle, &kept.id);
+            collapsed.entry(kept.id.clone()).or_default().push(ad);
+            continue;
+        }
+
+        ads_to_send.push((ad, chat_ids));
+    }
+    for (ad, _) in &mut ads_to_send {
+        if let Some(similar) = collapsed.get(&ad.id) {
+            ad.labels.push(format!("(+{} ähnliche)", similar.len()));
+        }
+    }
+
+    // Held-back ads whose seller may be notified about again go first, since they are the oldest.
+    // They are released before the new ads are scheduled, so their sellers' turns go to them.
+    let now = unix_now();
+    let mut seller_by_ad: HashMap<String, String> = HashMap::new();
+    let mut released = Vec::new();
+    if let Some(interval) = SELLER_MIN_INTERVAL_SECS
+        && let Some(queue) = sellers.as_mut()
+    {
+        released = queue.release(now, interval);
+        if !released.is_empty() || queue.len() > 0 {
+            log!(
+                "{} zurückgehaltene Anzeige(n) werden jetzt gesendet, {} warten noch.",
+                released.len(),
+                queue.len()
+            );
+        }
+    }
+    // The search and chats of each released ad, for putting it back if it isn't delivered.
+    let mut released_by_id: HashMap<String, (String, Vec<String>)> = HashMap::new();
+    let mut released_ads = Vec::with_capacity(released.len());
+    let mut released_chats = Vec::with_capacity(released.len());
+    for queued in released {
+        let mut ad = queued.ad;
+        ad.search = profile
+            .searches
+            .iter()
+            .find(|search| search.name == queued.search)
+            .map_or("", |search| search.name);
+        seller_by_ad.insert(ad.id.clone(), queued.seller);
+        released_by_id.insert(ad.id.clone(), (queued.search, queued.chat_ids.clone()));
+        released_ads.push(ad);
+        released_chats.push(queued.chat_ids);
+    }
+
+    // Only the ads about to be sent are worth the extra requests for their seller.
+    let filter_sellers = MIN_SELLER_LISTINGS.is_some() || MAX_SELLER_LISTINGS.is_some();
+    if filter_sellers || SELLER_MIN_INTERVAL_SECS.is_some() || !BLOCKED_SELLERS.is_empty() {
+        let mut listings_by_seller: HashMap<String, Option<usize>> = HashMap::new();
+        let mut kept = Vec::with_capacity(ads_to_send.len());
+        for (ad, chat_ids) in ads_to_send {
+            let Some(seller) = seller_of(source, governor, &ad).await else {
+                kept.push((ad, chat_ids));
+                continue;
+            };
+
+            // Never announce the listings of a blocked seller.
+            if let Some(entry) = BLOCKED_SELLERS.iter().find(|entry| seller.matches(entry)) {
+                log!(
+                    ad = &ad.id;
+                    "Anzeige '{}' wird übersprungen, der Verkäufer '{}' ist blockiert.",
+                    ad.title,
+                    entry
+                );
+                diagnostics.record(&ad, SkipReason::BlockedSeller, entry);
+                mark_seen(store, ever_seen.as_mut(), &ad).await;
+                continue;
+            }
+
+            let seller = seller.url;
+            let listings = match filter_sellers {
+                true => seller_listings(source, governor, &seller, &mut listings_by_seller).await,
+                false => None,
+            };
+            if let Some(listings) = listings
+                && (MIN_SELLER_LISTINGS.is_some_and(|min| listings < min)
+                    || MAX_SELLER_LISTINGS.is_some_and(|max| listings > max))
+            {
+                log!(
+                    ad = &ad.id;
+                    "Anzeige '{}' wird übersprungen, der Verkäufer hat {} aktive Anzeigen.",
+                    ad.title,
+                    listings
+                );
+                diagnostics.record(
+                    &ad,
+                    SkipReason::FilteredSeller,
+                    &format!("{} aktive Anzeigen", listings),
+                );
+                mark_seen(store, ever_seen.as_mut(), &ad).await;
+                continue;
+            }
+
+            // Hold the ad back if this seller had their turn too recently. It is marked as seen,
+            // so only the queue brings it back.
+            if let Some(interval) = SELLER_MIN_INTERVAL_SECS
+                && let Some(queue) = sellers.as_mut()
+                && !queue.try_schedule(&seller, now, interval)
+            {
+                log!(
+                    ad = &ad.id;
+                    "Anzeige '{}' wird zurückgehalten, derselbe Verkäufer wurde vor Kurzem gemeldet.",
+                    ad.title
+                );
+                diagnostics.record(&ad, SkipReason::SellerQueued, &seller);
+                mark_seen(store, ever_seen.as_mut(), &ad).await;
+                queue.enqueue(QueuedAd {
+                    search: ad.search.to_string(),
+                    chat_ids: chat_ids.iter().map(|chat_id| chat_id.to_string()).collect(),
+                    seller,
+                    ad,
+                });
+                continue;
+            }
+            seller_by_ad.insert(ad.id.clone(), seller);
+            kept.push((ad, chat_ids));
+        }
+        ads_to_send = kept;
+    }
+    let released_ads = released_ads.into_iter().zip(
+        released_chats
+            .iter()
+            .map(|chat_ids| chat_ids.iter().map(String::as_str).collect()),
+    );
+    ads_to_send.splice(0..0, released_ads);
+
+    // With `--limit`, cap the sends of this run, whether it's the first one or not, released ads
+    // included. New ads over the limit stay unseen and are sent by later runs, released ones go
+    // back into the seller queue.
+    if let Some(limit) = cli_args.limit
+        && ads_to_send.len() > limit
+    {
+        let mut held_back = Vec::new();
+        for (ad, _) in ads_to_send.split_off(limit) {
+            diagnostics.record(&ad, SkipReason::CapReached, &format!("--limit {}", limit));
+            match released_by_id.remove(&ad.id) {
+                Some((search, chat_ids)) => held_back.push(QueuedAd {
+                    seller: seller_by_ad.remove(&ad.id).unwrap_or_default(),
+                    ad,
+                    search,
+                    chat_ids,
+                }),
+                None => new_ads_found_total -= 1,
+            }
+        }
+        if let Some(queue) = sellers.as_mut() {
+            queue.put_back(held_back);
+        }
+    }
+
+    // Only the ads about to be sent are worth the extra request for their description.
+    if FETCH_DESCRIPTIONS {
+        for (ad, _) in &mut ads_to_send {
+            governor.acquire_url(&ad.link).await;
+            match source
+                .fetch_description(&ad.link, DESCRIPTION_SNIPPET_CHARS)
+                .await
+            {
+                Ok(description) => ad.description = description,
+                Err(e) => log!(
+                    ad = &ad.id;
+                    "Fehler beim Abrufen der Beschreibung von {}: {}",
+                    ad.link, e
+                ),
+            }
+        }
+    }
+
+    // 3. Send notifications for the selected ads, up to SEND_CONCURRENCY at a time.
+    // `buffered` yields the results in the original order, so the seen list keeps it too.
+    // Once too many deliveries failed in a row, the rest is left for the next run.
+    let breaker = &CircuitBreaker::new(MAX_CONSECUTIVE_SEND_FAILURES);
+    // On the first run, only delivered ads count towards FIRST_RUN_LIMIT, those in flight included.
+    let first_run_slots = &Cell::new(0);
+    let mut sends = stream::iter(ads_to_send.into_iter().enumerate())
+        .map(|(index, (ad, chat_ids))| async move {
+            if breaker.is_open() {
+                return (ad, SendOutcome::CircuitOpen);
+            }
+            let seeding = seeds(ad.search);
+            if seeding {
+                if first_run_slots.get() >= FIRST_RUN_LIMIT {
+                    return (ad, SendOutcome::CapReached);
+                }
+                first_run_slots.set(first_run_slots.get() + 1);
+            }
+            let delivery = Delivery {
+                chat_ids: &chat_ids,
+                silent: ALWAYS_SILENT || (seeding && SILENT_FIRST_RUN),
+            };
+
+            // Space out the ads to avoid hitting Telegram's rate limits, more so in a long burst
+            // and right after startup. Every Telegram request takes its own slot of the budget,
+            // so the other channels aren't paced by it.
+            let extra = SEND_DELAY_RAMP.extra_delay(index + 1)
+                + STARTUP_THROTTLE.extra_delay(governor.uptime());
+            governor.hold_back(TELEGRAM_API_HOST, extra);
+            let outcome = match deliver(notifiers, &ad, &delivery).await {
+                Ok(posted) => {
+                    if breaker.record(posted.is_some()) {
+                        log!(
+                            "{} Anzeigen in Folge konnten nicht zugestellt werden, Telegram scheint nicht erreichbar. Die übrigen Anzeigen werden beim nächsten Durchlauf gesendet.",
+                            MAX_CONSECUTIVE_SEND_FAILURES
+                        );
+                    }
+                    SendOutcome::Sent(posted)
+                }
+                // Every further ad would fail the same way, so stop right away.
+                Err(e) => {
+                    if breaker.trip() {
+                        log!(
+                            "Fehler: {}. Der Durchlauf wird abgebrochen, die übrigen Anzeigen bleiben ungesehen.",
+                            e
+                        );
+                    }
+                    SendOutcome::ChatUnreachable(e.to_string())
+                }
+            };
+            // A failed send frees its slot of the first-run limit for a later ad.
+            if seeding && !matches!(outcome, SendOutcome::Sent(Some(_))) {
+                first_run_slots.set(first_run_slots.get() - 1);
+            }
+            (ad, outcome)
+        })
+        .buffered(SEND_CONCURRENCY.max(1));
+
+    let mut requeued = Vec::new();
+    while let Some((ad, outcome)) = sends.next().await {
+        // A released ad is already marked as seen, so one that isn't delivered goes back into
+        // the seller queue instead of being lost.
+        if !matches!(outcome, SendOutcome::Sent(Some(_)))
+            && let Some((search, chat_ids)) = released_by_id.remove(&ad.id)
+            && let Some(seller) = seller_by_ad.get(&ad.id)
+        {
+            requeued.push(QueuedAd {
+                ad: ad.clone(),
+                search,
+                chat_ids,
+                seller: seller.clone(),
+            });
+        }
+
+        let posted = match outcome {
+            SendOutcome::Sent(posted) => posted,
+            SendOutcome::ChatUnreachable(e) => {
+                report.chat_unreachable = Some(e);
+                None
+            }
+            SendOutcome::CircuitOpen => {
+                // Skipped because the breaker is open; stays unseen for the next run.
+                diagnostics.record(
+                    &ad,
+                    SkipReason::CircuitOpen,
+                    &format!(
+                        "{} Zustellungen in Folge fehlgeschlagen",
+                        MAX_CONSECUTIVE_SEND_FAILURES
+                    ),
+                );
+                report.undelivered.push(ad.id);
+                continue;
+            }
+            SendOutcome::CapReached => {
+                // The rest of the first run stays unseen, as before the limit was reached.
+                diagnostics.record(
+                    &ad,
+                    SkipReason::CapReached,
+                    &format!("FIRST_RUN_LIMIT = {}", FIRST_RUN_LIMIT),
+                );
+                continue;
+            }
+        };
+
+        // Only mark the ad as seen if sending was successful
+        if let Some(posted) = posted {
+            // Add the new ad's ID to our queue to preserve order.
+            mark_seen(store, ever_seen.as_mut(), &ad).await;
+            for similar in collapsed.remove(&ad.id).unwrap_or_default() {
+                mark_seen(store, ever_seen.as_mut(), &similar).await;
+            }
+            if let Some(images) = recent_images.as_mut()
+                && let Some(image_url) = &ad.image_url
+            {
+                images.insert(image_url, MAX_RECENT_IMAGES);
+            }
+            if let Some(messages) = messages.as_mut() {
+                messages.record(ad.search, &ad.id, &posted, MAX_INDEXED_MESSAGES);
+            }
+            if let Some(interval) = SELLER_MIN_INTERVAL_SECS
+                && let Some(queue) = sellers.as_mut()
+                && let Some(seller) = seller_by_ad.get(&ad.id)
+            {
+                queue.record_sent(seller, unix_now(), interval);
+            }
+            report.delivered.push(ad.id);
+        } else {
+            log!(
+                ad = &ad.id;
+                "Nachricht für Anzeige '{}' wurde nicht erfolgreich gesendet und wird erneut versucht beim nächsten Durchlauf.",
+                ad.title
+            );
+            diagnostics.record(
+                &ad,
+                SkipReason::SendFailed,
+                "Alle Kanäle fehlgeschlagen, siehe Protokoll",
+            );
+            report.undelivered.push(ad.id);
+        }
+    }
+
+    if let Some(queue) = sellers.as_mut() {
+        queue.put_back(requeued);
+    }
+
+    // A search whose new ads aren't all handled keeps its old cursor, so the cursor doesn't hide
+    // the ones left for the next run.
+    // If the seen store can't tell, the ad counts as left over.
+    for (key, search_name) in &collected_keys {
+        if !store.contains(key).await.unwrap_or(false) {
+            match previous_cursors.get(search_name) {
+                Some(Some(previous)) => cursors.insert(search_name.to_string(), previous.clone()),
+                _ => cursors.remove(*search_name),
+            };
+        }
+    }
+
+    // After scanning, check if we found any new ads.
+    if new_ads_found_total > 0 {
+        log!(
+            "Verarbeitung abgeschlossen. Insgesamt {} neue Anzeige(n) gefunden.",
+            new_ads_found_total
+        );
+
+        // --- PRUNING LOGIC ---
+        // Remove the oldest entries so the list doesn't grow without bound.
+        let mut removed = store.prune(PRUNE_MODE, MAX_SEEN_ADS, MAX_SEEN_AGE_DAYS);
+        // Only a scan of every search knows all live ads; a skipped or failed search would
+        // have its still listed ads expire and announced again.
+        if let Some(ttl_days) = SEEN_TTL_DAYS
+            && report.succeeded.len() == profile.searches.len()
+        {
+            removed += store.expire(ttl_days, &stats.live_keys);
+        }
+        match store.len() {
+            Some(len) => log!(
+                "{} alte Einträge entfernt, die Liste der gesehenen Anzeigen enthält jetzt {} Einträge.",
+                removed,
+                len
+            ),
+            None => log!("{} alte Einträge entfernt.", removed),
+        }
+    } else {
+        log!("Keine neuen Anzeigen auf den gescannten Seiten gefunden.");
+    }
+
+    report.new_ads = new_ads_found_total;
+    if !report.failed.is_empty() {
+        log!("{}", report.summary());
+    }
+    Ok(report)
+}
+
+/// The outcome of `--healthcheck`, printed as JSON to stdout.
+#[derive(Debug, Default, Serialize)]
+struct HealthReport {
+    /// Whether the first page of the first search was scraped and contained at least one ad.
+    scrape_ok: bool,
+    /// The number of ads parsed from that page.
+    ads_parsed: usize,
+    /// Why scraping failed, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scrape_error: Option<String>,
+    /// Whether the silent test message reached every configured channel.
+    send_ok: bool,
+    /// Why sending failed, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    send_error: Option<String>,
+}
+
+/// Checks the whole pipeline once: scrapes the first result page and sends a silent test message.
+///
+/// Nothing is marked as seen, so the check can run alongside the regular scans.
+/// The test message is sent to the chat of every profile.
+///
+/// # Returns
+/// The report of both steps, healthy only if both succeeded.
+async fn healthcheck(
+    client: &Client,
+    profiles: &[(&'static Profile, Vec<AnyNotifier>)],
+) -> HealthReport {
+    let mut report = HealthReport::default();
+
+    match profiles
+        .iter()
+        .find_map(|(profile, _)| profile.searches.first())
+    {
+        Some(search) => match scrape_kleinanzeigen_page(client, &search.page_url(1)).await {
+            Ok(page) => {
+                report.ads_parsed = page.ads.len();
+                report.scrape_ok = !page.ads.is_empty();
+                if !report.scrape_ok {
+                    report.scrape_error =
+                        Some(page.layout_warning.unwrap_or_else(|| {
+                            "Keine Anzeigen auf der Seite gefunden".to_string()
+                        }));
+                }
+            }
+            Err(e) => report.scrape_error = Some(e.to_string()),
+        },
+        None => report.scrape_error = Some("Keine Suche konfiguriert".to_string()),
+    }
+
+    let mut errors = Vec::new();
+    for (profile, notifiers) in profiles {
+        let delivery = Delivery {
+            chat_ids: profile.default_chats(),
+            silent: true,
+        };
+        for notifier in notifiers {
+            if let Err(e) = notifier
+                .send_notice("✅ Healthcheck: Der Bot ist einsatzbereit.", &delivery)
+                .await
+            {
+                errors.push(format!("{}: {}", notifier.name(), e));
+            }
+        }
+    }
+    report.send_ok = errors.is_empty();
+    if !report.send_ok {
+        report.send_error = Some(errors.join("; "));
+    }
+    report
+}
+
+/// Flags an ad as a top match if its wishlist score reaches `threshold`, which is
+/// `TOP_MATCH_SCORE`, putting "🔥 TOP-TREFFER" in front of its other labels.
+fn mark_top_match(ad: &mut Ad, score: f64, threshold: Option<f64>) {
+    if threshold.is_some_and(|threshold| score >= threshold) {
+        ad.top_match = true;
+        ad.labels.insert(0, "🔥 TOP-TREFFER".to_string());
+    }
+}
+
+/// Fetches a single listing and sends it once with the caption a scan would build, for trying
+/// out caption changes on a particular ad.
+///
+/// The filters are not applied, but the labels they add are. Nothing is marked as seen.
+///
+/// # Arguments
+/// * `client` - The `reqwest::Client` used for scraping.
+/// * `url` - The link to the listing.
+/// * `profile` - The profile whose chats the ad is sent to.
+/// * `notifiers` - The channels of that profile.
+async fn send_single_ad(
+    client: &Client,
+    url: &str,
+    profile: &'static Profile,
+    notifiers: &[AnyNotifier],
+) -> Result<(), Box<dyn Error>> {
+    let mut ad = scrape_ad(client, url).await?;
+    ad.prefix = CAPTION_PREFIX.map(str::to_string);
+    if ad.negotiable && NEGOTIABLE_HANDLING == NegotiableHandling::Label {
+        ad.labels.push("🤝 Preis verhandelbar".to_string());
+    }
+    if let Some((entry, score)) = best_wishlist_match(&ad.title, WISHLIST)
+        && score >= WISHLIST_MIN_SCORE
+    {
+        ad.labels.push(format!(
+            "🎯 Wunschliste: {} ({:.0} %)",
+            entry,
+            score * 100.0
+        ));
+        mark_top_match(&mut ad, score, TOP_MATCH_SCORE);
+    }
+    if SCAM_HANDLING == ScamHandling::Warn
+        && matches_any_keyword(&ad.title, SCAM_KEYWORDS).is_some()
+    {
+        ad.labels.push("⚠️ Möglicher Betrug".to_string());
+    }
+    if FETCH_DESCRIPTIONS {
+        ad.description = client
+            .fetch_description(&ad.link, DESCRIPTION_SNIPPET_CHARS)
+            .await?;
+    }
+
+    let delivery = Delivery {
+        chat_ids: profile.default_chats(),
+        silent: ALWAYS_SILENT,
+    };
+    if deliver(notifiers, &ad, &delivery).await?.is_none() {
+        return Err(format!("Anzeige '{}' konnte nicht gesendet werden", ad.title).into());
+    }
+    log!(ad = &ad.id; "Anzeige '{}' gesendet.", ad.title);
+    Ok(())
+}
+
+/// Writes the profile's current seen ads to `path` in the versioned format, for moving them to another machine.
+fn export_seen(path: &str, profile: &Profile, force_reseed: bool) -> Result<(), Box<dyn Error>> {
+    let (seen_ads_queue, _) = load_seen_ads(
+        &profile.state_file(SEEN_ADS_FILE),
+        DEDUP_STRATEGY,
+        force_reseed,
+    )?;
+    save_seen_ads(path, &seen_ads_queue, DEDUP_STRATEGY)?;
+    log!(
+        "{} gesehene Anzeigen nach {} exportiert.",
+        seen_ads_queue.len(),
+        path
+    );
+    Ok(())
+}
+
+/// Deletes every recorded Telegram message of a search from the profile's chats, see `RECORD_MESSAGE_IDS`.
+///
+/// Messages Telegram can't delete anymore, e.g. because they are older than 48 hours, are dropped
+/// from the index; ones that failed for another reason are kept for the next attempt.
+async fn delete_search_messages(
+    notifier: &TelegramNotifier,
+    profile: &Profile,
+    search: &str,
+) -> Result<(), Box<dyn Error>> {
+    let path = profile.state_file(MESSAGE_INDEX_FILE);
+    let mut index = MessageIndex::load(&path);
+    let messages = index.take_search(search);
+    let total = messages.len();
+    let mut deleted = 0;
+    let mut failed = Vec::new();
+    for message in messages {
+        match notifier
+            .delete_message(&message.chat_id, message.message_id)
+            .await
+        {
+            Ok(true) => deleted += 1,
+            Ok(false) => {}
+            Err(e) => {
+                log!(
+                    "Fehler beim Löschen der Nachricht {} in Chat {}: {}",
+                    message.message_id,
+                    message.chat_id,
+                    e
+                );
+                failed.push(message);
+            }
+        }
+    }
+    let failed_count = failed.len();
+    index.restore(failed);
+    index.save(&path)?;
+    log!(
+        "{} von {} Nachrichten der Suche '{}' gelöscht, {} zu alt oder bereits entfernt, {} fehlgeschlagen.",
+        deleted,
+        total,
+        search,
+        total - deleted - failed_count,
+        failed_count
+    );
+    Ok(())
+}
+
+/// Merges the seen ads exported to `path` into the profile's seen list, keeping it within the pruning limits.
+fn import_seen(path: &str, profile: &Profile, force_reseed: bool) -> Result<(), Box<dyn Error>> {
+    let imported = read_seen_ads(path, DEDUP_STRATEGY)?;
+    let seen_ads_file = profile.state_file(SEEN_ADS_FILE);
+    let (mut seen_ads_queue, _) = load_seen_ads(&seen_ads_file, DEDUP_STRATEGY, force_reseed)?;
+    let imported_ids: Vec<String> = imported.iter().map(|entry| entry.id.clone()).collect();
+    let added = merge_seen_ads(&mut seen_ads_queue, imported);
+    let removed = prune_seen_ads(
+        &mut seen_ads_queue,
+        PRUNE_MODE,
+        MAX_SEEN_ADS,
+        MAX_SEEN_AGE_DAYS,
+    );
+    save_seen_ads(&seen_ads_file, &seen_ads_queue, DEDUP_STRATEGY)?;
+
+    // Pruned IDs are still remembered by the long-lived filter.
+    if ENABLE_EVER_SEEN_FILTER {
+        let ever_seen_file = profile.state_file(EVER_SEEN_FILE);
+        let mut filter = BloomFilter::load(
+            &ever_seen_file,
+            EVER_SEEN_CAPACITY,
+            EVER_SEEN_FALSE_POSITIVE_RATE,
+        );
+        for id in &imported_ids {
+            if !filter.contains(id) {
+                filter.insert(id);
+            }
+        }
+        filter.save(&ever_seen_file)?;
+    }
+
+    log!(
+        "{} neue Anzeigen aus {} importiert, {} alte Einträge entfernt. Die Liste enthält jetzt {} Einträge.",
+        added,
+        path,
+        removed,
+        seen_ads_queue.len()
+    );
+    Ok(())
+}
+
+/// Collects `SCRAPE_HEADERS` for the scraping client.
+///
+/// # Returns
+/// The headers, or a message naming the first one that isn't a valid header.
+fn scrape_headers() -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+    for &(name, value) in SCRAPE_HEADERS {
+        let header = HeaderName::from_bytes(name.as_bytes())
+            .ok()
+            .zip(HeaderValue::from_str(value).ok())
+            .ok_or_else(|| format!("Ungültiger Header '{}: {}'", name, value))?;
+        headers.insert(header.0, header.1);
+    }
+    Ok(headers)
+}
+
+/// Builds the HTTP client used for all Telegram API calls.
+///
+/// If `TELEGRAM_SOCKS5_PROXY` is set, every Telegram request is routed through it,
+/// while scraping keeps using its own direct client.
+///
+/// # Returns
+/// The client, or an error if the proxy is invalid or SOCKS support wasn't compiled in.
+fn build_telegram_client() -> Result<Client, Box<dyn Error>> {
+    let builder = Client::builder();
+
+    let Some(proxy_url) = TELEGRAM_SOCKS5_PROXY else {
+        return Ok(builder.build()?);
+    };
+
+    #[cfg(feature = "socks")]
+    {
+        log!(
+            "Telegram-Anfragen laufen über den SOCKS5-Proxy {}.",
+            proxy_url
+        );
+        Ok(builder.proxy(reqwest::Proxy::all(proxy_url)?).build()?)
+    }
+
+    #[cfg(not(feature = "socks"))]
+    {
+        Err(format!(
+            "TELEGRAM_SOCKS5_PROXY ist auf {} gesetzt, aber der Bot wurde ohne SOCKS-Unterstützung kompiliert. Bitte mit `cargo build --release --features socks` neu kompilieren.",
+            proxy_url
+        )
+        .into())
+    }
+}
+
+/// Sends a notice such as an alert or the daily summary through every notifier of a profile.
+///
+/// # Returns
+/// `true` if at least one notifier delivered it.
+async fn broadcast_notice(
+    profile: &'static Profile,
+    notifiers: &[AnyNotifier],
+    text: &str,
+) -> bool {
+    let delivery = Delivery {
+        chat_ids: profile.default_chats(),
+        silent: false,
+    };
+    let mut delivered = false;
+    for notifier in notifiers {
+        match notifier.send_notice(text, &delivery).await {
+            Ok(()) => delivered = true,
+            Err(e) => log!(
+                "Fehler beim Senden der Mitteilung über {}: {}",
+                notifier.name(),
+                e
+            ),
+        }
+    }
+    delivered
+}
+
+/// Sends each profile's daily summary once its time has passed, then counts anew.
+///
+/// A summary that didn't go out keeps its counts and is tried again by the next call.
+async fn send_daily_summaries(
+    profiles: &[(&'static Profile, Vec<AnyNotifier>)],
+    daily_stats: &mut [DailyStats],
+    summary_at: Option<NaiveTime>,
+    cli_args: &CliArgs,
+) {
+    let Some(at) = summary_at else {
+        return;
+    };
+    let now = TIMEZONE.now();
+    for ((profile, notifiers), stats) in profiles.iter().zip(daily_stats) {
+        if stats.is_due(now, at) && broadcast_notice(profile, notifiers, &stats.message()).await {
+            stats.reset(now);
+        }
+        if !cli_args.no_persist
+            && let Err(e) = save_daily_stats(&profile.state_file(SUMMARY_FILE), stats)
+        {
+            log!("Fehler beim Speichern der Tagesstatistik: {}", e);
+        }
+    }
+}
+
+/// Keeps the bot running, scanning each search every `POLL_INTERVAL_SECS` seconds or at its own
+/// `poll_interval_secs`.
+///
+/// If Telegram commands are enabled, a `/scan` message in a profile's chat
+/// triggers an immediate scan, and the bot replies there with the number of new ads.
+/// With `DAILY_SUMMARY_AT` set, a summary of the day's counts is sent once a day.
+///
+/// # Arguments
+/// * `scrape_client` - The `reqwest::Client` used for scraping.
+/// * `telegram_client` - The `reqwest::Client` used for the Telegram API.
+/// * `governor` - The request budgets shared by scraping and sending.
+/// * `profiles` - The profiles to scan, each with all channels its ads are announced on.
+/// * `telegram_throttle` - The rate limiter shared by all Telegram sends.
+/// * `cli_args` - The command-line options of this run.
+async fn watch(
+    scrape_client: &Client,
+    telegram_client: &Client,
+    governor: &Arc<RateGovernor>,
+    profiles: &[(&'static Profile, Vec<AnyNotifier>)],
+    telegram_throttle: Arc<Throttle>,
+    cli_args: &CliArgs,
+) {
+    // Every profile listens to its own bot; commands are tagged with the profile's index.
+    let (command_sender, mut command_receiver) = mpsc::channel(8);
+    for (index, (profile, _)) in profiles.iter().enumerate() {
+        if ENABLE_TELEGRAM_COMMANDS && profile.telegram_configured() {
+            spawn(listen_for_commands(
+                telegram_client.clone(),
+                profile.telegram_bot_token.to_string(),
+                profile.telegram_chat_id.to_string(),
+                profile.state_file(IGNORED_FILE),
+                index,
+                command_sender.clone(),
+            ));
+        }
+    }
+
+    log!(
+        "Watch-Modus gestartet. Scan alle {} Sekunden.",
+        POLL_INTERVAL_SECS
+    );
+    let summary_at =
+        DAILY_SUMMARY_AT.and_then(|(hour, minute)| NaiveTime::from_hms_opt(hour, minute, 0));
+    let mut daily_stats: Vec<DailyStats> = profiles
+        .iter()
+        .map(|(profile, _)| load_daily_stats(&profile.state_file(SUMMARY_FILE)))
+        .collect();
+    // With `--no-persist`, each profile's seen ads are kept in memory for as long as the bot runs.
+    let mut memory_states: Vec<SeenState<MemoryStore>> =
+        profiles.iter().map(|_| SeenState::in_memory()).collect();
+    // An unreadable schedule is ignored rather than leaving the bot idle for good.
+    let schedule = ACTIVE_SCHEDULE.and_then(|expression| {
+        Schedule::parse(expression)
+            .inspect_err(|e| log!("{}. Es wird rund um die Uhr gescannt.", e))
+            .ok()
+    });
+    // When each search of each profile is due next, all of them right away.
+    let started = Instant::now();
+    let mut next_due: Vec<Vec<Instant>> = profiles
+        .iter()
+        .map(|(profile, _)| vec![started; profile.searches.len()])
+        .collect();
+    let mut next_price_check = vec![started; profiles.len()];
+    let mut triggered_by_command: Option<usize> = None;
+    let mut blocked = false;
+    loop {
+        // Outside the schedule, idle until it's active again or a command asks for a scan.
+        let now = TIMEZONE.now();
+        if triggered_by_command.is_none()
+            && let Some(schedule) = &schedule
+            && !schedule.matches(now)
+        {
+            let wait = schedule
+                .wait_until_active(TIMEZONE, Utc::now())
+                .and_then(|wait| wait.to_std().ok())
+                .unwrap_or(Duration::from_secs(POLL_INTERVAL_SECS));
+            log!(
+                "Außerhalb des Zeitplans, nächster Scan in {} Minuten.",
+                wait.as_secs().div_ceil(60)
+            );
+            // Wake up at least every POLL_INTERVAL_SECS, so the daily summary isn't held back.
+            send_daily_summaries(profiles, &mut daily_stats, summary_at, cli_args).await;
+            triggered_by_command = select! {
+                _ = sleep(wait.min(Duration::from_secs(POLL_INTERVAL_SECS))) => None,
+                Some((index, Command::Scan)) = command_receiver.recv() => {
+                    log!("Befehl /scan empfangen. Starte sofortigen Scan.");
+                    Some(index)
+                }
+            };
+            continue;
+        }
+
+        let was_blocked = blocked;
+        blocked = false;
+        let mut replies = Vec::with_capacity(profiles.len());
+        let scan_started = Instant::now();
+        for ((((profile, notifiers), stats), memory), next_due) in profiles
+            .iter()
+            .zip(&mut daily_stats)
+            .zip(&mut memory_states)
+            .zip(&mut next_due)
+        {
+            // A command scans every search, otherwise only those whose interval has passed.
+            let due = take_due_searches(
+                profile.searches,
+                next_due,
+                scan_started,
+                triggered_by_command.is_some(),
+            );
+            if due.is_empty() {
+                replies.push(String::new());
+                continue;
+            }
+
+            let memory = cli_args.no_persist.then_some(memory);
+            let result = run_scan(
+                scrape_client,
+                governor,
+                notifiers,
+                profile,
+                cli_args,
+                memory,
+                Some(&due),
+            )
+            .await;
+            blocked |= matches!(&result, Err(e) if e.downcast_ref::<BlockedError>().is_some());
+            replies.push(match result {
+                Ok(report) => {
+                    stats.record(report.new_ads, report.delivered.len());
+                    format!("Scan abgeschlossen: {}", report.summary())
+                }
+                Err(e) => {
+                    log!("Fehler beim Scan: {}", e);
+                    format!("Scan fehlgeschlagen: {}", e)
+                }
+            });
+        }
+
+        // Watched listings are re-checked every POLL_INTERVAL_SECS, however often single searches
+        // are scanned in between.
+        if !blocked {
+            for ((profile, notifiers), next_check) in profiles.iter().zip(&mut next_price_check) {
+                if triggered_by_command.is_some() || *next_check <= scan_started {
+                    *next_check = scan_started + Duration::from_secs(POLL_INTERVAL_SECS);
+                    check_watched_ads(scrape_client, governor, notifiers, profile, cli_args).await;
+                }
+            }
+        }
+
+        // Alert once when a block starts, and once it's over.
+        if blocked != was_blocked {
+            let alert = if blocked {
+                format!(
+                    "⚠️ Kleinanzeigen blockiert den Bot (CAPTCHA oder Sperrseite). Nächster Versuch in {} Minuten.",
+                    BLOCKED_BACKOFF_SECS / 60
+                )
+            } else {
+                "✅ Kleinanzeigen ist wieder erreichbar.".to_string()
+            };
+            for (profile, notifiers) in profiles {
+                broadcast_notice(profile, notifiers, &alert).await;
+            }
+        }
+
+        send_daily_summaries(profiles, &mut daily_stats, summary_at, cli_args).await;
+
+        // Replies to commands go to the profile's chat and count against the same rate limit.
+        if let Some(index) = triggered_by_command {
+            let profile = profiles[index].0;
+            let reply_notifier = TelegramNotifier::new(
+                telegram_client.clone(),
+                scrape_client.clone(),
+                telegram_throttle.clone(),
+                governor.clone(),
+                profile.telegram_bot_token,
+            );
+            if let Err(e) = reply_notifier
+                .send_message(profile.telegram_chat_id, &replies[index])
+                .await
+            {
+                log!("Fehler beim Beantworten des Befehls: {}", e);
+            }
+        }
+
+        // Sleep until the next search is due, unless a command asks for a scan earlier.
+        // While blocked, back off longer to let the block expire.
+        let interval = if blocked {
+            Duration::from_secs(BLOCKED_BACKOFF_SECS)
+        } else {
+            next_due
+                .iter()
+                .flatten()
+                .min()
+                .map_or(Duration::from_secs(POLL_INTERVAL_SECS), |due_at| {
+                    due_at.saturating_duration_since(Instant::now())
+                })
+        };
+        triggered_by_command = select! {
+            _ = sleep(interval) => None,
+            Some((index, Command::Scan)) = command_receiver.recv() => {
+                log!("Befehl /scan empfangen. Starte sofortigen Scan.");
+                Some(index)
+            }
+        };
+    }
+}
+
+// --- Main Program ---
+#[main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let cli_args = CliArgs::parse()?;
+
+    // All log output goes to stderr, so stdout stays free for machine-readable output.
+    set_format(if cli_args.log_json {
+        LogFormat::Json
+    } else {
+        LOG_FORMAT
+    });
+    set_debug(LOG_DEBUG);
+    if cli_args.no_persist && RECORD_MESSAGE_IDS {
+        log!(
+            "RECORD_MESSAGE_IDS ist mit --no-persist abgeschaltet, da --delete-search die Nachrichten aus {} liest.",
+            MESSAGE_INDEX_FILE
+        );
+    }
+
+    // `--profile` picks one of the profiles, otherwise all of them run.
+    let profiles: Vec<&'static Profile> = match &cli_args.profile {
+        Some(name) => vec![
+            PROFILES
+                .iter()
+                .find(|profile| profile.name == name)
+                .ok_or_else(|| format!("Unbekanntes Profil: {}", name))?,
+        ],
+        None => PROFILES.iter().collect(),
+    };
+
+    // Moving the seen list between machines needs neither credentials nor a scan.
+    if let Some(path) = &cli_args.export_seen {
+        return export_seen(path, profiles[0], cli_args.force_reseed);
+    }
+    if let Some(path) = &cli_args.import_seen {
+        return import_seen(path, profiles[0], cli_args.force_reseed);
+    }
+
+    // Checking the configuration before a deploy must not scrape or send anything.
+    if cli_args.validate_config {
+        let report = validate_config();
+        report.print();
+        if !report.is_ok() {
+            return Err("Konfiguration ungültig".into());
+        }
+        return Ok(());
+    }
+
+    // A simple guard to prevent running with placeholder credentials, unless another channel is set up.
+    // Emitting JSON never talks to Telegram, so it works without credentials.
+    let other_channels_configured =
+        DISCORD_WEBHOOK_URL.is_some() || MATRIX_HOMESERVER.is_some() || NTFY_TOPIC.is_some();
+    if !cli_args.emit_json
+        && !profiles.iter().any(|profile| profile.telegram_configured())
+        && !other_channels_configured
+    {
+        log!(
+            "FEHLER: Bitte ersetze die Platzhalter für TELEGRAM_BOT_TOKEN und TELEGRAM_CHAT_ID im Skript oder richte einen anderen Kanal (Discord, Matrix, ntfy) ein."
+        );
+        // A probe must not report an unconfigured bot as healthy.
+        if cli_args.healthcheck {
+            return Err("Healthcheck fehlgeschlagen".into());
+        }
+        return Ok(());
+    }
+
+    // Initialize an HTTP client with browser-like headers to avoid being blocked.
+    let scrape_client = Client::builder()
+        .default_headers(scrape_headers()?)
+        .build()?;
+
+    // Telegram gets its own client, so a proxy for it never affects scraping.
+    let telegram_client = build_telegram_client()?;
+
+    // All Telegram sends share one throttle so the per-chat ceiling holds across every call.
+    let telegram_throttle = Arc::new(Throttle::per_minute(TELEGRAM_MAX_MESSAGES_PER_MINUTE));
+
+    // Scraping and sending share one set of request budgets per host.
+    let governor = Arc::new(RateGovernor::new(HOST_BUDGETS));
+    governor.log_budgets();
+
+    // Removing old notifications only talks to Telegram.
+    if let Some(search) = &cli_args.delete_search {
+        for &profile in &profiles {
+            if profile.telegram_configured() {
+                let notifier = TelegramNotifier::new(
+                    telegram_client.clone(),
+                    scrape_client.clone(),
+                    telegram_throttle.clone(),
+                    governor.clone(),
+                    profile.telegram_bot_token,
+                );
+                delete_search_messages(&notifier, profile, search).await?;
+            }
+        }
+        return Ok(());
+    }
+
+    // Every profile sends with its own Telegram bot. The other channels are added to the first
+    // profile when configured.
+    let mut profile_notifiers = Vec::new();
+    for &profile in &profiles {
+        let mut notifiers = Vec::new();
+        if profile.telegram_configured() {
+            let mut notifier = TelegramNotifier::new(
+                telegram_client.clone(),
+                scrape_client.clone(),
+                telegram_throttle.clone(),
+                governor.clone(),
+                profile.telegram_bot_token,
+            );
+            if REUSE_FILE_IDS {
+                notifier = notifier.with_file_ids(profile.state_file(FILE_ID_FILE));
+            }
+            notifiers.push(AnyNotifier::Telegram(notifier));
+        }
+        if profile.name == PROFILES[0].name {
+            if let Some(webhook_url) = DISCORD_WEBHOOK_URL {
+                notifiers.push(AnyNotifier::Discord(DiscordWebhookNotifier::new(
+                    scrape_client.clone(),
+                    webhook_url,
+                )));
+            }
+            if let Some(homeserver) = MATRIX_HOMESERVER {
+                notifiers.push(AnyNotifier::Matrix(MatrixNotifier::new(
+                    scrape_client.clone(),
+                    homeserver,
+                    MATRIX_ACCESS_TOKEN,
+                    MATRIX_ROOM_ID,
+                )));
+            }
+            if let Some(topic) = NTFY_TOPIC {
+                notifiers.push(AnyNotifier::Ntfy(NtfyNotifier::new(
+                    scrape_client.clone(),
+                    NTFY_SERVER,
+                    topic,
+                )));
+            }
+        }
+        profile_notifiers.push((profile, notifiers));
+    }
+
+    // Exit right after the readiness probe; a failed check exits with a non-zero status.
+    if cli_args.healthcheck {
+        let report = healthcheck(&scrape_client, &profile_notifiers).await;
+        println!("{}", to_string_pretty(&report)?);
+        if !(report.scrape_ok && report.send_ok) {
+            return Err("Healthcheck fehlgeschlagen".into());
+        }
+        return Ok(());
+    }
+
+    // Sending a single ad neither scans the searches nor touches the seen ads.
+    if let Some(url) = &cli_args.send_ad {
+        let (profile, notifiers) = &profile_notifiers[0];
+        return send_single_ad(&scrape_client, url, profile, notifiers).await;
+    }
+
+    if cli_args.watch {
+        watch(
+            &scrape_client,
+            &telegram_client,
+            &governor,
+            &profile_notifiers,
+            telegram_throttle,
+            &cli_args,
+        )
+        .await;
+    } else {
+        // A failing profile doesn't keep the others from being scanned.
+        let mut first_error = None;
+        for (profile, notifiers) in &profile_notifiers {
+            let mut memory = SeenState::in_memory();
+            let memory = cli_args.no_persist.then_some(&mut memory);
+            match run_scan(
+                &scrape_client,
+                &governor,
+                notifiers,
+                profile,
+                &cli_args,
+                memory,
+                None,
+            )
+            .await
+            {
+                Ok(_) => {
+                    check_watched_ads(&scrape_client, &governor, notifiers, profile, &cli_args)
+                        .await
+                }
+                Err(e) => {
+                    log!("Fehler beim Scan: {}", e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        if let Some(e) = first_error {
+            return Err(e);
+        }
+    }
+
+    // Print final message and return success
+    log!("Skript beendet.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::scrape::ScrapedPage;
+    use crate::throttle::with_jitter;
+
+    /// Serves scripted result pages, as lists of ad IDs keyed by page URL.
+    struct ScriptedSource {
+        pages: HashMap<String, Vec<String>>,
+    }
+
+    impl ScriptedSource {
+        /// Scripts the pages of the first search.
+        fn with_pages(pages: &[&[&str]]) -> Self {
+            Self::with_searches(&[(&SEARCHES[0], pages)])
+        }
+
+        /// Scripts the pages of several searches.
+        fn with_searches(searches: &[(&Search, &[&[&str]])]) -> Self {
+            let mut scripted = HashMap::new();
+            for (search, pages) in searches {
+                for (page, ids) in (1..).zip(pages.iter()) {
+                    scripted.insert(
+                        search.page_url(page),
+                        ids.iter().map(|id| id.to_string()).collect(),
+                    );
+                }
+            }
+            Self { pages: scripted }
+        }
+    }
+
+    impl PageSource for ScriptedSource {
+        async fn fetch_page(&self, url: &str) -> Result<ScrapedPage, Box<dyn Error>> {
+            let ids = self.pages.get(url).map(Vec::as_slice).unwrap_or_default();
+            Ok(ScrapedPage {
+                ads: ids.iter().map(|id| ad(id)).collect(),
+                layout_warning: None,
+            })
+        }
+
+        async fn fetch_description(
+            &self,
+            _url: &str,
+            _max_chars: usize,
+        ) -> Result<Option<String>, Box<dyn Error>> {
+            Ok(None)
+        }
+
+        async fn fetch_seller(&self, _url: &str) -> Result<Option<Seller>, Box<dyn Error>> {
+            Ok(None)
+        }
+
+        async fn fetch_seller_listing_count(
+            &self,
+            _url: &str,
+        ) -> Result<Option<usize>, Box<dyn Error>> {
+            Ok(None)
+        }
+    }
+
+    /// Records the IDs of the ads it was asked to send, failing for the IDs in `failing`.
+    /// With `random_delays`, each send takes a few random milliseconds, like a real API.
+    #[derive(Default)]
+    struct MockNotifier {
+        sent: RefCell<Vec<String>>,
+        failing: RefCell<HashSet<String>>,
+        random_delays: bool,
+    }
+
+    impl MockNotifier {
+        fn take_sent(&self) -> Vec<String> {
+            let mut sent = self.sent.take();
+            sent.sort();
+            sent
+        }
+    }
+
+    impl Notifier for MockNotifier {
+        fn name(&self) -> &'static str {
+            "Mock"
+        }
+
+        async fn send_ad(
+            &self,
+            ad: &Ad,
+            _delivery: &Delivery<'_>,
+        ) -> Result<Vec<PostedMessage>, Box<dyn Error>> {
+            if self.random_delays {
+                sleep(with_jitter(Duration::ZERO, Duration::from_millis(5))).await;
+            }
+            if self.failing.borrow().contains(&ad.id) {
+                return Err("scripted failure".into());
+            }
+            self.sent.borrow_mut().push(ad.id.clone());
+            Ok(Vec::new())
+        }
+
+        async fn send_notice(
+            &self,
+            _text: &str,
+            _delivery: &Delivery<'_>,
+        ) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+    }
+
+    fn ad(id: &str) -> Ad {
+        Ad {
+            id: id.to_string(),
+            title: format!("Anzeige {}", id),
+            link: format!("https://www.kleinanzeigen.de/s-anzeige/{}", id),
+            image_url: None,
+            full_image_url: None,
+            posted: None,
+            posted_at: None,
+            location: None,
+            shipping: None,
+            negotiable: false,
+            promoted: false,
+            views: None,
+            condition: None,
+            search: "",
+            labels: Vec::new(),
+            prefix: None,
+            description: None,
+            top_match: false,
+        }
+    }
+
+    fn empty_state() -> SeenState<MemoryStore> {
+        SeenState::in_memory()
+    }
+
+    fn seen_ids(state: &SeenState<MemoryStore>) -> Vec<String> {
+        let mut ids: Vec<String> = state
+            .store
+            .entries()
+            .iter()
+            .map(|entry| entry.id.clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn failed_sends_are_retried_and_seen_ads_are_not_repeated() {
+        let governor = RateGovernor::new(&[]);
+        let cli_args = CliArgs::default();
+        let notifiers = [MockNotifier::default()];
+        let mut state = empty_state();
+
+        // First run: one ad fails to send and stays unseen.
+        notifiers[0].failing.borrow_mut().insert("3".to_string());
+        let source = ScriptedSource::with_pages(&[&["1", "2", "3", "4"]]);
+        let report = process_scan(
+            &source,
+            &governor,
+            &notifiers,
+            &PROFILES[0],
+            &cli_args,
+            &mut state,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.new_ads, 4);
+        assert_eq!(notifiers[0].take_sent(), ["1", "2", "4"]);
+        assert_eq!(seen_ids(&state), ["1", "2", "4"]);
+
+        // Second run: a new ad appears on top, and the failed one is retried.
+        notifiers[0].failing.borrow_mut().clear();
+        let source = ScriptedSource::with_pages(&[&["5", "1", "2", "3", "4"]]);
+        let report = process_scan(
+            &source,
+            &governor,
+            &notifiers,
+            &PROFILES[0],
+            &cli_args,
+            &mut state,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.new_ads, 2);
+        assert_eq!(notifiers[0].take_sent(), ["3", "5"]);
+        assert_eq!(seen_ids(&state), ["1", "2", "3", "4", "5"]);
+
+        // Third run: nothing changed, so nothing is sent.
+        let report = process_scan(
+            &source,
+            &governor,
+            &notifiers,
+            &PROFILES[0],
+            &cli_args,
+            &mut state,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.new_ads, 0);
+        assert!(notifiers[0].take_sent().is_empty());
+        assert_eq!(state.store.len(), Some(5));
+    }
+
+    #[tokio::test]
+    async fn a_changed_dedup_strategy_rebuilds_the_seen_list_silently() {
+        let governor = RateGovernor::new(&[]);
+        let cli_args = CliArgs::default();
+        let notifiers = [MockNotifier::default()];
+        let mut state = empty_state();
+        state.strategy_changed = true;
+
+        // The live ads were announced under their old keys, so they are only marked as seen.
+        let source = ScriptedSource::with_pages(&[&["1", "2", "3"]]);
+        let report = process_scan(
+            &source,
+            &governor,
+            &notifiers,
+            &PROFILES[0],
+            &cli_args,
+            &mut state,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.new_ads, 3);
+        assert!(notifiers[0].take_sent().is_empty());
+        assert_eq!(seen_ids(&state), ["1", "2", "3"]);
+
+        // Ads posted afterwards are announced as usual.
+        let source = ScriptedSource::with_pages(&[&["4", "1", "2", "3"]]);
+        process_scan(
+            &source,
+            &governor,
+            &notifiers,
+            &PROFILES[0],
+            &cli_args,
+            &mut state,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(notifiers[0].take_sent(), ["4"]);
+    }
+
+    #[tokio::test]
+    async fn starting_live_seeds_the_pages_the_next_run_scans() {
+        let governor = RateGovernor::new(&[]);
+        let cli_args = CliArgs::default();
+        let notifiers = [MockNotifier::default()];
+        let mut state = empty_state();
+        state.strategy_changed = true;
+        let source = ScriptedSource::with_pages(&[&["10", "9"], &["8", "7"], &["6", "5"]]);
+        process_scan(
+            &source,
+            &governor,
+            &notifiers,
+            &PROFILES[0],
+            &cli_args,
+            &mut state,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(notifiers[0].take_sent().is_empty());
+
+        // The top ad was deleted, so the cursor isn't found and the overlap page is scanned.
+        let source = ScriptedSource::with_pages(&[&["11", "9"], &["8", "7"], &["6", "5"]]);
+        process_scan(
+            &source,
+            &governor,
+            &notifiers,
+            &PROFILES[0],
+            &cli_args,
+            &mut state,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(notifiers[0].take_sent(), ["11"]);
+    }
+
+    #[tokio::test]
+    async fn failed_sends_do_not_use_up_the_first_run_limit() {
+        let governor = RateGovernor::new(&[]);
+        let cli_args = CliArgs::default();
+        let notifiers = [MockNotifier::default()];
+        let mut state = empty_state();
+        notifiers[0].failing.borrow_mut().insert("2".to_string());
+        notifiers[0].failing.borrow_mut().insert("5".to_string());
+
+        let ids: Vec<String> = (1..=FIRST_RUN_LIMIT + 5).map(|id| id.to_string()).collect();
+        let page: Vec<&str> = ids.iter().map(String::as_str).collect();
+        let source = ScriptedSource::with_pages(&[&page]);
+        process_scan(
+            &source,
+            &governor,
+            &notifiers,
+            &PROFILES[0],
+            &cli_args,
+            &mut state,
+            None,
+        )
+        .await
+        .unwrap();
+
+        // The two failed ads make room for the next two, and the rest stays unseen.
+        let sent = notifiers[0].sent.take();
+        assert_eq!(sent.len(), FIRST_RUN_LIMIT);
+        assert_eq!(sent.last().unwrap(), &(FIRST_RUN_LIMIT + 2).to_string());
+        assert_eq!(state.store.len(), Some(FIRST_RUN_LIMIT));
+    }
+
+    /// Two searches sharing an ad, to check the order across searches.
+    static TWO_SEARCHES: Profile = Profile {
+        name: "",
+        telegram_bot_token: TELEGRAM_BOT_TOKEN,
+        telegram_chat_id: TELEGRAM_CHAT_ID,
+        searches: &[
+            Search {
+                name: "Erste",
+                base_url: "https://www.kleinanzeigen.de/s-erste",
+                url_suffix: "/c1",
+                keyword: None,
+                chat_ids: &[],
+                max_pages: None,
+                prefix: None,
+                poll_interval_secs: None,
+            },
+            Search {
+                name: "Zweite",
+                base_url: "https://www.kleinanzeigen.de/s-zweite",
+                url_suffix: "/c2",
+                keyword: None,
+                chat_ids: &[],
+                max_pages: None,
+                prefix: None,
+                poll_interval_secs: None,
+            },
+        ],
+        watched_ads: &[],
+    };
+
+    #[tokio::test]
+    async fn the_same_pages_give_the_same_notification_sequence() {
+        let governor = RateGovernor::new(&[]);
+        let cli_args = CliArgs::default();
+        let pages: [(&Search, &[&[&str]]); 2] = [
+            (&TWO_SEARCHES.searches[0], &[&["4", "3"], &["2"]]),
+            (&TWO_SEARCHES.searches[1], &[&["9", "3"], &["8"]]),
+        ];
+
+        // Sends that take varying time still reach the chat in order.
+        let mut sequences = Vec::new();
+        for _ in 0..3 {
+            let notifiers = [MockNotifier {
+                random_delays: true,
+                ..MockNotifier::default()
+            }];
+            let source = ScriptedSource::with_searches(&pages);
+            process_scan(
+                &source,
+                &governor,
+                &notifiers,
+                &TWO_SEARCHES,
+                &cli_args,
+                &mut empty_state(),
+                None,
+            )
+            .await
+            .unwrap();
+            sequences.push(notifiers[0].sent.take());
+        }
+
+        // Searches in configured order, pages in order, and the shared ad where it was found first.
+        assert_eq!(sequences[0], ["4", "3", "2", "9", "8"]);
+        assert!(sequences.iter().all(|sequence| *sequence == sequences[0]));
+    }
+
+    #[tokio::test]
+    async fn searches_that_are_not_due_sit_out_the_scan() {
+        let governor = RateGovernor::new(&[]);
+        let cli_args = CliArgs::default();
+        let pages: [(&Search, &[&[&str]]); 2] = [
+            (&TWO_SEARCHES.searches[0], &[&["4", "3"]]),
+            (&TWO_SEARCHES.searches[1], &[&["9", "8"]]),
+        ];
+        let notifiers = [MockNotifier::default()];
+        let mut state = empty_state();
+        let source = ScriptedSource::with_searches(&pages);
+        let report = process_scan(
+            &source,
+            &governor,
+            &notifiers,
+            &TWO_SEARCHES,
+            &cli_args,
+            &mut state,
+            Some(&HashSet::from(["Zweite"])),
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.succeeded, ["Zweite"]);
+        assert_eq!(notifiers[0].take_sent(), ["8", "9"]);
+        assert_eq!(seen_ids(&state), ["8", "9"]);
+    }
+
+    #[test]
+    fn top_matches_are_flagged_and_labelled_first() {
+        let mut top = ad("1");
+        top.labels.push("🎯 Wunschliste: sofa (97 %)".to_string());
+        mark_top_match(&mut top, 0.97, Some(0.95));
+        assert!(top.top_match);
+        assert_eq!(top.labels[0], "🔥 TOP-TREFFER");
+        assert!(
+            serde_json::to_string(&top)
+                .unwrap()
+                .contains("\"top_match\":true")
+        );
+
+        let mut close = ad("2");
+        mark_top_match(&mut close, 0.94, Some(0.95));
+        mark_top_match(&mut close, 1.0, None);
+        assert!(!close.top_match);
+        assert!(close.labels.is_empty());
+        assert!(!serde_json::to_string(&close).unwrap().contains("top_match"));
+    }
+
+    #[test]
+    fn only_searches_whose_interval_passed_are_picked() {
+        static SEARCHES_WITH_INTERVALS: [Search; 2] = [
+            Search {
+                poll_interval_secs: Some(60),
+                ..TWO_SEARCHES.searches[0]
+            },
+            Search {
+                poll_interval_secs: Some(180),
+                ..TWO_SEARCHES.searches[1]
+            },
+        ];
+        let started = Instant::now();
+        let mut next_due = vec![started; 2];
+        let at = |secs| started + Duration::from_secs(secs);
+        let mut due_at = |secs, every_search| {
+            let mut due: Vec<_> = take_due_searches(
+                &SEARCHES_WITH_INTERVALS,
+                &mut next_due,
+                at(secs),
+                every_search,
+            )
+            .into_iter()
+            .collect();
+            due.sort();
+            due
+        };
+        assert_eq!(due_at(0, false), ["Erste", "Zweite"]);
+        assert!(due_at(30, false).is_empty());
+        assert_eq!(due_at(60, false), ["Erste"]);
+        assert_eq!(due_at(180, false), ["Erste", "Zweite"]);
+        // A command scans every search and starts their intervals anew.
+        assert_eq!(due_at(200, true), ["Erste", "Zweite"]);
+        assert!(due_at(240, false).is_empty());
+        assert_eq!(due_at(260, false), ["Erste"]);
+    }
+
+    #[tokio::test]
+    async fn a_search_seeded_by_an_earlier_attempt_is_scanned_normally() {
+        let governor = RateGovernor::new(&[]);
+        let cli_args = CliArgs::default();
+        // "Erste" got through the interrupted first run, "Zweite" has failed so far.
+        let mut state = empty_state();
+        state.store.insert("1").await;
+        state.first_run = Some(FirstRun {
+            complete: false,
+            seeded: BTreeSet::from(["Erste".to_string()]),
+            attempts: 1,
+        });
+        let ids: Vec<String> = (100..100 + FIRST_RUN_LIMIT + 2)
+            .map(|id| id.to_string())
+            .collect();
+        let mut page: Vec<&str> = ids.iter().map(String::as_str).collect();
+        page.push("1");
+        let pages: [(&Search, &[&[&str]]); 2] = [
+            (&TWO_SEARCHES.searches[0], &[&page[..]]),
+            (&TWO_SEARCHES.searches[1], &[&["9", "8"]]),
+        ];
+        let notifiers = [MockNotifier::default()];
+        let source = ScriptedSource::with_searches(&pages);
+        process_scan(
+            &source,
+            &governor,
+            &notifiers,
+            &TWO_SEARCHES,
+            &cli_args,
+            &mut state,
+            None,
+        )
+        .await
+        .unwrap();
+
+        // FIRST_RUN_LIMIT only caps the search that is still seeding.
+        assert_eq!(notifiers[0].take_sent().len(), FIRST_RUN_LIMIT + 4);
+    }
+
+    #[tokio::test]
+    async fn limit_leaves_the_rest_for_later_runs() {
+        let governor = RateGovernor::new(&[]);
+        let cli_args = CliArgs {
+            limit: Some(2),
+            ..CliArgs::default()
+        };
+        let notifiers = [MockNotifier::default()];
+        let mut state = empty_state();
+        state.store.insert("0").await;
+
+        let source = ScriptedSource::with_pages(&[&["1", "2", "3", "0"]]);
+        process_scan(
+            &source,
+            &governor,
+            &notifiers,
+            &PROFILES[0],
+            &cli_args,
+            &mut state,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(notifiers[0].take_sent(), ["1", "2"]);
+
+        process_scan(
+            &source,
+            &governor,
+            &notifiers,
+            &PROFILES[0],
+            &cli_args,
+            &mut state,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(notifiers[0].take_sent(), ["3"]);
+    }
+
+    #[tokio::test]
+    async fn paging_stops_at_the_top_ad_of_the_last_run() {
+        let governor = RateGovernor::new(&[]);
+        let cli_args = CliArgs::default();
+        let notifiers = [MockNotifier::default()];
+        let mut state = empty_state();
+        state.store.insert("unrelated").await;
+        state
+            .cursors
+            .insert(SEARCHES[0].name.to_string(), "10".to_string());
+
+        // The seen entry of the cursor ad was pruned, only the cursor marks where the last run began.
+        let pages: &[&[&str]] = &[
+            &["40"],
+            &["30"],
+            &["10"],
+            &["9"],
+            &["8"],
+            &["7"],
+            &["6"],
+            &["5"],
+        ];
+        let source = ScriptedSource::with_pages(pages);
+        process_scan(
+            &source,
+            &governor,
+            &notifiers,
+            &PROFILES[0],
+            &cli_args,
+            &mut state,
+            None,
+        )
+        .await
+        .unwrap();
+
+        // The cursor is on page 3, and neither it nor the ads below it are sent again.
+        assert_eq!(notifiers[0].take_sent(), ["30", "40"]);
+        assert_eq!(state.cursors[SEARCHES[0].name], "40");
+    }
+
+    #[tokio::test]
+    async fn the_cursor_is_not_moved_past_an_ad_only_the_filter_knows() {
+        let governor = RateGovernor::new(&[]);
+        let cli_args = CliArgs::default();
+        let notifiers = [MockNotifier::default()];
+        let mut state = empty_state();
+        state.store.insert("1").await;
+        state
+            .cursors
+            .insert(SEARCHES[0].name.to_string(), "1".to_string());
+        let mut filter = BloomFilter::with_rate(100, 0.01);
+        filter.insert("2");
+        state.ever_seen = Some(filter);
+
+        let source = ScriptedSource::with_pages(&[&["3", "2", "1"]]);
+        process_scan(
+            &source,
+            &governor,
+            &notifiers,
+            &PROFILES[0],
+            &cli_args,
+            &mut state,
+            None,
+        )
+        .await
+        .unwrap();
+
+        // "2" may be a false positive of the filter, so the next run starts from "1" again.
+        assert_eq!(notifiers[0].take_sent(), ["3"]);
+        assert_eq!(state.cursors[SEARCHES[0].name], "1");
+    }
+
+    /// A seen store that can't be asked, like Redis while it is unreachable.
+    struct UnreachableStore;
+
+    impl SeenStore for UnreachableStore {
+        async fn contains(&self, _key: &str) -> Result<bool, Box<dyn Error>> {
+            Err("Verbindung abgelehnt".into())
+        }
+
+        async fn insert(&mut self, _key: &str) {}
+
+        fn prune(&mut self, _mode: PruneMode, _max_entries: usize, _max_age_days: u64) -> usize {
+            0
+        }
+
+        fn expire(&mut self, _max_age_days: u64, _live: &HashSet<String>) -> usize {
+            0
+        }
+
+        fn persist(&self) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+
+        fn len(&self) -> Option<usize> {
+            None
+        }
+    }
+
+    #[tokio::test]
+    async fn an_unreachable_seen_store_fails_the_search_and_keeps_its_cursor() {
+        let governor = RateGovernor::new(&[]);
+        let cli_args = CliArgs::default();
+        let notifiers = [MockNotifier::default()];
+        let mut state = SeenState {
+            store: UnreachableStore,
+            ever_seen: None,
+            ignored: BTreeSet::new(),
+            cursors: BTreeMap::from([(SEARCHES[0].name.to_string(), "1".to_string())]),
+            recent_images: None,
+            messages: None,
+            sellers: None,
+            first_run: None,
+            strategy_changed: false,
+        };
+
+        let source = ScriptedSource::with_pages(&[&["3", "2", "1"]]);
+        let result = process_scan(
+            &source,
+            &governor,
+            &notifiers,
+            &PROFILES[0],
+            &cli_args,
+            &mut state,
+            None,
+        )
+        .await;
+
+        // Nothing is sent or taken as seen, and the next run starts from the same place.
+        assert!(result.is_err());
+        assert!(notifiers[0].take_sent().is_empty());
+        assert_eq!(state.cursors[SEARCHES[0].name], "1");
+    }
+
+    #[tokio::test]
+    async fn seen_list_is_pruned_across_runs() {
+        if PRUNE_MODE != PruneMode::Count {
+            return;
+        }
+        let governor = RateGovernor::new(&[]);
+        let cli_args = CliArgs::default();
+        let notifiers = [MockNotifier::default()];
+        let mut state = empty_state();
+
+        // Enough runs of fresh ads to exceed the limit of the seen list.
+        let per_run = 30;
+        let runs = MAX_SEEN_ADS / per_run + 2;
+        for run in 0..runs {
+            let ids: Vec<String> = (0..per_run)
+                .map(|index| (run * per_run + index).to_string())
+                .collect();
+            let page: Vec<&str> = ids.iter().map(String::as_str).collect();
+            let source = ScriptedSource::with_pages(&[&page]);
+            process_scan(
+                &source,
+                &governor,
+                &notifiers,
+                &PROFILES[0],
+                &cli_args,
+                &mut state,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+
+        // The oldest ads were dropped, the newest are kept.
+        assert_eq!(state.store.len(), Some(MAX_SEEN_ADS));
+        let ids = seen_ids(&state);
+        assert!(!ids.contains(&"0".to_string()));
+        assert!(ids.contains(&((runs * per_run) - 1).to_string()));
+    }
 }
@@ -0,0 +1,117 @@
+use std::{
+    error::Error,
+    fs::{read_to_string, write},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string_pretty};
+
+use crate::notifier::PostedMessage;
+
+/// A Telegram message an ad was posted as, recorded so it can be deleted later.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedMessage {
+    /// The name of the search that found the ad.
+    pub search: String,
+    /// The ID of the ad.
+    pub ad_id: String,
+    /// The chat the message was posted to.
+    pub chat_id: String,
+    /// The ID of the message within its chat.
+    pub message_id: i64,
+}
+
+/// The messages ads were posted as, oldest first, see `RECORD_MESSAGE_IDS`.
+#[derive(Debug, Default)]
+pub struct MessageIndex {
+    /// The recorded messages, oldest first.
+    messages: Vec<IndexedMessage>,
+}
+
+impl MessageIndex {
+    /// Loads the index from `path`.
+    ///
+    /// If the file does not exist or contains invalid data, it starts with an empty index.
+    pub fn load(path: &str) -> Self {
+        let messages = match read_to_string(path) {
+            Ok(content) => from_str(&content).unwrap_or_else(|e| {
+                log!("Fehler beim Parsen der Datei {}: {}", path, e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        Self { messages }
+    }
+
+    /// Saves the index to a JSON file.
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        write(path, to_string_pretty(&self.messages)?)?;
+        Ok(())
+    }
+
+    /// Records the messages an ad was posted as, dropping the oldest ones beyond `max_entries`.
+    pub fn record(
+        &mut self,
+        search: &str,
+        ad_id: &str,
+        posted: &[PostedMessage],
+        max_entries: usize,
+    ) {
+        self.messages
+            .extend(posted.iter().map(|message| IndexedMessage {
+                search: search.to_string(),
+                ad_id: ad_id.to_string(),
+                chat_id: message.chat_id.clone(),
+                message_id: message.message_id,
+            }));
+        let excess = self.messages.len().saturating_sub(max_entries);
+        self.messages.drain(..excess);
+    }
+
+    /// Removes the messages of a search from the index.
+    ///
+    /// # Returns
+    /// The removed messages, oldest first.
+    pub fn take_search(&mut self, search: &str) -> Vec<IndexedMessage> {
+        let (taken, kept) = self
+            .messages
+            .drain(..)
+            .partition(|message| message.search == search);
+        self.messages = kept;
+        taken
+    }
+
+    /// Puts messages back into the index, e.g. after deleting them failed.
+    pub fn restore(&mut self, messages: Vec<IndexedMessage>) {
+        self.messages.extend(messages);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn posted(message_id: i64) -> PostedMessage {
+        PostedMessage {
+            chat_id: "42".to_string(),
+            message_id,
+        }
+    }
+
+    #[test]
+    fn messages_that_could_not_be_deleted_are_kept() {
+        let mut index = MessageIndex::default();
+        index.record("Sofa", "1", &[posted(10), posted(11)], 100);
+        index.record("Lampe", "2", &[posted(12)], 100);
+
+        let sofa = index.take_search("Sofa");
+        assert_eq!(sofa.len(), 2);
+        assert!(sofa.iter().all(|message| message.ad_id == "1"));
+        assert!(index.take_search("Sofa").is_empty());
+
+        // Deleting failed, so the messages are there for the next attempt.
+        index.restore(sofa.clone());
+        assert_eq!(index.take_search("Sofa"), sofa);
+        assert_eq!(index.take_search("Lampe")[0].message_id, 12);
+    }
+}
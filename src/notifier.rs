@@ -0,0 +1,116 @@
+mod caption;
+mod discord;
+mod matrix;
+mod ntfy;
+mod telegram;
+
+use std::error::Error;
+
+pub use discord::DiscordWebhookNotifier;
+pub use matrix::MatrixNotifier;
+pub use ntfy::NtfyNotifier;
+pub use telegram::{ChatUnreachable, ImageMode, TelegramNotifier};
+
+use crate::Ad;
+
+/// A message an ad was posted as, for channels whose messages can be deleted later.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostedMessage {
+    /// The chat the message was posted to.
+    pub chat_id: String,
+    /// The ID of the message within its chat.
+    pub message_id: i64,
+}
+
+/// Where and how a single ad is delivered.
+pub struct Delivery<'a> {
+    /// The Telegram chats the ad's search is routed to.
+    /// Channels without a notion of chats, such as a Discord webhook or an ntfy topic, ignore them.
+    pub chat_ids: &'a [&'a str],
+    /// Whether to deliver the ad without a notification sound.
+    pub silent: bool,
+}
+
+/// A destination that new ads are announced to.
+///
+/// Each implementation handles its own formatting and retry logic, so callers only
+/// need to know whether the ad was delivered.
+pub trait Notifier {
+    /// A short human-readable name used in log messages.
+    fn name(&self) -> &'static str;
+
+    /// Announces a single ad.
+    ///
+    /// # Arguments
+    /// * `ad` - The ad to announce.
+    /// * `delivery` - Where and how the ad is delivered.
+    ///
+    /// # Returns
+    /// The messages the ad was posted as once it was delivered, empty for channels that don't
+    /// report them, or an error if all attempts failed.
+    async fn send_ad(
+        &self,
+        ad: &Ad,
+        delivery: &Delivery<'_>,
+    ) -> Result<Vec<PostedMessage>, Box<dyn Error>>;
+
+    /// Sends a short plain-text notice that isn't a new ad, such as a price drop.
+    ///
+    /// # Arguments
+    /// * `text` - The unformatted message text.
+    /// * `delivery` - Where and how the notice is delivered.
+    ///
+    /// # Returns
+    /// `Ok(())` once the notice was delivered, or an error if all attempts failed.
+    async fn send_notice(&self, text: &str, delivery: &Delivery<'_>) -> Result<(), Box<dyn Error>>;
+}
+
+/// Any of the supported notifiers, so that a mixed list can be iterated in `main`.
+pub enum AnyNotifier {
+    Telegram(TelegramNotifier),
+    Discord(DiscordWebhookNotifier),
+    Matrix(MatrixNotifier),
+    Ntfy(NtfyNotifier),
+}
+
+impl AnyNotifier {
+    /// Saves what the notifier learned during the run, such as the Telegram `file_id`s.
+    pub fn save_state(&self) {
+        if let AnyNotifier::Telegram(notifier) = self {
+            notifier.save_file_ids();
+        }
+    }
+}
+
+impl Notifier for AnyNotifier {
+    fn name(&self) -> &'static str {
+        match self {
+            AnyNotifier::Telegram(notifier) => notifier.name(),
+            AnyNotifier::Discord(notifier) => notifier.name(),
+            AnyNotifier::Matrix(notifier) => notifier.name(),
+            AnyNotifier::Ntfy(notifier) => notifier.name(),
+        }
+    }
+
+    async fn send_ad(
+        &self,
+        ad: &Ad,
+        delivery: &Delivery<'_>,
+    ) -> Result<Vec<PostedMessage>, Box<dyn Error>> {
+        match self {
+            AnyNotifier::Telegram(notifier) => notifier.send_ad(ad, delivery).await,
+            AnyNotifier::Discord(notifier) => notifier.send_ad(ad, delivery).await,
+            AnyNotifier::Matrix(notifier) => notifier.send_ad(ad, delivery).await,
+            AnyNotifier::Ntfy(notifier) => notifier.send_ad(ad, delivery).await,
+        }
+    }
+
+    async fn send_notice(&self, text: &str, delivery: &Delivery<'_>) -> Result<(), Box<dyn Error>> {
+        match self {
+            AnyNotifier::Telegram(notifier) => notifier.send_notice(text, delivery).await,
+            AnyNotifier::Discord(notifier) => notifier.send_notice(text, delivery).await,
+            AnyNotifier::Matrix(notifier) => notifier.send_notice(text, delivery).await,
+            AnyNotifier::Ntfy(notifier) => notifier.send_notice(text, delivery).await,
+        }
+    }
+}
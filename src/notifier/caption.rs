@@ -0,0 +1,169 @@
+use chrono::{DateTime, Utc};
+
+use crate::{
+    Ad, MAX_TITLE_CHARS, SHOW_FRESHNESS,
+    location::parse_place,
+    text::{escape_html, truncate_text},
+};
+
+/// The markup a caption is rendered in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptionFormat {
+    /// HTML as understood by Telegram's HTML parse mode and Matrix's `formatted_body`.
+    Html,
+    /// Unformatted text with the link spelled out.
+    PlainText,
+}
+
+/// Shortens a title to `max_chars` characters with an ellipsis, see `MAX_TITLE_CHARS`.
+fn shorten_title(title: &str, max_chars: Option<usize>) -> String {
+    match max_chars {
+        Some(max_chars) => truncate_text(title, max_chars),
+        None => title.to_string(),
+    }
+}
+
+/// Describes how long ago an ad was posted, e.g. "vor 12 Minuten".
+fn freshness(posted_at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = (now - posted_at).num_minutes();
+    let (count, one, many) = match minutes {
+        ..1 => return "gerade eben".to_string(),
+        1..60 => (minutes, "Minute", "Minuten"),
+        60..1440 => (minutes / 60, "Stunde", "Stunden"),
+        _ => (minutes / 1440, "Tag", "Tagen"),
+    };
+    format!("vor {} {}", count, if count == 1 { one } else { many })
+}
+
+/// Describes when an ad was posted for the caption, see `SHOW_FRESHNESS`.
+///
+/// # Returns
+/// How long ago it was, the text from the page if the time isn't known to the minute, or `None`
+/// if the ad shows no posting time.
+fn posted_text(ad: &Ad, now: DateTime<Utc>) -> Option<String> {
+    let posted = ad.posted.as_deref()?;
+    // A bare date reads as midnight, which would make the ad look older than it is.
+    match ad.posted_at {
+        Some(posted_at) if posted.contains(':') => Some(freshness(posted_at, now)),
+        _ => Some(posted.to_string()),
+    }
+}
+
+/// Builds the caption announcing an ad.
+///
+/// Labels such as warnings go first, followed by the header with the search's prefix,
+/// the title, the description snippet, the posting time if `SHOW_FRESHNESS` is set, the location, shipping availability and view count if known, and the links
+/// to the full-resolution image and the ad.
+pub fn build_caption(ad: &Ad, format: CaptionFormat) -> String {
+    let bold = |text: &str| match format {
+        CaptionFormat::Html => format!("<b>{}</b>", text),
+        CaptionFormat::PlainText => text.to_string(),
+    };
+    let escape = |text: &str| match format {
+        CaptionFormat::Html => escape_html(text),
+        CaptionFormat::PlainText => text.to_string(),
+    };
+
+    let mut caption = String::new();
+
+    // Labels such as warnings go first, so they are seen before the title.
+    for label in &ad.labels {
+        caption.push_str(&format!("{}\n", bold(&escape(label))));
+    }
+    let header = match &ad.prefix {
+        Some(prefix) => format!("{} Neuer kostenloser Artikel gefunden!", prefix),
+        None => "Neuer kostenloser Artikel gefunden!".to_string(),
+    };
+    caption.push_str(&format!(
+        "{}\n{} {}\n",
+        bold(&escape(&header)),
+        bold("Titel:"),
+        escape(&shorten_title(&ad.title, MAX_TITLE_CHARS))
+    ));
+    if let Some(description) = &ad.description {
+        caption.push_str(&format!(
+            "{} {}\n",
+            bold("Beschreibung:"),
+            escape(description)
+        ));
+    }
+    if SHOW_FRESHNESS && let Some(posted) = posted_text(ad, Utc::now()) {
+        caption.push_str(&format!("{} {}\n", bold("Eingestellt:"), escape(&posted)));
+    }
+    // The town with its postal code, and the district on its own line, to judge the way to pickup.
+    if let Some(location) = &ad.location {
+        let place = parse_place(location);
+        let town = match (place.town, place.postal_code) {
+            (Some(town), Some(postal_code)) => Some(format!("{} ({})", town, postal_code)),
+            (town, postal_code) => town.or(postal_code).map(str::to_string),
+        };
+        if let Some(town) = town {
+            caption.push_str(&format!("{} {}\n", bold("Ort:"), escape(&town)));
+        }
+        if let Some(district) = place.district {
+            caption.push_str(&format!("{} {}\n", bold("Ortsteil:"), escape(district)));
+        }
+    }
+    if let Some(shipping) = ad.shipping {
+        let shipping = if shipping { "möglich" } else { "Nur Abholung" };
+        caption.push_str(&format!("{} {}\n", bold("Versand:"), shipping));
+    }
+    if let Some(condition) = &ad.condition {
+        caption.push_str(&format!("{} {}\n", bold("Zustand:"), escape(condition)));
+    }
+    if let Some(views) = ad.views {
+        caption.push_str(&format!("{} {}\n", bold("Aufrufe:"), views));
+    }
+    if let Some(full_image_url) = &ad.full_image_url {
+        match format {
+            CaptionFormat::Html => caption.push_str(&format!(
+                "<a href='{}'>🖼 Bild</a>\n",
+                escape_html(full_image_url)
+            )),
+            CaptionFormat::PlainText => caption.push_str(&format!("🖼 Bild: {}\n", full_image_url)),
+        }
+    }
+    match format {
+        CaptionFormat::Html => caption.push_str(&format!(
+            "<a href='{}'>Anzeige ansehen</a>",
+            escape_html(&ad.link)
+        )),
+        CaptionFormat::PlainText => caption.push_str(&format!("Anzeige ansehen: {}", ad.link)),
+    }
+    caption
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_titles_are_cut_at_a_character_boundary() {
+        let title = "Großes Ecksofa für Wohnzimmer, grün, sehr gut erhalten";
+        assert_eq!(shorten_title(title, None), title);
+        assert_eq!(shorten_title(title, Some(60)), title);
+        assert_eq!(shorten_title(title, Some(20)), "Großes Ecksofa für…");
+
+        // Without spaces the cut falls between two multi-byte characters.
+        let title = "ÄÖÜäöüßÄÖÜäöüß";
+        let shortened = shorten_title(title, Some(8));
+        assert_eq!(shortened, "ÄÖÜäöüß…");
+        assert_eq!(shortened.chars().count(), 8);
+    }
+
+    #[test]
+    fn freshness_is_told_in_german() {
+        let now = DateTime::parse_from_rfc3339("2026-10-14T12:00:00Z")
+            .unwrap()
+            .to_utc();
+        let ago = |minutes| freshness(now - chrono::Duration::minutes(minutes), now);
+        assert_eq!(ago(0), "gerade eben");
+        assert_eq!(ago(-5), "gerade eben");
+        assert_eq!(ago(1), "vor 1 Minute");
+        assert_eq!(ago(12), "vor 12 Minuten");
+        assert_eq!(ago(60), "vor 1 Stunde");
+        assert_eq!(ago(181), "vor 3 Stunden");
+        assert_eq!(ago(1440), "vor 1 Tag");
+        assert_eq!(ago(3 * 1440), "vor 3 Tagen");
+    }
+}
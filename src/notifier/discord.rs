@@ -0,0 +1,173 @@
+use std::{error::Error, time::Duration};
+
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use serde_json::{Value, from_slice, json};
+use tokio::time::sleep;
+
+use crate::{
+    Ad, RETRY_JITTER,
+    notifier::{Delivery, Notifier, PostedMessage},
+    throttle::with_jitter,
+};
+
+/// Discord rejects embed titles longer than this many characters.
+const MAX_EMBED_TITLE_CHARS: usize = 256;
+
+/// The message flag that delivers a message without a push notification.
+const SUPPRESS_NOTIFICATIONS_FLAG: u64 = 1 << 12;
+
+/// Represents the body Discord returns when a webhook is rate limited.
+#[derive(Debug, Deserialize)]
+struct DiscordRateLimit {
+    /// Time to wait in seconds before retrying.
+    retry_after: Option<f64>,
+}
+
+/// Posts ads as embeds to a Discord channel via an incoming webhook.
+pub struct DiscordWebhookNotifier {
+    /// The HTTP client used for all webhook calls.
+    client: Client,
+    /// The full webhook URL, including its ID and token.
+    webhook_url: String,
+}
+
+impl DiscordWebhookNotifier {
+    /// Creates a notifier posting to the given webhook URL.
+    pub fn new(client: Client, webhook_url: &str) -> Self {
+        Self {
+            client,
+            webhook_url: webhook_url.to_string(),
+        }
+    }
+
+    /// Builds the webhook payload with an embed announcing the ad.
+    ///
+    /// # Arguments
+    /// * `ad` - The ad to announce.
+    /// * `silent` - Whether to post the message without a push notification.
+    fn ad_payload(ad: &Ad, silent: bool) -> Value {
+        let title: String = ad.title.chars().take(MAX_EMBED_TITLE_CHARS).collect();
+        let mut embed = json!({
+            "title": title,
+            "url": ad.link,
+        });
+        let mut description = ad.description.clone().unwrap_or_default();
+        if let Some(shipping) = ad.shipping {
+            let shipping = if shipping { "möglich" } else { "Nur Abholung" };
+            if !description.is_empty() {
+                description.push('\n');
+            }
+            description.push_str(&format!("Versand: {}", shipping));
+        }
+        if !description.is_empty() {
+            embed["description"] = json!(description);
+        }
+        if let Some(image_url) = &ad.image_url {
+            embed["image"] = json!({ "url": image_url });
+        }
+        let mut content: String = ad
+            .labels
+            .iter()
+            .map(|label| format!("**{}**\n", label))
+            .collect();
+        if let Some(prefix) = &ad.prefix {
+            content.push_str(&format!("{} ", prefix));
+        }
+        content.push_str("**Neuer kostenloser Artikel gefunden!**");
+        let mut payload = json!({
+            "content": content,
+            "embeds": [embed],
+        });
+        if silent {
+            payload["flags"] = json!(SUPPRESS_NOTIFICATIONS_FLAG);
+        }
+        payload
+    }
+
+    /// Posts a payload to the webhook.
+    ///
+    /// # Returns
+    /// `Ok(None)` on success, `Ok(Some(seconds))` if Discord asks us to retry later,
+    /// or an error for any other failure.
+    async fn post_payload(&self, payload: &Value) -> Result<Option<f64>, Box<dyn Error>> {
+        let response = self
+            .client
+            .post(&self.webhook_url)
+            .json(payload)
+            .send()
+            .await?;
+
+        // Check if the response is successful
+        if response.status().is_success() {
+            log!("Discord-Nachricht erfolgreich gesendet.");
+            return Ok(None);
+        }
+
+        // Handle error response
+        let status = response.status();
+        let error_bytes = response.bytes().await?;
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            // Default retry after 5 seconds if not specified
+            let retry_after = from_slice::<DiscordRateLimit>(&error_bytes)
+                .ok()
+                .and_then(|limit| limit.retry_after)
+                .unwrap_or(5.0);
+            return Ok(Some(retry_after));
+        }
+
+        let error_body = String::from_utf8_lossy(&error_bytes);
+        let error_message = format!("Discord Webhook Fehler: {} - {}", status, error_body);
+        Err(error_message.into())
+    }
+
+    /// Posts a payload, retrying once if Discord rate-limits the first attempt.
+    ///
+    /// # Returns
+    /// `Ok(())` once the payload was delivered, or an error if all attempts failed.
+    async fn post_with_retry(&self, payload: &Value) -> Result<(), Box<dyn Error>> {
+        match self.post_payload(payload).await? {
+            None => Ok(()),
+            Some(retry_after) => {
+                // Rate limiting, wait and retry once
+                log!(
+                    "Discord Rate limiting erkannt. Warte {} Sekunden vor erneutem Versuch.",
+                    retry_after
+                );
+                sleep(with_jitter(
+                    Duration::from_secs_f64(retry_after.max(0.0)),
+                    RETRY_JITTER,
+                ))
+                .await;
+                match self.post_payload(payload).await? {
+                    None => Ok(()),
+                    Some(_) => Err("Discord-Webhook weiterhin rate-limitiert".into()),
+                }
+            }
+        }
+    }
+}
+
+impl Notifier for DiscordWebhookNotifier {
+    fn name(&self) -> &'static str {
+        "Discord"
+    }
+
+    async fn send_ad(
+        &self,
+        ad: &Ad,
+        delivery: &Delivery<'_>,
+    ) -> Result<Vec<PostedMessage>, Box<dyn Error>> {
+        self.post_with_retry(&Self::ad_payload(ad, delivery.silent))
+            .await?;
+        Ok(Vec::new())
+    }
+
+    async fn send_notice(&self, text: &str, delivery: &Delivery<'_>) -> Result<(), Box<dyn Error>> {
+        let mut payload = json!({ "content": text });
+        if delivery.silent {
+            payload["flags"] = json!(SUPPRESS_NOTIFICATIONS_FLAG);
+        }
+        self.post_with_retry(&payload).await
+    }
+}
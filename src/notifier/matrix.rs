@@ -0,0 +1,245 @@
+use std::{
+    error::Error,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use reqwest::{Client, StatusCode, Url};
+use serde::Deserialize;
+use serde_json::{Value, from_slice, json};
+use tokio::time::sleep;
+
+use crate::{
+    Ad, RETRY_JITTER,
+    notifier::{
+        Delivery, Notifier, PostedMessage,
+        caption::{CaptionFormat, build_caption},
+    },
+    seen::unix_now,
+    throttle::with_jitter,
+};
+
+/// Represents the body Matrix returns for errors such as rate limiting.
+#[derive(Debug, Deserialize)]
+struct MatrixError {
+    /// Time to wait in milliseconds before retrying.
+    retry_after_ms: Option<u64>,
+}
+
+/// Represents the body Matrix returns after a media upload.
+#[derive(Debug, Deserialize)]
+struct MatrixUpload {
+    /// The `mxc://` URI of the uploaded file.
+    content_uri: String,
+}
+
+/// Posts ads to a Matrix room through the client-server API.
+pub struct MatrixNotifier {
+    /// The HTTP client used for all API calls.
+    client: Client,
+    /// The base URL of the homeserver, e.g. "https://matrix.org".
+    homeserver: String,
+    /// The access token of the account that posts the messages.
+    access_token: String,
+    /// The ID of the room the messages are posted to, e.g. "!abc123:matrix.org".
+    room_id: String,
+    /// Makes every transaction ID of this process unique.
+    transaction_counter: AtomicU64,
+}
+
+impl MatrixNotifier {
+    /// Creates a notifier posting to `room_id` on `homeserver` as the owner of `access_token`.
+    pub fn new(client: Client, homeserver: &str, access_token: &str, room_id: &str) -> Self {
+        Self {
+            client,
+            homeserver: homeserver.trim_end_matches('/').to_string(),
+            access_token: access_token.to_string(),
+            room_id: room_id.to_string(),
+            transaction_counter: AtomicU64::new(0),
+        }
+    }
+
+    /// Builds an API URL from the homeserver and the given path segments, percent-encoding each one.
+    fn api_url(&self, segments: &[&str]) -> Result<Url, Box<dyn Error>> {
+        let mut url = Url::parse(&self.homeserver)?;
+        url.path_segments_mut()
+            .map_err(|_| "Ungültige Matrix-Homeserver-URL")?
+            .extend(segments);
+        Ok(url)
+    }
+
+    /// Sends one event to the room.
+    ///
+    /// # Arguments
+    /// * `content` - The event content.
+    /// * `transaction_id` - The ID of the transaction, the same for every attempt of one event.
+    ///
+    /// # Returns
+    /// `Ok(None)` on success, `Ok(Some(seconds))` if the homeserver asks us to retry later,
+    /// or an error for any other failure.
+    async fn put_message(
+        &self,
+        content: &Value,
+        transaction_id: &str,
+    ) -> Result<Option<f64>, Box<dyn Error>> {
+        let url = self.api_url(&[
+            "_matrix",
+            "client",
+            "v3",
+            "rooms",
+            &self.room_id,
+            "send",
+            "m.room.message",
+            transaction_id,
+        ])?;
+
+        let response = self
+            .client
+            .put(url)
+            .bearer_auth(&self.access_token)
+            .json(content)
+            .send()
+            .await?;
+
+        // Check if the response is successful
+        if response.status().is_success() {
+            log!("Matrix-Nachricht erfolgreich gesendet.");
+            return Ok(None);
+        }
+
+        // Handle error response
+        let status = response.status();
+        let error_bytes = response.bytes().await?;
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            // Default retry after 5 seconds if not specified
+            let retry_after_ms = from_slice::<MatrixError>(&error_bytes)
+                .ok()
+                .and_then(|error| error.retry_after_ms)
+                .unwrap_or(5_000);
+            return Ok(Some(retry_after_ms as f64 / 1000.0));
+        }
+
+        let error_body = String::from_utf8_lossy(&error_bytes);
+        let error_message = format!("Matrix API Fehler: {} - {}", status, error_body);
+        Err(error_message.into())
+    }
+
+    /// Sends one event, retrying once if the homeserver rate-limits the first attempt.
+    ///
+    /// # Returns
+    /// `Ok(())` once the event was delivered, or an error if all attempts failed.
+    async fn send_with_retry(&self, content: &Value) -> Result<(), Box<dyn Error>> {
+        // The transaction ID lets the homeserver drop a retry of an event that went through.
+        let transaction_id = format!(
+            "{}-{}",
+            unix_now(),
+            self.transaction_counter.fetch_add(1, Ordering::Relaxed)
+        );
+        match self.put_message(content, &transaction_id).await? {
+            None => Ok(()),
+            Some(retry_after) => {
+                // Rate limiting, wait and retry once
+                log!(
+                    "Matrix Rate limiting erkannt. Warte {} Sekunden vor erneutem Versuch.",
+                    retry_after
+                );
+                sleep(with_jitter(
+                    Duration::from_secs_f64(retry_after.max(0.0)),
+                    RETRY_JITTER,
+                ))
+                .await;
+                match self.put_message(content, &transaction_id).await? {
+                    None => Ok(()),
+                    Some(_) => Err("Matrix-Homeserver weiterhin rate-limitiert".into()),
+                }
+            }
+        }
+    }
+
+    /// Downloads the ad's image and uploads it to the homeserver, since Matrix only shows `mxc://` media.
+    ///
+    /// # Returns
+    /// The `mxc://` URI of the uploaded image, or an error if either step failed.
+    async fn upload_image(&self, image_url: &str) -> Result<String, Box<dyn Error>> {
+        let image = self
+            .client
+            .get(image_url)
+            .send()
+            .await?
+            .error_for_status()?;
+        let content_type = image
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("image/jpeg")
+            .to_string();
+        let bytes = image.bytes().await?;
+
+        let url = self.api_url(&["_matrix", "media", "v3", "upload"])?;
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(&self.access_token)
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(bytes)
+            .send()
+            .await?
+            .error_for_status()?;
+        let upload: MatrixUpload = from_slice(&response.bytes().await?)?;
+        Ok(upload.content_uri)
+    }
+}
+
+impl Notifier for MatrixNotifier {
+    fn name(&self) -> &'static str {
+        "Matrix"
+    }
+
+    async fn send_ad(
+        &self,
+        ad: &Ad,
+        delivery: &Delivery<'_>,
+    ) -> Result<Vec<PostedMessage>, Box<dyn Error>> {
+        // Notices are shown without a notification by most clients.
+        let msgtype = if delivery.silent {
+            "m.notice"
+        } else {
+            "m.text"
+        };
+        let content = json!({
+            "msgtype": msgtype,
+            "body": build_caption(ad, CaptionFormat::PlainText),
+            "format": "org.matrix.custom.html",
+            "formatted_body": build_caption(ad, CaptionFormat::Html).replace('\n', "<br>"),
+        });
+        self.send_with_retry(&content).await?;
+
+        // The image follows as its own event. Failing to post it doesn't undo the delivered text.
+        if let Some(image_url) = &ad.image_url {
+            match self.upload_image(image_url).await {
+                Ok(content_uri) => {
+                    let image = json!({
+                        "msgtype": "m.image",
+                        "body": ad.title,
+                        "url": content_uri,
+                    });
+                    if let Err(e) = self.send_with_retry(&image).await {
+                        log!("Fehler beim Senden des Bildes an Matrix: {}", e);
+                    }
+                }
+                Err(e) => log!("Fehler beim Hochladen des Bildes zu Matrix: {}", e),
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    async fn send_notice(&self, text: &str, delivery: &Delivery<'_>) -> Result<(), Box<dyn Error>> {
+        let msgtype = if delivery.silent {
+            "m.notice"
+        } else {
+            "m.text"
+        };
+        self.send_with_retry(&json!({ "msgtype": msgtype, "body": text }))
+            .await
+    }
+}
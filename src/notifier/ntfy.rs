@@ -0,0 +1,148 @@
+use std::{error::Error, time::Duration};
+
+use reqwest::{Client, StatusCode, header::RETRY_AFTER};
+use serde_json::{Value, json};
+use tokio::time::sleep;
+
+use crate::{
+    Ad, MAX_RETRY_AFTER_SECS, RETRY_JITTER,
+    notifier::{Delivery, Notifier, PostedMessage},
+    throttle::with_jitter,
+};
+
+/// How long to wait before retrying if ntfy rate-limits us without saying for how long.
+const DEFAULT_RETRY_AFTER_SECS: u64 = 5;
+
+/// Pushes ads to an ntfy topic, for phone notifications without a Telegram bot.
+///
+/// Messages are published as JSON to the server root, which carries the title,
+/// click action and attachment without having to encode them into headers.
+pub struct NtfyNotifier {
+    /// The HTTP client used for all publish calls.
+    client: Client,
+    /// The base URL of the ntfy server, e.g. "https://ntfy.sh".
+    server: String,
+    /// The topic the messages are published to.
+    topic: String,
+}
+
+impl NtfyNotifier {
+    /// Creates a notifier publishing to `topic` on the given server.
+    pub fn new(client: Client, server: &str, topic: &str) -> Self {
+        Self {
+            client,
+            server: server.trim_end_matches('/').to_string(),
+            topic: topic.to_string(),
+        }
+    }
+
+    /// Builds the message announcing the ad.
+    ///
+    /// The title is the message, tapping it opens the ad, and the image is attached if there is one.
+    ///
+    /// # Arguments
+    /// * `ad` - The ad to announce.
+    /// * `silent` - Whether to publish the message with low priority, which doesn't vibrate or ring.
+    fn ad_payload(&self, ad: &Ad, silent: bool) -> Value {
+        let mut message: String = ad
+            .labels
+            .iter()
+            .map(|label| format!("{}\n", label))
+            .collect();
+        message.push_str(&ad.title);
+        let title = match &ad.prefix {
+            Some(prefix) => format!("{} Neuer kostenloser Artikel gefunden!", prefix),
+            None => "Neuer kostenloser Artikel gefunden!".to_string(),
+        };
+        let mut payload = self.payload(&message, silent);
+        payload["title"] = json!(title);
+        payload["click"] = json!(ad.link);
+        if let Some(image_url) = &ad.image_url {
+            payload["attach"] = json!(image_url);
+        }
+        payload
+    }
+
+    /// Builds a plain message for the topic.
+    fn payload(&self, message: &str, silent: bool) -> Value {
+        // Priority 2 is "low", 3 the default.
+        json!({
+            "topic": self.topic,
+            "message": message,
+            "priority": if silent { 2 } else { 3 },
+        })
+    }
+
+    /// Publishes a message.
+    ///
+    /// # Returns
+    /// `Ok(None)` on success, `Ok(Some(seconds))` if ntfy asks us to retry later,
+    /// or an error for any other failure.
+    async fn publish(&self, payload: &Value) -> Result<Option<u64>, Box<dyn Error>> {
+        let response = self.client.post(&self.server).json(payload).send().await?;
+
+        // Check if the response is successful
+        if response.status().is_success() {
+            log!("ntfy-Nachricht erfolgreich gesendet.");
+            return Ok(None);
+        }
+
+        // Handle error response
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(DEFAULT_RETRY_AFTER_SECS);
+            // Never stall the scan longer than Telegram's cap, however long the server asks for.
+            return Ok(Some(retry_after.min(MAX_RETRY_AFTER_SECS)));
+        }
+        let error_body = response.text().await.unwrap_or_default();
+        Err(format!("ntfy Fehler: {} - {}", status, error_body).into())
+    }
+
+    /// Publishes a message, retrying once if ntfy rate-limits the first attempt.
+    ///
+    /// # Returns
+    /// `Ok(())` once the message was delivered, or an error if all attempts failed.
+    async fn publish_with_retry(&self, payload: &Value) -> Result<(), Box<dyn Error>> {
+        match self.publish(payload).await? {
+            None => Ok(()),
+            Some(retry_after) => {
+                // Rate limiting, wait and retry once
+                log!(
+                    "ntfy Rate limiting erkannt. Warte {} Sekunden vor erneutem Versuch.",
+                    retry_after
+                );
+                sleep(with_jitter(Duration::from_secs(retry_after), RETRY_JITTER)).await;
+                match self.publish(payload).await? {
+                    None => Ok(()),
+                    Some(_) => Err("ntfy weiterhin rate-limitiert".into()),
+                }
+            }
+        }
+    }
+}
+
+impl Notifier for NtfyNotifier {
+    fn name(&self) -> &'static str {
+        "ntfy"
+    }
+
+    async fn send_ad(
+        &self,
+        ad: &Ad,
+        delivery: &Delivery<'_>,
+    ) -> Result<Vec<PostedMessage>, Box<dyn Error>> {
+        self.publish_with_retry(&self.ad_payload(ad, delivery.silent))
+            .await?;
+        Ok(Vec::new())
+    }
+
+    async fn send_notice(&self, text: &str, delivery: &Delivery<'_>) -> Result<(), Box<dyn Error>> {
+        self.publish_with_retry(&self.payload(text, delivery.silent))
+            .await
+    }
+}
@@ -0,0 +1,1749 @@
+use std::{
+    collections::HashSet,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{TimeDelta, Utc};
+use reqwest::{Client, StatusCode, header::CONTENT_TYPE};
+use serde::Deserialize;
+use serde_json::{from_slice, json};
+use tokio::{sync::Semaphore, time::sleep};
+
+use crate::{
+    Ad, CONVERT_WEBP_UPLOADS, DISABLE_LINK_PREVIEW, ENABLE_AD_BUTTONS, FALLBACK_CHAT_ID,
+    FILE_ID_TTL_DAYS, IMAGE_MODE, MAX_CONCURRENT_IMAGE_DOWNLOADS, MAX_FILE_IDS,
+    MAX_IMAGE_DOWNLOAD_BYTES, MAX_RETRY_AFTER_SECS, MAX_TOTAL_RETRY_WAIT_SECS, NO_IMAGE_MARKER,
+    PIN_TOP_MATCHES, RETRY_JITTER, SEND_LOCATION_PIN, TELEGRAM_API_HOST, TELEGRAM_MAX_RETRIES,
+    TEXT_BEFORE_PHOTO, UPLOAD_PHOTO_FALLBACK, VALIDATE_IMAGE_URLS,
+    commands::AdAction,
+    file_ids::FileIds,
+    location::coordinates_for,
+    notifier::{
+        Delivery, Notifier, PostedMessage,
+        caption::{CaptionFormat, build_caption},
+    },
+    scrape::image_url_variants,
+    text::escape_html,
+    throttle::{RateGovernor, Throttle, with_jitter},
+};
+
+/// Limits how many downloaded images are held in memory for uploading at once.
+static IMAGE_DOWNLOADS: Semaphore = Semaphore::const_new(MAX_CONCURRENT_IMAGE_DOWNLOADS);
+
+/// Separates the parts of an uploaded photo request.
+const MULTIPART_BOUNDARY: &str = "kleinanzeigen-bot-7f3a9c2e51d84b06";
+
+/// How an ad's image is posted.
+// Only the variant chosen in the configuration is ever constructed.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageMode {
+    /// As a photo, which Telegram fetches by its URL and downscales.
+    Photo,
+    /// As a file in its original dimensions. Telegram only fetches GIF, PDF and ZIP files by URL,
+    /// so the image is downloaded and uploaded.
+    Document,
+}
+
+/// The part of a successful Telegram answer that identifies the posted message.
+#[derive(Debug, Deserialize)]
+struct TelegramResponse {
+    /// The posted message, or `true` for requests that don't post one.
+    result: Option<TelegramMessage>,
+}
+
+/// A message as returned by Telegram.
+#[derive(Debug, Deserialize)]
+struct TelegramMessage {
+    /// The ID of the message within its chat.
+    message_id: i64,
+    /// The sizes of a posted photo, smallest first.
+    #[serde(default)]
+    photo: Vec<PhotoSize>,
+}
+
+/// One size of a photo as returned by Telegram.
+#[derive(Debug, Deserialize)]
+struct PhotoSize {
+    /// The ID to send the photo again without Telegram fetching it.
+    file_id: String,
+}
+
+/// Represents a Telegram API error response.
+#[derive(Debug, Deserialize)]
+struct TelegramError {
+    /// The error code.
+    error_code: Option<i32>,
+    /// The human-readable reason, e.g. "Bad Request: can't parse entities".
+    description: Option<String>,
+    /// Additional parameters for the error.
+    parameters: Option<TelegramErrorParameters>,
+}
+
+/// Additional parameters for Telegram API errors.
+#[derive(Debug, Deserialize)]
+struct TelegramErrorParameters {
+    /// Time to wait before retrying (for rate limiting).
+    retry_after: Option<i64>,
+}
+
+/// What a `400 Bad Request` from Telegram was caused by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BadRequestKind {
+    /// Telegram couldn't fetch or process the image (dimensions, size, type, unreachable URL).
+    Image,
+    /// The caption or text itself was rejected, e.g. broken HTML or too long.
+    Formatting,
+    /// Any other reason.
+    Other,
+}
+
+/// A `400 Bad Request` answer from Telegram, classified by its cause.
+#[derive(Debug)]
+struct BadRequest {
+    /// What caused the request to be rejected.
+    kind: BadRequestKind,
+    /// Telegram's description of the problem.
+    description: String,
+}
+
+impl Display for BadRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            BadRequestKind::Image => write!(f, "Bild abgelehnt: {}", self.description),
+            BadRequestKind::Formatting => write!(
+                f,
+                "Nachrichtenvorlage abgelehnt, bitte Formatierung der Beschriftung prüfen: {}",
+                self.description
+            ),
+            BadRequestKind::Other => write!(f, "Ungültige Anfrage: {}", self.description),
+        }
+    }
+}
+
+impl Error for BadRequest {}
+
+/// Telegram refuses to deliver to a chat at all: the bot was removed or blocked,
+/// or the chat ID is wrong. Retrying won't help until the configuration is fixed.
+#[derive(Debug)]
+pub struct ChatUnreachable {
+    /// Telegram's description of the problem, or the chats affected.
+    description: String,
+}
+
+impl Display for ChatUnreachable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Telegram-Chat nicht erreichbar, bitte prüfen, ob der Bot noch Mitglied ist und die Chat-ID stimmt: {}",
+            self.description
+        )
+    }
+}
+
+impl Error for ChatUnreachable {}
+
+/// Tracks how long a single message has waited on Telegram's rate limits.
+///
+/// A bogus or extreme `retry_after` must not freeze the bot, so each requested wait is
+/// capped at `MAX_RETRY_AFTER_SECS` and all waits for one message together at
+/// `MAX_TOTAL_RETRY_WAIT_SECS`. Once a limit is hit, the message counts as failed and
+/// is retried on the next run instead.
+#[derive(Debug, Default)]
+struct RetryBudget {
+    /// The seconds already spent waiting for this message.
+    waited_secs: u64,
+}
+
+impl RetryBudget {
+    /// Waits `retry_after` seconds if the caps allow it.
+    ///
+    /// # Returns
+    /// `true` after waiting, or `false` without waiting if a cap would be exceeded.
+    async fn wait(&mut self, retry_after: i64) -> bool {
+        let retry_after = retry_after.max(0) as u64;
+        if retry_after > MAX_RETRY_AFTER_SECS {
+            log!(
+                "Telegram verlangt {} Sekunden Wartezeit, mehr als die erlaubten {}. Nachricht wird beim nächsten Durchlauf erneut versucht.",
+                retry_after,
+                MAX_RETRY_AFTER_SECS
+            );
+            return false;
+        }
+        if self.waited_secs + retry_after > MAX_TOTAL_RETRY_WAIT_SECS {
+            log!(
+                "Maximale Gesamtwartezeit von {} Sekunden für diese Nachricht erreicht. Nachricht wird beim nächsten Durchlauf erneut versucht.",
+                MAX_TOTAL_RETRY_WAIT_SECS
+            );
+            return false;
+        }
+
+        sleep(with_jitter(Duration::from_secs(retry_after), RETRY_JITTER)).await;
+        self.waited_secs += retry_after;
+        true
+    }
+}
+
+/// A request Telegram accepted.
+struct Accepted {
+    /// The ID of the posted message, if the request posted one.
+    message_id: Option<i64>,
+    /// The `file_id` of the posted photo in its largest size, if the message has one.
+    file_id: Option<String>,
+}
+
+impl Accepted {
+    /// Reads the IDs of the posted message and its photo from the body of a successful answer.
+    fn from_body(body: &[u8]) -> Self {
+        let message = from_slice::<TelegramResponse>(body)
+            .ok()
+            .and_then(|response| response.result);
+        Self {
+            message_id: message.as_ref().map(|message| message.message_id),
+            file_id: message
+                .and_then(|message| message.photo.into_iter().last())
+                .map(|size| size.file_id),
+        }
+    }
+}
+
+/// Telegram's answer to a single request.
+enum Attempt {
+    /// The request went through.
+    Accepted(Accepted),
+    /// Telegram asks us to retry after this many seconds.
+    RetryAfter(i64),
+}
+
+/// A single request to a chat, see `TelegramNotifier::send_with_retries`.
+enum Payload<'a> {
+    /// A photo with an HTML caption.
+    Photo {
+        url: &'a str,
+        caption: &'a str,
+        reply_markup: Option<&'a str>,
+    },
+    /// A photo with an HTML caption, downloaded by the bot and uploaded as bytes.
+    PhotoUpload {
+        url: &'a str,
+        caption: &'a str,
+        reply_markup: Option<&'a str>,
+    },
+    /// An image downloaded and uploaded as a document with a caption, see `ImageMode::Document`.
+    Document {
+        url: &'a str,
+        caption: &'a str,
+        reply_markup: Option<&'a str>,
+    },
+    /// An HTML text message.
+    Text {
+        text: &'a str,
+        reply_markup: Option<&'a str>,
+    },
+    /// A map pin.
+    Location { latitude: f64, longitude: f64 },
+    /// The deletion of a message the bot posted.
+    Deletion { message_id: i64 },
+    /// Pinning a message the bot posted.
+    Pin { message_id: i64 },
+}
+
+/// Sends ads to Telegram chats through the Bot API.
+pub struct TelegramNotifier {
+    /// The HTTP client used for all API calls.
+    client: Client,
+    /// The HTTP client used for checking and downloading ad images, which bypasses the Telegram
+    /// proxy like scraping does.
+    image_client: Client,
+    /// The rate limiter shared by all Telegram sends.
+    throttle: Arc<Throttle>,
+    /// The request budgets shared by scraping and sending, each API request takes a slot.
+    governor: Arc<RateGovernor>,
+    /// The token of the bot that posts the messages.
+    bot_token: String,
+    /// The chats Telegram refused to deliver to, skipped until the bot restarts.
+    unreachable_chats: Mutex<HashSet<String>>,
+    /// The `file_id`s of photos this bot sent and the file they're saved in, if `REUSE_FILE_IDS`
+    /// is enabled.
+    file_ids: Option<(Mutex<FileIds>, String)>,
+}
+
+impl TelegramNotifier {
+    /// Creates a notifier posting with the bot identified by `bot_token`.
+    pub fn new(
+        client: Client,
+        image_client: Client,
+        throttle: Arc<Throttle>,
+        governor: Arc<RateGovernor>,
+        bot_token: &str,
+    ) -> Self {
+        Self {
+            client,
+            image_client,
+            throttle,
+            governor,
+            bot_token: bot_token.to_string(),
+            unreachable_chats: Mutex::new(HashSet::new()),
+            file_ids: None,
+        }
+    }
+
+    /// Reuses the `file_id`s of photos sent before, stored in the file at `path`.
+    pub fn with_file_ids(mut self, path: String) -> Self {
+        self.file_ids = Some((Mutex::new(FileIds::load(&path)), path));
+        self
+    }
+
+    /// Looks up the `file_id` Telegram returned when the photo at `url` was sent before.
+    fn stored_file_id(&self, url: &str) -> Option<String> {
+        let (file_ids, _) = self.file_ids.as_ref()?;
+        file_ids
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(url, Utc::now(), file_id_ttl())
+            .map(str::to_string)
+    }
+
+    /// Stores or forgets the `file_id` of the photo at `url`, saved by `save_file_ids`.
+    ///
+    /// # Arguments
+    /// * `url` - The image URL the photo was sent from.
+    /// * `file_id` - The `file_id` Telegram returned, or `None` to forget the stored one.
+    fn update_file_id(&self, url: &str, file_id: Option<&str>) {
+        let Some((file_ids, _)) = &self.file_ids else {
+            return;
+        };
+        let mut file_ids = file_ids.lock().unwrap_or_else(|e| e.into_inner());
+        match file_id {
+            Some(file_id) => {
+                file_ids.insert(url, file_id, Utc::now(), file_id_ttl(), MAX_FILE_IDS);
+            }
+            None => {
+                file_ids.remove(url);
+            }
+        }
+    }
+
+    /// Saves the `file_id`s stored or forgotten since the last save, once per run rather than
+    /// after every photo.
+    pub fn save_file_ids(&self) {
+        let Some((file_ids, path)) = &self.file_ids else {
+            return;
+        };
+        let mut file_ids = file_ids.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = file_ids.save(path) {
+            log!("Fehler beim Speichern der Datei {}: {}", path, e);
+        }
+    }
+
+    /// Sends a photo by the `file_id` stored for its URL, so Telegram doesn't fetch it again.
+    ///
+    /// A `file_id` Telegram rejects as an image is forgotten, and the caller sends the photo by URL instead.
+    ///
+    /// # Returns
+    /// The accepted message, or `None` if there is no stored `file_id` or sending by it failed.
+    async fn send_stored_photo(
+        &self,
+        chat_id: &str,
+        url: &str,
+        caption: &str,
+        silent: bool,
+        reply_markup: Option<&str>,
+        budget: &mut RetryBudget,
+    ) -> Option<Accepted> {
+        if IMAGE_MODE != ImageMode::Photo {
+            return None;
+        }
+        let file_id = self.stored_file_id(url)?;
+        let photo = Payload::Photo {
+            url: &file_id,
+            caption,
+            reply_markup,
+        };
+        match self
+            .send_with_retries(chat_id, &photo, silent, TELEGRAM_MAX_RETRIES, budget)
+            .await
+        {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                // Only a rejected image means the file_id is stale; other errors say nothing
+                // about it.
+                if e.downcast_ref::<BadRequest>().map(|bad| bad.kind) == Some(BadRequestKind::Image)
+                {
+                    log!(
+                        "Telegram lehnt die gespeicherte file_id ab ({}). Sende das Bild über die URL.",
+                        e
+                    );
+                    self.update_file_id(url, None);
+                }
+                None
+            }
+        }
+    }
+
+    /// Returns whether Telegram refused to deliver to this chat before.
+    fn is_unreachable(&self, chat_id: &str) -> bool {
+        self.unreachable_chats
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(chat_id)
+    }
+
+    /// Stops sending to a chat Telegram refused to deliver to.
+    fn drop_chat(&self, chat_id: &str, error: &ChatUnreachable) {
+        let newly_dropped = self
+            .unreachable_chats
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(chat_id.to_string());
+        if newly_dropped {
+            log!(
+                "Fehler: {}. Chat {} wird bis zum Neustart übersprungen.",
+                error,
+                chat_id
+            );
+        }
+    }
+
+    /// Sends a photo with a caption to a Telegram chat.
+    ///
+    /// # Arguments
+    /// * `chat_id` - The chat to post to.
+    /// * `photo_url` - The URL of the image to send.
+    /// * `caption` - The HTML-formatted caption for the photo.
+    /// * `silent` - Whether to deliver the message without a notification sound.
+    /// * `reply_markup` - The serialized inline keyboard attached to the message, if any.
+    ///
+    /// # Returns
+    /// `Attempt::Accepted` with the ID of the posted message on success, `Attempt::RetryAfter` if
+    /// Telegram asks us to retry later, or an error for any other failure.
+    async fn send_photo_message(
+        &self,
+        chat_id: &str,
+        photo_url: &str,
+        caption: &str,
+        silent: bool,
+        reply_markup: Option<&str>,
+    ) -> Result<Attempt, Box<dyn Error>> {
+        let url = format!("https://api.telegram.org/bot{}/sendPhoto", self.bot_token);
+
+        // Use form data to match what curl is doing, with HTML formatting
+        let mut params = vec![
+            ("chat_id", chat_id),
+            ("photo", photo_url),
+            ("caption", caption),
+            ("parse_mode", "HTML"),
+            (
+                "disable_notification",
+                if silent { "true" } else { "false" },
+            ),
+        ];
+        if let Some(reply_markup) = reply_markup {
+            params.push(("reply_markup", reply_markup));
+        }
+
+        // Wait until the chat's rate limit allows another message
+        self.governor.acquire(TELEGRAM_API_HOST).await;
+        self.throttle.acquire(chat_id).await;
+
+        // Send the POST request to the Telegram API with the photo and caption
+        let response = self.client.post(&url).form(&params).send().await?;
+
+        // Check if the response is successful
+        if response.status().is_success() {
+            let body = response.bytes().await?;
+            log!("Fotonachricht erfolgreich gesendet.");
+            return Ok(Attempt::Accepted(Accepted::from_body(&body)));
+        }
+
+        // Handle error response
+        let status = response.status();
+        let error_bytes = response.bytes().await?;
+
+        // Try to parse the error response as JSON
+        if let Some(retry_after) = retry_after_from_error(&error_bytes) {
+            return Ok(Attempt::RetryAfter(retry_after));
+        }
+
+        // Construct and return a detailed error with status code and response body
+        Err(api_error(status, &error_bytes))
+    }
+
+    /// Downloads an image and uploads its bytes with a caption, as a photo for images Telegram
+    /// can't fetch by itself, or as a document to keep its original dimensions.
+    ///
+    /// At most `MAX_CONCURRENT_IMAGE_DOWNLOADS` images are held in memory at once.
+    ///
+    /// # Arguments
+    /// * `method` - The Bot API method, "sendPhoto" or "sendDocument".
+    /// * `file_field` - The field carrying the image, "photo" or "document".
+    /// * `chat_id` - The chat to post to.
+    /// * `image_url` - The URL of the image to download.
+    /// * `caption` - The HTML-formatted caption for the photo.
+    /// * `silent` - Whether to deliver the message without a notification sound.
+    /// * `reply_markup` - The serialized inline keyboard attached to the message, if any.
+    ///
+    /// # Returns
+    /// `Attempt::Accepted` with the ID of the posted message on success, `Attempt::RetryAfter` if
+    /// Telegram asks us to retry later, or an error for any other failure, including a failed or
+    /// too large download.
+    #[allow(clippy::too_many_arguments)]
+    async fn upload_image(
+        &self,
+        method: &str,
+        file_field: &str,
+        chat_id: &str,
+        image_url: &str,
+        caption: &str,
+        silent: bool,
+        reply_markup: Option<&str>,
+    ) -> Result<Attempt, Box<dyn Error>> {
+        // The permit is held until the upload is done, since the image stays in memory until then.
+        let _permit = IMAGE_DOWNLOADS.acquire().await?;
+        let mut image = self.download_image(image_url).await?;
+
+        // Some clients render WebP poorly, so ask the image server for the JPEG rendition instead.
+        if CONVERT_WEBP_UPLOADS
+            && is_webp(&image)
+            && let Some(jpeg_url) = jpeg_variant(image_url)
+        {
+            match self.download_image(&jpeg_url).await {
+                Ok(jpeg) if !is_webp(&jpeg) => image = jpeg,
+                Ok(_) => log!(
+                    "Keine JPEG-Fassung für {} verfügbar, lade WebP hoch.",
+                    image_url
+                ),
+                Err(e) => log!(
+                    "Fehler beim Laden der JPEG-Fassung von {}: {}. Lade WebP hoch.",
+                    image_url,
+                    e
+                ),
+            }
+        }
+
+        let url = format!("https://api.telegram.org/bot{}/{}", self.bot_token, method);
+        let mut fields = vec![
+            ("chat_id", chat_id),
+            ("caption", caption),
+            ("parse_mode", "HTML"),
+            (
+                "disable_notification",
+                if silent { "true" } else { "false" },
+            ),
+        ];
+        if let Some(reply_markup) = reply_markup {
+            fields.push(("reply_markup", reply_markup));
+        }
+
+        // Wait until the chat's rate limit allows another message
+        self.governor.acquire(TELEGRAM_API_HOST).await;
+        self.throttle.acquire(chat_id).await;
+
+        let response = self
+            .client
+            .post(&url)
+            .header(
+                CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", MULTIPART_BOUNDARY),
+            )
+            .body(multipart_body(&fields, file_field, &image))
+            .send()
+            .await?;
+        if response.status().is_success() {
+            let body = response.bytes().await?;
+            log!("Hochgeladenes Bild erfolgreich gesendet ({}).", method);
+            return Ok(Attempt::Accepted(Accepted::from_body(&body)));
+        }
+
+        // Handle error response
+        let status = response.status();
+        let error_bytes = response.bytes().await?;
+        if let Some(retry_after) = retry_after_from_error(&error_bytes) {
+            return Ok(Attempt::RetryAfter(retry_after));
+        }
+        Err(api_error(status, &error_bytes))
+    }
+
+    /// Downloads an image chunk by chunk, aborting as soon as it exceeds `MAX_IMAGE_DOWNLOAD_BYTES`.
+    async fn download_image(&self, image_url: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let too_large = || format!("Bild größer als {} Bytes", MAX_IMAGE_DOWNLOAD_BYTES);
+        self.governor.acquire_url(image_url).await;
+        let mut response = self
+            .image_client
+            .get(image_url)
+            .send()
+            .await?
+            .error_for_status()?;
+        if response
+            .content_length()
+            .is_some_and(|length| length > MAX_IMAGE_DOWNLOAD_BYTES as u64)
+        {
+            return Err(too_large().into());
+        }
+
+        // The announced length may be missing or wrong, so the chunks are counted too.
+        let mut image = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if image.len() + chunk.len() > MAX_IMAGE_DOWNLOAD_BYTES {
+                return Err(too_large().into());
+            }
+            image.extend_from_slice(&chunk);
+        }
+        Ok(image)
+    }
+
+    /// Sends a text-only message to a Telegram chat.
+    ///
+    /// # Arguments
+    /// * `chat_id` - The chat to post to.
+    /// * `message` - The HTML-formatted message string to send.
+    /// * `silent` - Whether to deliver the message without a notification sound.
+    /// * `reply_markup` - The serialized inline keyboard attached to the message, if any.
+    ///
+    /// # Returns
+    /// `Attempt::Accepted` with the ID of the posted message on success, `Attempt::RetryAfter` if
+    /// Telegram asks us to retry later, or an error for any other failure.
+    async fn send_text_message(
+        &self,
+        chat_id: &str,
+        message: &str,
+        silent: bool,
+        reply_markup: Option<&str>,
+    ) -> Result<Attempt, Box<dyn Error>> {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.bot_token);
+
+        // Use form data to match what curl is doing, with HTML formatting
+        let mut params = vec![
+            ("chat_id", chat_id),
+            ("text", message),
+            ("parse_mode", "HTML"),
+            (
+                "disable_notification",
+                if silent { "true" } else { "false" },
+            ),
+            (
+                "disable_web_page_preview",
+                if DISABLE_LINK_PREVIEW {
+                    "true"
+                } else {
+                    "false"
+                },
+            ),
+        ];
+        if let Some(reply_markup) = reply_markup {
+            params.push(("reply_markup", reply_markup));
+        }
+
+        // Wait until the chat's rate limit allows another message
+        self.governor.acquire(TELEGRAM_API_HOST).await;
+        self.throttle.acquire(chat_id).await;
+
+        // Send the POST request to the Telegram API with the text message
+        let response = self.client.post(&url).form(&params).send().await?;
+
+        // Check if the response is successful
+        if response.status().is_success() {
+            let body = response.bytes().await?;
+            log!("Textnachricht erfolgreich gesendet.");
+            return Ok(Attempt::Accepted(Accepted::from_body(&body)));
+        }
+
+        // Handle error response
+        let status = response.status();
+        let error_bytes = response.bytes().await?;
+
+        // Try to parse the error response as JSON
+        if let Some(retry_after) = retry_after_from_error(&error_bytes) {
+            return Ok(Attempt::RetryAfter(retry_after));
+        }
+
+        // Construct and return a detailed error with status code and response body
+        Err(api_error(status, &error_bytes))
+    }
+
+    /// Sends a map pin to a Telegram chat.
+    ///
+    /// # Arguments
+    /// * `chat_id` - The chat to post to.
+    /// * `latitude` - The latitude of the pin.
+    /// * `longitude` - The longitude of the pin.
+    /// * `silent` - Whether to deliver the pin without a notification sound.
+    ///
+    /// # Returns
+    /// `Attempt::Accepted` with the ID of the posted message on success, `Attempt::RetryAfter` if
+    /// Telegram asks us to retry later, or an error for any other failure.
+    async fn send_location(
+        &self,
+        chat_id: &str,
+        latitude: f64,
+        longitude: f64,
+        silent: bool,
+    ) -> Result<Attempt, Box<dyn Error>> {
+        let url = format!(
+            "https://api.telegram.org/bot{}/sendLocation",
+            self.bot_token
+        );
+        let latitude = latitude.to_string();
+        let longitude = longitude.to_string();
+        let params = [
+            ("chat_id", chat_id),
+            ("latitude", latitude.as_str()),
+            ("longitude", longitude.as_str()),
+            (
+                "disable_notification",
+                if silent { "true" } else { "false" },
+            ),
+        ];
+
+        // Wait until the chat's rate limit allows another message
+        self.governor.acquire(TELEGRAM_API_HOST).await;
+        self.throttle.acquire(chat_id).await;
+
+        let response = self.client.post(&url).form(&params).send().await?;
+        if response.status().is_success() {
+            let body = response.bytes().await?;
+            log!("Standort erfolgreich gesendet.");
+            return Ok(Attempt::Accepted(Accepted::from_body(&body)));
+        }
+
+        // Handle error response
+        let status = response.status();
+        let error_bytes = response.bytes().await?;
+        if let Some(retry_after) = retry_after_from_error(&error_bytes) {
+            return Ok(Attempt::RetryAfter(retry_after));
+        }
+        Err(api_error(status, &error_bytes))
+    }
+
+    /// Deletes a message the bot posted in a Telegram chat.
+    ///
+    /// # Returns
+    /// `Attempt::Accepted` once deleted, `Attempt::RetryAfter` if Telegram asks us to retry later,
+    /// or an error for any other failure.
+    async fn send_deletion(
+        &self,
+        chat_id: &str,
+        message_id: i64,
+    ) -> Result<Attempt, Box<dyn Error>> {
+        let url = format!(
+            "https://api.telegram.org/bot{}/deleteMessage",
+            self.bot_token
+        );
+        let message_id = message_id.to_string();
+        let params = [("chat_id", chat_id), ("message_id", message_id.as_str())];
+
+        // Deletions count against the same rate limit as messages
+        self.governor.acquire(TELEGRAM_API_HOST).await;
+        self.throttle.acquire(chat_id).await;
+
+        let response = self.client.post(&url).form(&params).send().await?;
+        if response.status().is_success() {
+            return Ok(Attempt::Accepted(Accepted {
+                message_id: None,
+                file_id: None,
+            }));
+        }
+
+        // Handle error response
+        let status = response.status();
+        let error_bytes = response.bytes().await?;
+        if let Some(retry_after) = retry_after_from_error(&error_bytes) {
+            return Ok(Attempt::RetryAfter(retry_after));
+        }
+        Err(api_error(status, &error_bytes))
+    }
+
+    /// Pins a message the bot posted in a Telegram chat.
+    ///
+    /// The pin is always silent: the message itself already notified the chat, and a pin with
+    /// a notification would alert every member a second time.
+    ///
+    /// # Returns
+    /// `Attempt::Accepted` once pinned, `Attempt::RetryAfter` if Telegram asks us to retry later,
+    /// or an error for any other failure, e.g. if the bot isn't allowed to pin messages.
+    async fn send_pin(&self, chat_id: &str, message_id: i64) -> Result<Attempt, Box<dyn Error>> {
+        let url = format!(
+            "https://api.telegram.org/bot{}/pinChatMessage",
+            self.bot_token
+        );
+        let message_id = message_id.to_string();
+        let params = [
+            ("chat_id", chat_id),
+            ("message_id", message_id.as_str()),
+            ("disable_notification", "true"),
+        ];
+
+        // Pins count against the same rate limit as messages
+        self.governor.acquire(TELEGRAM_API_HOST).await;
+        self.throttle.acquire(chat_id).await;
+
+        let response = self.client.post(&url).form(&params).send().await?;
+        if response.status().is_success() {
+            return Ok(Attempt::Accepted(Accepted {
+                message_id: None,
+                file_id: None,
+            }));
+        }
+
+        // Handle error response
+        let status = response.status();
+        let error_bytes = response.bytes().await?;
+        if let Some(retry_after) = retry_after_from_error(&error_bytes) {
+            return Ok(Attempt::RetryAfter(retry_after));
+        }
+        Err(api_error(status, &error_bytes))
+    }
+
+    /// Pins the message of a top match if `PIN_TOP_MATCHES` is enabled.
+    ///
+    /// Failures are only logged, since the ad itself was already delivered.
+    async fn pin_top_match(&self, chat_id: &str, ad: &Ad, message_id: Option<i64>) {
+        if !PIN_TOP_MATCHES || !ad.top_match {
+            return;
+        }
+        let Some(message_id) = message_id else {
+            return;
+        };
+        let pin = Payload::Pin { message_id };
+        match self
+            .send_with_retries(
+                chat_id,
+                &pin,
+                true,
+                TELEGRAM_MAX_RETRIES,
+                &mut RetryBudget::default(),
+            )
+            .await
+        {
+            Ok(Some(_)) => {}
+            Ok(None) => log!("Top-Treffer wegen Rate Limiting nicht angeheftet."),
+            Err(e) => log!(
+                ad = &ad.id;
+                "Fehler beim Anheften des Top-Treffers '{}': {}",
+                ad.title,
+                e
+            ),
+        }
+    }
+
+    /// Deletes a message the bot posted, e.g. for `--delete-search`.
+    ///
+    /// # Returns
+    /// `Ok(true)` once deleted, `Ok(false)` if Telegram can't delete it anymore because it is
+    /// older than 48 hours or already gone, or an error for any other failure.
+    pub async fn delete_message(
+        &self,
+        chat_id: &str,
+        message_id: i64,
+    ) -> Result<bool, Box<dyn Error>> {
+        let deletion = Payload::Deletion { message_id };
+        match self
+            .send_with_retries(
+                chat_id,
+                &deletion,
+                false,
+                TELEGRAM_MAX_RETRIES,
+                &mut RetryBudget::default(),
+            )
+            .await
+        {
+            Ok(Some(_)) => Ok(true),
+            Ok(None) => Err("Wartezeit für Rate Limiting überschreitet das Limit".into()),
+            // "message can't be deleted" or "message to delete not found"
+            Err(e) if e.is::<BadRequest>() => {
+                log!(
+                    "Nachricht {} in Chat {} kann nicht gelöscht werden: {}",
+                    message_id,
+                    chat_id,
+                    e
+                );
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Follows up an ad with a map pin if pins are enabled and its coordinates are known.
+    ///
+    /// Failures are only logged, since the ad itself was already delivered.
+    ///
+    /// # Returns
+    /// The ID of the pin's message, if one was posted.
+    async fn send_location_pin(&self, chat_id: &str, ad: &Ad, silent: bool) -> Option<i64> {
+        if !SEND_LOCATION_PIN {
+            return None;
+        }
+        let (latitude, longitude) = ad.location.as_deref().and_then(coordinates_for)?;
+
+        let pin = Payload::Location {
+            latitude,
+            longitude,
+        };
+        match self
+            .send_with_retries(
+                chat_id,
+                &pin,
+                silent,
+                TELEGRAM_MAX_RETRIES,
+                &mut RetryBudget::default(),
+            )
+            .await
+        {
+            Ok(Some(accepted)) => accepted.message_id,
+            Ok(None) => {
+                log!("Standort wegen Rate Limiting nicht gesendet.");
+                None
+            }
+            Err(e) => {
+                log!("Fehler beim Senden des Standorts: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Sends a plain informational message, such as a reply to a command.
+    ///
+    /// # Arguments
+    /// * `chat_id` - The chat to post to.
+    /// * `text` - The HTML-formatted message string to send.
+    ///
+    /// # Returns
+    /// `Ok(())` once the message was delivered, or an error if all attempts failed.
+    pub async fn send_message(&self, chat_id: &str, text: &str) -> Result<(), Box<dyn Error>> {
+        if self
+            .send_text_with_retries(chat_id, text, false, None, &mut RetryBudget::default())
+            .await
+            .is_some()
+        {
+            Ok(())
+        } else {
+            Err("Telegram-Nachricht konnte nicht zugestellt werden".into())
+        }
+    }
+
+    /// Sends a payload, waiting out Telegram's rate limits up to `max_retries` times.
+    ///
+    /// # Arguments
+    /// * `chat_id` - The chat to post to.
+    /// * `payload` - The message to send.
+    /// * `silent` - Whether to deliver the message without a notification sound.
+    /// * `max_retries` - How often a rate-limited attempt is repeated.
+    /// * `budget` - The retry budget of the message this payload belongs to.
+    ///
+    /// # Returns
+    /// `Ok(Some(_))` once Telegram accepted the request, `Ok(None)` if it was still rate-limited
+    /// after all retries or a wait exceeded the budget, or the error of a failure other than
+    /// rate limiting.
+    async fn send_with_retries(
+        &self,
+        chat_id: &str,
+        payload: &Payload<'_>,
+        silent: bool,
+        max_retries: u32,
+        budget: &mut RetryBudget,
+    ) -> Result<Option<Accepted>, Box<dyn Error>> {
+        let mut retries = 0;
+        loop {
+            let result = match *payload {
+                Payload::Photo {
+                    url,
+                    caption,
+                    reply_markup,
+                } => {
+                    self.send_photo_message(chat_id, url, caption, silent, reply_markup)
+                        .await
+                }
+                Payload::PhotoUpload {
+                    url,
+                    caption,
+                    reply_markup,
+                } => {
+                    self.upload_image(
+                        "sendPhoto",
+                        "photo",
+                        chat_id,
+                        url,
+                        caption,
+                        silent,
+                        reply_markup,
+                    )
+                    .await
+                }
+                Payload::Document {
+                    url,
+                    caption,
+                    reply_markup,
+                } => {
+                    self.upload_image(
+                        "sendDocument",
+                        "document",
+                        chat_id,
+                        url,
+                        caption,
+                        silent,
+                        reply_markup,
+                    )
+                    .await
+                }
+                Payload::Text { text, reply_markup } => {
+                    self.send_text_message(chat_id, text, silent, reply_markup)
+                        .await
+                }
+                Payload::Location {
+                    latitude,
+                    longitude,
+                } => {
+                    self.send_location(chat_id, latitude, longitude, silent)
+                        .await
+                }
+                Payload::Deletion { message_id } => self.send_deletion(chat_id, message_id).await,
+                Payload::Pin { message_id } => self.send_pin(chat_id, message_id).await,
+            };
+            let retry_after = match result {
+                Ok(Attempt::Accepted(accepted)) => return Ok(Some(accepted)),
+                Ok(Attempt::RetryAfter(retry_after)) => retry_after,
+                Err(e) => {
+                    if let Some(unreachable) = e.downcast_ref::<ChatUnreachable>() {
+                        self.drop_chat(chat_id, unreachable);
+                    }
+                    return Err(e);
+                }
+            };
+            if retries == max_retries {
+                log!(
+                    "Nach {} Wiederholungen weiterhin Rate Limiting.",
+                    max_retries
+                );
+                return Ok(None);
+            }
+            retries += 1;
+            log!(
+                "Rate limiting erkannt. Warte {} Sekunden vor Wiederholung {} von {}.",
+                retry_after,
+                retries,
+                max_retries
+            );
+
+            // Wait for the specified duration before retrying, unless it exceeds the caps
+            if !budget.wait(retry_after).await {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Checks with a HEAD request whether an image URL, after following redirects, serves an image.
+    async fn image_available(&self, url: &str) -> bool {
+        self.governor.acquire_url(url).await;
+        match self.image_client.head(url).send().await {
+            Ok(response) => {
+                let content_type = response
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok());
+                is_image_response(response.status(), content_type)
+            }
+            Err(e) => {
+                log!("Fehler beim Prüfen des Bildes {}: {}", url, e);
+                false
+            }
+        }
+    }
+
+    /// Sends the photo of an ad without caption, after its details went out as text.
+    ///
+    /// # Arguments
+    /// * `chat_id` - The chat to post to.
+    /// * `image_url` - The image URL as scraped, tried in each configured resolution.
+    /// * `silent` - Whether to deliver the message without a notification sound.
+    /// * `budget` - The retry budget of the message this photo belongs to.
+    ///
+    /// # Returns
+    /// The accepted photo, or `None` if it couldn't be delivered.
+    async fn send_uncaptioned_photo(
+        &self,
+        chat_id: &str,
+        image_url: &str,
+        silent: bool,
+        budget: &mut RetryBudget,
+    ) -> Option<Accepted> {
+        if let Some(accepted) = self
+            .send_stored_photo(chat_id, image_url, "", silent, None, budget)
+            .await
+        {
+            return Some(accepted);
+        }
+
+        // Try the image in each configured resolution.
+        for photo_url in image_url_variants(image_url) {
+            let photo = image_payload(&photo_url, "", None);
+            match self
+                .send_with_retries(chat_id, &photo, silent, TELEGRAM_MAX_RETRIES, budget)
+                .await
+            {
+                Ok(accepted) => {
+                    if let Some(file_id) = accepted.as_ref().and_then(|a| a.file_id.as_deref()) {
+                        self.update_file_id(image_url, Some(file_id));
+                    }
+                    return accepted;
+                }
+                Err(e)
+                    if e.downcast_ref::<BadRequest>().map(|bad| bad.kind)
+                        == Some(BadRequestKind::Image) =>
+                {
+                    log!(
+                        "Telegram kann das Bild nicht verwenden ({}). Versuche eine andere Auflösung.",
+                        e
+                    );
+                }
+                Err(e) => {
+                    log!("Fehler beim Senden der Fotonachricht: {}", e);
+                    return None;
+                }
+            }
+        }
+        None
+    }
+
+    /// Sends a text message, retrying up to `TELEGRAM_MAX_RETRIES` times on rate limits.
+    ///
+    /// # Arguments
+    /// * `chat_id` - The chat to post to.
+    /// * `text` - The HTML-formatted message string to send.
+    /// * `silent` - Whether to deliver the message without a notification sound.
+    /// * `reply_markup` - The serialized inline keyboard attached to the message, if any.
+    /// * `budget` - The retry budget of the message this text belongs to.
+    ///
+    /// # Returns
+    /// The accepted message, or `None` if it couldn't be delivered.
+    async fn send_text_with_retries(
+        &self,
+        chat_id: &str,
+        text: &str,
+        silent: bool,
+        reply_markup: Option<&str>,
+        budget: &mut RetryBudget,
+    ) -> Option<Accepted> {
+        let message = Payload::Text { text, reply_markup };
+        self.send_with_retries(chat_id, &message, silent, TELEGRAM_MAX_RETRIES, budget)
+            .await
+            .unwrap_or_else(|e| {
+                log!("Fehler beim Senden der Textnachricht: {}", e);
+                None
+            })
+    }
+
+    /// Delivers an ad to a single chat, falling back to text if the photo can't be sent.
+    ///
+    /// # Arguments
+    /// * `ad` - The ad to announce.
+    /// * `chat_id` - The chat to post to.
+    /// * `silent` - Whether to deliver the ad without a notification sound.
+    ///
+    /// # Returns
+    /// The IDs of the messages the ad was posted as, or an error if all attempts failed.
+    async fn send_ad_to_chat(
+        &self,
+        ad: &Ad,
+        chat_id: &str,
+        silent: bool,
+    ) -> Result<Vec<i64>, Box<dyn Error>> {
+        let caption = build_caption(ad, CaptionFormat::Html);
+        // Text messages optionally say that the photo is missing.
+        let text_caption = match NO_IMAGE_MARKER {
+            Some(marker) => format!("{}\n<i>{}</i>", caption, escape_html(marker)),
+            None => caption.clone(),
+        };
+        // The buttons let the chat dismiss the ad; they're handled by the command listener.
+        let keyboard = ENABLE_AD_BUTTONS.then(|| ad_keyboard(&ad.id));
+        let mut budget = RetryBudget::default();
+
+        // Optionally check the image first, so a broken redirect doesn't cost a failed sendPhoto.
+        let mut image_url = ad.image_url.as_deref();
+        if VALIDATE_IMAGE_URLS
+            && let Some(url) = image_url
+            && !self.image_available(url).await
+        {
+            log!(
+                ad = &ad.id;
+                "Das Bild der Anzeige '{}' ist nicht abrufbar. Sende die Anzeige ohne Bild.",
+                ad.title
+            );
+            image_url = None;
+        }
+
+        // If the ad has an image, send a photo message. Otherwise, send a text message.
+        let mut posted;
+        let mut photo_follow_up = None;
+        if TEXT_BEFORE_PHOTO && let Some(image_url) = image_url {
+            // The details arrive as plain text, which screen readers handle reliably,
+            // and the photo follows without a caption. The ad counts as delivered with the text.
+            posted = self
+                .send_text_with_retries(chat_id, &caption, silent, keyboard.as_deref(), &mut budget)
+                .await;
+            if posted.is_some() {
+                photo_follow_up = self
+                    .send_uncaptioned_photo(chat_id, image_url, silent, &mut budget)
+                    .await;
+                if photo_follow_up.is_none() {
+                    log!(
+                        "Das Foto zur Anzeige '{}' konnte nicht gesendet werden.",
+                        ad.title
+                    );
+                }
+            }
+        } else if let Some(image_url) = image_url {
+            // A photo Telegram already has is sent by its file_id.
+            posted = self
+                .send_stored_photo(
+                    chat_id,
+                    image_url,
+                    &caption,
+                    silent,
+                    keyboard.as_deref(),
+                    &mut budget,
+                )
+                .await;
+
+            // Otherwise, try the image in each configured resolution before giving up on it.
+            let photo_urls = if posted.is_some() {
+                Vec::new()
+            } else {
+                image_url_variants(image_url)
+            };
+            for (attempt, photo_url) in photo_urls.iter().enumerate() {
+                let photo = image_payload(photo_url, &caption, keyboard.as_deref());
+                match self
+                    .send_with_retries(chat_id, &photo, silent, TELEGRAM_MAX_RETRIES, &mut budget)
+                    .await
+                {
+                    Ok(accepted) => {
+                        if let Some(file_id) = accepted.as_ref().and_then(|a| a.file_id.as_deref())
+                        {
+                            self.update_file_id(image_url, Some(file_id));
+                        }
+                        posted = accepted;
+                    }
+                    Err(e) => {
+                        let kind = e.downcast_ref::<BadRequest>().map(|bad| bad.kind);
+                        if kind == Some(BadRequestKind::Formatting) || e.is::<ChatUnreachable>() {
+                            // The same caption would fail as text too, or the chat is gone entirely.
+                            return Err(e);
+                        }
+                        if kind == Some(BadRequestKind::Image) && attempt + 1 < photo_urls.len() {
+                            log!(
+                                "Telegram kann das Bild nicht verwenden ({}). Versuche eine andere Auflösung.",
+                                e
+                            );
+                            continue;
+                        }
+                        if kind == Some(BadRequestKind::Image)
+                            && UPLOAD_PHOTO_FALLBACK
+                            && IMAGE_MODE == ImageMode::Photo
+                        {
+                            log!(
+                                "Telegram kann das Bild nicht verwenden ({}). Lade es selbst hoch.",
+                                e
+                            );
+                            let upload = Payload::PhotoUpload {
+                                url: image_url,
+                                caption: &caption,
+                                reply_markup: keyboard.as_deref(),
+                            };
+                            match self
+                                .send_with_retries(
+                                    chat_id,
+                                    &upload,
+                                    silent,
+                                    TELEGRAM_MAX_RETRIES,
+                                    &mut budget,
+                                )
+                                .await
+                            {
+                                Ok(Some(accepted)) => {
+                                    // The next time, Telegram has the photo without an upload.
+                                    if let Some(file_id) = &accepted.file_id {
+                                        self.update_file_id(image_url, Some(file_id));
+                                    }
+                                    posted = Some(accepted);
+                                    break;
+                                }
+                                Ok(None) => {}
+                                Err(e) => log!(
+                                    "Fehler beim Hochladen des Bildes: {}. Sende die Anzeige ohne Bild.",
+                                    e
+                                ),
+                            }
+                        } else if kind == Some(BadRequestKind::Image) {
+                            log!(
+                                "Telegram kann das Bild nicht verwenden ({}). Sende die Anzeige ohne Bild.",
+                                e
+                            );
+                        } else {
+                            log!(
+                                "Fehler beim Senden der Fotonachricht: {}. Fallback auf Textnachricht.",
+                                e
+                            );
+                        }
+
+                        // If sending the photo fails, try sending a text message instead.
+                        posted = self
+                            .send_text_with_retries(
+                                chat_id,
+                                &text_caption,
+                                silent,
+                                keyboard.as_deref(),
+                                &mut budget,
+                            )
+                            .await;
+                    }
+                }
+                break;
+            }
+        } else {
+            posted = self
+                .send_text_with_retries(
+                    chat_id,
+                    &text_caption,
+                    silent,
+                    keyboard.as_deref(),
+                    &mut budget,
+                )
+                .await;
+        }
+
+        let Some(posted) = posted else {
+            return Err("Telegram-Nachricht konnte nicht zugestellt werden".into());
+        };
+        self.pin_top_match(chat_id, ad, posted.message_id).await;
+        let pin = self.send_location_pin(chat_id, ad, silent).await;
+        Ok([
+            posted.message_id,
+            photo_follow_up.and_then(|photo| photo.message_id),
+            pin,
+        ]
+        .into_iter()
+        .flatten()
+        .collect())
+    }
+}
+
+/// How long a stored `file_id` is reused, see `REUSE_FILE_IDS`.
+fn file_id_ttl() -> TimeDelta {
+    TimeDelta::days(FILE_ID_TTL_DAYS)
+}
+
+/// Builds the payload posting an image as configured in `IMAGE_MODE`.
+fn image_payload<'a>(url: &'a str, caption: &'a str, reply_markup: Option<&'a str>) -> Payload<'a> {
+    match IMAGE_MODE {
+        ImageMode::Photo => Payload::Photo {
+            url,
+            caption,
+            reply_markup,
+        },
+        ImageMode::Document => Payload::Document {
+            url,
+            caption,
+            reply_markup,
+        },
+    }
+}
+
+/// Builds a `multipart/form-data` body separated by `MULTIPART_BOUNDARY`.
+///
+/// # Arguments
+/// * `fields` - The text fields as (name, value).
+/// * `file_field` - The name of the field carrying the file.
+/// * `file` - The bytes of the file.
+fn multipart_body(fields: &[(&str, &str)], file_field: &str, file: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(file.len() + 1024);
+    for (name, value) in fields {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                MULTIPART_BOUNDARY, name, value
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}.jpg\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+            MULTIPART_BOUNDARY, file_field, file_field
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(file);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", MULTIPART_BOUNDARY).as_bytes());
+    body
+}
+
+/// Checks whether downloaded image bytes are WebP, by their RIFF header.
+fn is_webp(image: &[u8]) -> bool {
+    image.len() >= 12 && &image[..4] == b"RIFF" && &image[8..12] == b"WEBP"
+}
+
+/// Turns an image URL with an "AUTO" resolution rule, which lets the server pick the format,
+/// into one asking for JPEG, e.g. "?rule=$_59.AUTO" into "?rule=$_59.JPG".
+///
+/// # Returns
+/// The JPEG URL, or `None` if the URL has no such rule.
+fn jpeg_variant(image_url: &str) -> Option<String> {
+    let (base_url, rule) = image_url.split_once("?rule=")?;
+    let size = rule.strip_suffix(".AUTO")?;
+    Some(format!("{}?rule={}.JPG", base_url, size))
+}
+
+/// Checks whether a response to an image URL can be sent as a photo: 2xx with an image content type.
+fn is_image_response(status: StatusCode, content_type: Option<&str>) -> bool {
+    status.is_success()
+        && content_type.is_some_and(|content_type| {
+            content_type
+                .trim_start()
+                .to_ascii_lowercase()
+                .starts_with("image/")
+        })
+}
+
+/// Builds the inline keyboard with the "Interessiert" and "Ignorieren" buttons of an ad.
+fn ad_keyboard(ad_id: &str) -> String {
+    json!({
+        "inline_keyboard": [[
+            {
+                "text": "✅ Interessiert",
+                "callback_data": AdAction::Interested.callback_data(ad_id),
+            },
+            {
+                "text": "🔕 Ignorieren",
+                "callback_data": AdAction::Ignore.callback_data(ad_id),
+            },
+        ]]
+    })
+    .to_string()
+}
+
+/// Builds the error for a failed API call, classifying `400 Bad Request` answers.
+fn api_error(status: StatusCode, error_bytes: &[u8]) -> Box<dyn Error> {
+    let description = from_slice::<TelegramError>(error_bytes)
+        .ok()
+        .and_then(|error| error.description);
+    if let Some(description) = description {
+        if is_chat_unreachable(status, &description) {
+            return Box::new(ChatUnreachable { description });
+        }
+        if status == StatusCode::BAD_REQUEST {
+            let kind = classify_bad_request(&description);
+            return Box::new(BadRequest { kind, description });
+        }
+    }
+
+    let error_body = String::from_utf8_lossy(error_bytes);
+    format!("Telegram API Fehler: {} - {}", status, error_body).into()
+}
+
+/// Checks whether an error means the chat can't be posted to at all, such as
+/// "Forbidden: bot was blocked by the user" or "Bad Request: chat not found".
+fn is_chat_unreachable(status: StatusCode, description: &str) -> bool {
+    let description = description.to_lowercase();
+    status == StatusCode::FORBIDDEN
+        || (status == StatusCode::BAD_REQUEST && description.contains("chat not found"))
+}
+
+/// Determines from Telegram's error description whether the image or the text was at fault.
+fn classify_bad_request(description: &str) -> BadRequestKind {
+    const IMAGE_MARKERS: &[&str] = &[
+        "photo_invalid_dimensions",
+        "image_process_failed",
+        "photo_save_file_invalid",
+        "photo_ext_invalid",
+        "wrong file identifier/http url",
+        "failed to get http url content",
+        "wrong type of the web page content",
+        "file is too big",
+    ];
+    const FORMATTING_MARKERS: &[&str] = &[
+        "can't parse entities",
+        "caption is too long",
+        "message is too long",
+        "text must be non-empty",
+        "unsupported start tag",
+    ];
+
+    let description = description.to_lowercase();
+    if IMAGE_MARKERS
+        .iter()
+        .any(|marker| description.contains(marker))
+    {
+        BadRequestKind::Image
+    } else if FORMATTING_MARKERS
+        .iter()
+        .any(|marker| description.contains(marker))
+    {
+        BadRequestKind::Formatting
+    } else {
+        BadRequestKind::Other
+    }
+}
+
+/// Extracts the retry delay from a Telegram error body if it reports rate limiting.
+///
+/// # Returns
+/// The number of seconds to wait, or `None` if the error was not a rate limit.
+fn retry_after_from_error(error_bytes: &[u8]) -> Option<i64> {
+    let telegram_error = from_slice::<TelegramError>(error_bytes).ok()?;
+    if telegram_error.error_code != Some(429) {
+        return None;
+    }
+
+    // Default retry after 30 seconds if not specified
+    Some(
+        telegram_error
+            .parameters
+            .and_then(|params| params.retry_after)
+            .unwrap_or(30),
+    )
+}
+
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &'static str {
+        "Telegram"
+    }
+
+    async fn send_ad(
+        &self,
+        ad: &Ad,
+        delivery: &Delivery<'_>,
+    ) -> Result<Vec<PostedMessage>, Box<dyn Error>> {
+        // The ad counts as delivered once any of its chats received it, so the chats that missed
+        // it are tried once more after the others before it's marked as seen.
+        let mut delivered = false;
+        let mut posted = Vec::new();
+        let mut missed: Vec<&str> = Vec::new();
+        for chat_id in delivery.chat_ids {
+            if self.is_unreachable(chat_id) {
+                continue;
+            }
+            match self.send_ad_to_chat(ad, chat_id, delivery.silent).await {
+                Ok(message_ids) => {
+                    delivered = true;
+                    posted.extend(message_ids.into_iter().map(|message_id| PostedMessage {
+                        chat_id: chat_id.to_string(),
+                        message_id,
+                    }));
+                }
+                Err(e) => {
+                    log!("Fehler beim Senden an Chat {}: {}", chat_id, e);
+                    missed.push(chat_id);
+                }
+            }
+        }
+        if delivered {
+            for chat_id in missed {
+                if self.is_unreachable(chat_id) {
+                    continue;
+                }
+                log!(ad = &ad.id; "Sende die Anzeige '{}' erneut an Chat {}.", ad.title, chat_id);
+                match self.send_ad_to_chat(ad, chat_id, delivery.silent).await {
+                    Ok(message_ids) => {
+                        posted.extend(message_ids.into_iter().map(|message_id| PostedMessage {
+                            chat_id: chat_id.to_string(),
+                            message_id,
+                        }))
+                    }
+                    Err(e) => log!(
+                        ad = &ad.id;
+                        "Fehler: Chat {} erhält die Anzeige '{}' nicht, die übrigen Chats haben sie bereits: {}",
+                        chat_id,
+                        ad.title,
+                        e
+                    ),
+                }
+            }
+        }
+
+        // Rather than missing the ad, try the fallback chat once the regular ones failed.
+        if !delivered
+            && let Some(fallback) = FALLBACK_CHAT_ID
+            && !delivery.chat_ids.contains(&fallback)
+            && !self.is_unreachable(fallback)
+        {
+            log!(
+                ad = &ad.id;
+                "Sende die Anzeige '{}' an den Ersatz-Chat {}.",
+                ad.title,
+                fallback
+            );
+            match self.send_ad_to_chat(ad, fallback, delivery.silent).await {
+                Ok(message_ids) => {
+                    delivered = true;
+                    posted.extend(message_ids.into_iter().map(|message_id| PostedMessage {
+                        chat_id: fallback.to_string(),
+                        message_id,
+                    }));
+                }
+                Err(e) => log!("Fehler beim Senden an den Ersatz-Chat {}: {}", fallback, e),
+            }
+        }
+
+        if delivered {
+            Ok(posted)
+        } else if !delivery.chat_ids.is_empty()
+            && delivery
+                .chat_ids
+                .iter()
+                .all(|chat_id| self.is_unreachable(chat_id))
+        {
+            Err(Box::new(ChatUnreachable {
+                description: format!("Chat(s) {}", delivery.chat_ids.join(", ")),
+            }))
+        } else {
+            Err("Telegram-Nachricht konnte nicht zugestellt werden".into())
+        }
+    }
+
+    async fn send_notice(&self, text: &str, delivery: &Delivery<'_>) -> Result<(), Box<dyn Error>> {
+        let text = escape_html(text);
+        let mut delivered = false;
+        for chat_id in delivery.chat_ids {
+            if !self.is_unreachable(chat_id)
+                && self
+                    .send_text_with_retries(
+                        chat_id,
+                        &text,
+                        delivery.silent,
+                        None,
+                        &mut RetryBudget::default(),
+                    )
+                    .await
+                    .is_some()
+            {
+                delivered = true;
+            }
+        }
+
+        if delivered {
+            Ok(())
+        } else {
+            Err("Telegram-Nachricht konnte nicht zugestellt werden".into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_chats_the_bot_cannot_post_to() {
+        assert!(is_chat_unreachable(
+            StatusCode::FORBIDDEN,
+            "Forbidden: bot was blocked by the user"
+        ));
+        assert!(is_chat_unreachable(
+            StatusCode::BAD_REQUEST,
+            "Bad Request: chat not found"
+        ));
+        assert!(!is_chat_unreachable(
+            StatusCode::BAD_REQUEST,
+            "Bad Request: can't parse entities"
+        ));
+        assert!(api_error(
+            StatusCode::FORBIDDEN,
+            br#"{"ok":false,"error_code":403,"description":"Forbidden: bot was kicked from the group chat"}"#
+        )
+        .is::<ChatUnreachable>());
+    }
+
+    #[test]
+    fn multipart_bodies_separate_fields_and_file() {
+        let body = multipart_body(&[("chat_id", "42")], "photo", b"\xff\xd8");
+        let mut expected = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"chat_id\"\r\n\r\n42\r\n--{b}\r\nContent-Disposition: form-data; name=\"photo\"; filename=\"photo.jpg\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+            b = MULTIPART_BOUNDARY
+        )
+        .into_bytes();
+        expected.extend_from_slice(b"\xff\xd8");
+        expected.extend_from_slice(format!("\r\n--{}--\r\n", MULTIPART_BOUNDARY).as_bytes());
+        assert_eq!(body, expected);
+    }
+
+    #[test]
+    fn accepted_photos_keep_the_file_id_of_the_largest_size() {
+        let body = br#"{"ok":true,"result":{"message_id":42,"photo":[{"file_id":"small","width":90},{"file_id":"large","width":1280}]}}"#;
+        let accepted = Accepted::from_body(body);
+        assert_eq!(accepted.message_id, Some(42));
+        assert_eq!(accepted.file_id.as_deref(), Some("large"));
+
+        let text = Accepted::from_body(br#"{"ok":true,"result":{"message_id":43}}"#);
+        assert_eq!(text.message_id, Some(43));
+        assert_eq!(text.file_id, None);
+    }
+
+    #[test]
+    fn only_successful_image_responses_are_usable() {
+        assert!(is_image_response(StatusCode::OK, Some("image/jpeg")));
+        assert!(is_image_response(StatusCode::OK, Some("Image/WebP")));
+        assert!(!is_image_response(
+            StatusCode::OK,
+            Some("text/html; charset=utf-8")
+        ));
+        assert!(!is_image_response(StatusCode::OK, None));
+        assert!(!is_image_response(
+            StatusCode::NOT_FOUND,
+            Some("image/jpeg")
+        ));
+    }
+
+    #[test]
+    fn webp_images_are_swapped_for_jpeg() {
+        assert!(is_webp(b"RIFF\x10\0\0\0WEBPVP8 "));
+        assert!(!is_webp(b"\xff\xd8\xff\xe0\0\x10JFIF\0"));
+        assert!(!is_webp(b"RIFF"));
+
+        assert_eq!(
+            jpeg_variant("https://img.example.de/a.jpg?rule=$_59.AUTO").as_deref(),
+            Some("https://img.example.de/a.jpg?rule=$_59.JPG")
+        );
+        assert_eq!(
+            jpeg_variant("https://img.example.de/a.jpg?rule=$_59.JPG"),
+            None
+        );
+        assert_eq!(jpeg_variant("https://img.example.de/a.jpg"), None);
+    }
+
+    #[test]
+    fn classifies_image_errors() {
+        assert_eq!(
+            classify_bad_request("Bad Request: PHOTO_INVALID_DIMENSIONS"),
+            BadRequestKind::Image
+        );
+        assert_eq!(
+            classify_bad_request("Bad Request: wrong file identifier/HTTP URL specified"),
+            BadRequestKind::Image
+        );
+    }
+
+    #[test]
+    fn classifies_formatting_errors() {
+        assert_eq!(
+            classify_bad_request(
+                "Bad Request: can't parse entities: Unsupported start tag \"x\" at byte offset 3"
+            ),
+            BadRequestKind::Formatting
+        );
+        assert_eq!(
+            classify_bad_request("Bad Request: message caption is too long"),
+            BadRequestKind::Formatting
+        );
+    }
+
+    #[test]
+    fn unknown_bad_requests_are_other() {
+        assert_eq!(
+            classify_bad_request("Bad Request: chat not found"),
+            BadRequestKind::Other
+        );
+    }
+}
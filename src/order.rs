@@ -0,0 +1,66 @@
+use std::cmp::{Ordering, Reverse};
+
+use chrono::{DateTime, Utc};
+
+use crate::Ad;
+
+/// The order in which new ads are sent within one run.
+// Only the variant chosen in the configuration is ever constructed.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationOrder {
+    /// Keep the order in which the ads appeared on the search pages.
+    PageOrder,
+    /// Send the most recently posted ads first.
+    NewestFirst,
+    /// Send the oldest ads first, so the chat reads chronologically.
+    OldestFirst,
+    /// Send the most viewed ads first. Ads without a view count go last.
+    MostViewedFirst,
+    /// Sort by ad ID, lowest first. Higher IDs are usually newer ads.
+    IdAscending,
+    /// Sort by ad ID, highest first.
+    IdDescending,
+}
+
+/// Compares two ad IDs numerically, falling back to string order for non-numeric IDs.
+fn compare_ids(a: &str, b: &str) -> Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+/// Compares two posting times, always placing ads with an unknown time last.
+fn compare_posting_times(
+    a: Option<DateTime<Utc>>,
+    b: Option<DateTime<Utc>>,
+    newest_first: bool,
+) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if newest_first => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Sorts ads in place according to `order`.
+///
+/// The sort is stable, so ads with equal keys keep their page order.
+pub fn sort_ads(ads: &mut [Ad], order: NotificationOrder) {
+    match order {
+        NotificationOrder::PageOrder => {}
+        NotificationOrder::NewestFirst => {
+            ads.sort_by(|a, b| compare_posting_times(a.posted_at, b.posted_at, true))
+        }
+        NotificationOrder::OldestFirst => {
+            ads.sort_by(|a, b| compare_posting_times(a.posted_at, b.posted_at, false))
+        }
+        // `Reverse` keeps `None` after every count.
+        NotificationOrder::MostViewedFirst => ads.sort_by_key(|ad| Reverse(ad.views)),
+        NotificationOrder::IdAscending => ads.sort_by(|a, b| compare_ids(&a.id, &b.id)),
+        NotificationOrder::IdDescending => ads.sort_by(|a, b| compare_ids(&b.id, &a.id)),
+    }
+}
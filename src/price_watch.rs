@@ -0,0 +1,125 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::{read_to_string, write},
+};
+
+use reqwest::Client;
+use serde_json::{from_str, to_string_pretty};
+
+use crate::{
+    notifier::{AnyNotifier, Delivery, Notifier},
+    scrape::scrape_listing_price,
+    throttle::RateGovernor,
+};
+
+/// Loads the last known prices in cents, keyed by listing URL.
+///
+/// If the file does not exist or contains invalid data, it returns an empty map.
+fn load_prices(path: &str) -> HashMap<String, u64> {
+    match read_to_string(path) {
+        Ok(content) => from_str(&content).unwrap_or_else(|e| {
+            log!("Fehler beim Parsen der Datei {}: {}", path, e);
+            HashMap::new()
+        }),
+        Err(_) => HashMap::new(),
+    }
+}
+
+/// Saves the last known prices to a JSON file.
+fn save_prices(path: &str, prices: &HashMap<String, u64>) -> Result<(), Box<dyn Error>> {
+    write(path, to_string_pretty(prices)?)?;
+    Ok(())
+}
+
+/// Formats a price in cents the way Kleinanzeigen shows it, e.g. "1200 €" or "12,50 €".
+fn format_price(cents: u64) -> String {
+    if cents.is_multiple_of(100) {
+        format!("{} €", cents / 100)
+    } else {
+        format!("{},{:02} €", cents / 100, cents % 100)
+    }
+}
+
+/// Re-checks the price of every watched listing and announces decreases.
+///
+/// The last known price of each listing is kept in `path`. The first check of a listing
+/// only records its price; increases and unchanged prices are recorded silently.
+///
+/// # Arguments
+/// * `client` - The `reqwest::Client` used for scraping.
+/// * `governor` - The request budgets the listing requests count against.
+/// * `notifiers` - All channels price drops are announced on.
+/// * `watched` - The URLs of the watched listings.
+/// * `path` - The file the last known prices are stored in.
+/// * `chat_ids` - The Telegram chats price drops are sent to.
+/// * `persist` - Whether to save the checked prices, `false` with `--no-persist`.
+pub async fn check_price_drops(
+    client: &Client,
+    governor: &RateGovernor,
+    notifiers: &[AnyNotifier],
+    watched: &[&str],
+    path: &str,
+    chat_ids: &[&str],
+    persist: bool,
+) {
+    if watched.is_empty() {
+        return;
+    }
+
+    let mut prices = load_prices(path);
+    for &url in watched {
+        governor.acquire_url(url).await;
+        let listing = match scrape_listing_price(client, url).await {
+            Ok(listing) => listing,
+            Err(e) => {
+                log!("Fehler beim Abrufen von {}: {}", url, e);
+                continue;
+            }
+        };
+        let Some(price) = listing.price else {
+            log!("Kein Preis gefunden für {}.", url);
+            continue;
+        };
+
+        let previous = prices.get(url).copied();
+        let mut delivered = true;
+        if let Some(previous) = previous
+            && price < previous
+        {
+            let text = format!(
+                "💸 Preis gesunken: {} → {}\n{}\n{}",
+                format_price(previous),
+                format_price(price),
+                listing.title.as_deref().unwrap_or("Beobachtete Anzeige"),
+                url
+            );
+            let delivery = Delivery {
+                chat_ids,
+                silent: false,
+            };
+            delivered = false;
+            for notifier in notifiers {
+                match notifier.send_notice(&text, &delivery).await {
+                    Ok(()) => delivered = true,
+                    Err(e) => log!(
+                        "Fehler beim Senden der Preisänderung über {}: {}",
+                        notifier.name(),
+                        e
+                    ),
+                }
+            }
+        }
+
+        // Keep the old price if the drop couldn't be announced, so it's retried next run.
+        if delivered {
+            prices.insert(url.to_string(), price);
+        }
+    }
+
+    // Forget listings that are no longer watched.
+    prices.retain(|url, _| watched.contains(&url.as_str()));
+    if persist && let Err(e) = save_prices(path, &prices) {
+        log!("Fehler beim Speichern der Datei {}: {}", path, e);
+    }
+}
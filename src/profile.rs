@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use crate::search::Search;
+
+/// An independent bot run from the same process, with its own credentials, searches and state files.
+pub struct Profile {
+    /// A short name used in log messages and in front of the state files, e.g. "anna".
+    /// The profile with an empty name keeps the plain file names.
+    pub name: &'static str,
+    /// The token of the Telegram bot this profile sends with.
+    pub telegram_bot_token: &'static str,
+    /// The chat the ads go to, unless a search names its own.
+    pub telegram_chat_id: &'static str,
+    /// The searches scanned for this profile.
+    pub searches: &'static [Search],
+    /// The listings whose price drops are announced in this profile's chat, see `WATCHED_ADS`.
+    pub watched_ads: &'static [&'static str],
+}
+
+impl Profile {
+    /// Checks whether the Telegram placeholders were replaced.
+    pub fn telegram_configured(&self) -> bool {
+        self.telegram_bot_token != "YOUR_TELEGRAM_BOT_TOKEN"
+            && self.telegram_chat_id != "YOUR_GROUP_CHAT_ID"
+    }
+
+    /// Returns the profile's chat as a list, as expected by `Delivery` and `Search::target_chats`.
+    pub fn default_chats(&'static self) -> &'static [&'static str] {
+        std::slice::from_ref(&self.telegram_chat_id)
+    }
+
+    /// Returns where this profile keeps a state file, e.g. "anna_seen_ads.json" for "seen_ads.json".
+    ///
+    /// The prefix goes in front of the file name, so files in other directories work too.
+    pub fn state_file(&self, file: &str) -> String {
+        if self.name.is_empty() {
+            return file.to_string();
+        }
+        let path = Path::new(file);
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        path.with_file_name(format!("{}_{}", self.name, file_name))
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &'static str) -> Profile {
+        Profile {
+            name,
+            telegram_bot_token: "YOUR_TELEGRAM_BOT_TOKEN",
+            telegram_chat_id: "YOUR_GROUP_CHAT_ID",
+            searches: &[],
+            watched_ads: &[],
+        }
+    }
+
+    #[test]
+    fn state_files_are_prefixed_with_the_profile_name() {
+        assert_eq!(profile("").state_file("seen_ads.json"), "seen_ads.json");
+        assert_eq!(
+            profile("anna").state_file("seen_ads.json"),
+            "anna_seen_ads.json"
+        );
+        assert_eq!(
+            profile("anna").state_file("/var/lib/bot/cursors.json"),
+            "/var/lib/bot/anna_cursors.json"
+        );
+    }
+}
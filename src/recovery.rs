@@ -0,0 +1,78 @@
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    fs::{read_to_string, write},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string_pretty};
+
+/// What the last run left behind, to tell when the bot has recovered from failed deliveries.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RunState {
+    /// The IDs of the ads the last run couldn't deliver.
+    #[serde(default)]
+    pub undelivered: BTreeSet<String>,
+}
+
+/// Loads the state of the last run.
+///
+/// If the file does not exist or contains invalid data, it returns a state without failures.
+pub fn load_run_state(path: &str) -> RunState {
+    match read_to_string(path) {
+        Ok(content) => from_str(&content).unwrap_or_else(|e| {
+            log!("Fehler beim Parsen der Datei {}: {}", path, e);
+            RunState::default()
+        }),
+        Err(_) => RunState::default(),
+    }
+}
+
+/// Saves the state of this run for the next one.
+pub fn save_run_state(path: &str, state: &RunState) -> Result<(), Box<dyn Error>> {
+    write(path, to_string_pretty(state)?)?;
+    Ok(())
+}
+
+/// Checks whether this run cleared the backlog of a previous run with failed deliveries.
+///
+/// # Arguments
+/// * `previous` - The state of the last run.
+/// * `delivered` - The IDs of the ads delivered in this run.
+/// * `undelivered` - The IDs of the ads this run couldn't deliver.
+///
+/// # Returns
+/// The number of previously undelivered ads that were delivered now, or `None` if the last
+/// run had no failures or this one failed too.
+pub fn recovered(
+    previous: &RunState,
+    delivered: &[String],
+    undelivered: &[String],
+) -> Option<usize> {
+    if previous.undelivered.is_empty() || !undelivered.is_empty() {
+        return None;
+    }
+    Some(
+        delivered
+            .iter()
+            .filter(|id| previous.undelivered.contains(*id))
+            .count(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovery_needs_failures_before_and_none_now() {
+        let ids = |ids: &[&str]| -> Vec<String> { ids.iter().map(|id| id.to_string()).collect() };
+        let failed = RunState {
+            undelivered: ids(&["1", "2"]).into_iter().collect(),
+        };
+
+        assert_eq!(recovered(&failed, &ids(&["1", "2", "3"]), &[]), Some(2));
+        assert_eq!(recovered(&failed, &ids(&["3"]), &ids(&["1"])), None);
+        assert_eq!(recovered(&RunState::default(), &ids(&["1"]), &[]), None);
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use chrono::{DateTime, Duration, NaiveDateTime, Timelike, Utc};
+
+use crate::timezone::TimeZone;
+
+/// A cron expression that can't be read.
+#[derive(Debug)]
+pub struct ScheduleError {
+    /// The expression as configured.
+    pub expression: String,
+    /// What's wrong with it.
+    pub problem: String,
+}
+
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Ungültiger Zeitplan '{}': {}",
+            self.expression, self.problem
+        )
+    }
+}
+
+impl Error for ScheduleError {}
+
+/// When watch mode scans, as a cron expression such as "0 * 9-17 * * Mon-Fri", see
+/// `ACTIVE_SCHEDULE`.
+///
+/// The expression is read by the `cron` crate: second, minute, hour, day of month, month, day of
+/// week (1 for Sunday to 7 for Saturday, or names like `Mon`) and an optional year. The schedule
+/// is active during every minute with a matching time, compared with the wall clock.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    /// The parsed expression.
+    cron: cron::Schedule,
+}
+
+impl Schedule {
+    /// Reads a cron expression.
+    ///
+    /// # Returns
+    /// The schedule, or a `ScheduleError` with the problem the `cron` crate reports.
+    pub fn parse(expression: &str) -> Result<Self, ScheduleError> {
+        cron::Schedule::from_str(expression)
+            .map(|cron| Self { cron })
+            .map_err(|e| ScheduleError {
+                expression: expression.to_string(),
+                problem: e.to_string(),
+            })
+    }
+
+    /// Checks whether the schedule is active during the minute of a wall-clock time.
+    pub fn matches(&self, time: NaiveDateTime) -> bool {
+        let Some(minute_start) = time.with_second(0).and_then(|time| time.with_nanosecond(0))
+        else {
+            return false;
+        };
+        self.next_match(minute_start - Duration::seconds(1))
+            .is_some_and(|next| next < minute_start + Duration::minutes(1))
+    }
+
+    /// Finds the next wall-clock time the schedule is active, which is `time` itself if it
+    /// already is.
+    ///
+    /// # Returns
+    /// That time, or `None` if the schedule never matches again.
+    pub fn next_active(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        if self.matches(time) {
+            return Some(time);
+        }
+        self.next_match(time)
+    }
+
+    /// Returns how long to wait from `now` until the schedule is active in `timezone`.
+    ///
+    /// The wait is measured between points in time, so it stays right across a change to or
+    /// from summer time.
+    ///
+    /// # Returns
+    /// The wait, zero if the schedule is active already, or `None` if it never is again.
+    pub fn wait_until_active(&self, timezone: TimeZone, now: DateTime<Utc>) -> Option<Duration> {
+        let wall_clock = timezone.wall_clock(now);
+        let mut candidate = self.next_active(wall_clock)?;
+        // A wall-clock time skipped when summer time starts doesn't exist in every time zone.
+        loop {
+            if let Some(next) = timezone.to_utc(candidate) {
+                return Some((next - now).max(Duration::zero()));
+            }
+            candidate = self.next_match(candidate)?;
+        }
+    }
+
+    /// Finds the first wall-clock time after `time` that the expression matches.
+    fn next_match(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        self.cron
+            .after(&time.and_utc())
+            .next()
+            .map(|next| next.naive_utc())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // 2025-06-02 is a Monday.
+        NaiveDate::from_ymd_opt(2025, 6, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn weekday_business_hours() {
+        let schedule = Schedule::parse("0 * 9-17 * * Mon-Fri").unwrap();
+        assert!(schedule.matches(at(2, 9, 0)));
+        assert!(schedule.matches(at(6, 17, 59) + Duration::seconds(30)));
+        assert!(!schedule.matches(at(2, 18, 0)));
+        assert!(!schedule.matches(at(7, 12, 0)));
+
+        // From Friday evening, the next scan is on Monday morning.
+        assert_eq!(schedule.next_active(at(6, 18, 30)), Some(at(9, 9, 0)));
+        assert_eq!(schedule.next_active(at(2, 10, 0)), Some(at(2, 10, 0)));
+    }
+
+    #[test]
+    fn steps_lists_and_sunday() {
+        let schedule = Schedule::parse("0 */15 8,20 * * Sun").unwrap();
+        assert!(schedule.matches(at(1, 20, 45)));
+        assert!(!schedule.matches(at(1, 20, 50)));
+        assert!(!schedule.matches(at(2, 20, 45)));
+    }
+
+    #[test]
+    fn the_wait_spans_the_end_of_summer_time() {
+        let schedule = Schedule::parse("0 0 9 * * *").unwrap();
+        // 22:00 summer time on the evening before the clocks go back.
+        let now = NaiveDate::from_ymd_opt(2026, 10, 24)
+            .unwrap()
+            .and_hms_opt(20, 0, 0)
+            .unwrap()
+            .and_utc();
+        assert_eq!(
+            schedule.wait_until_active(TimeZone::EuropeBerlin, now),
+            Some(Duration::hours(12))
+        );
+        assert_eq!(
+            schedule.wait_until_active(TimeZone::Utc, now),
+            Some(Duration::hours(13))
+        );
+    }
+
+    #[test]
+    fn invalid_expressions_are_rejected() {
+        assert!(Schedule::parse("0 * 9-17 *").is_err());
+        assert!(Schedule::parse("0 * 24 * * *").is_err());
+        assert!(Schedule::parse("0 * 17-9 * * *").is_err());
+    }
+}
@@ -0,0 +1,1471 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use reqwest::{Client, StatusCode, Url};
+use scraper::{CaseSensitivity, ElementRef, Html, Selector};
+use tokio::time::sleep;
+
+use crate::{
+    Ad, FALLBACK_AD_LINKS, IMAGE_RESOLUTION, IMAGE_RESOLUTION_RULES, MALFORMED_PAGE_RETRIES,
+    MALFORMED_PAGE_RETRY_DELAY_SECS, SANITIZE_TITLES, TIMEZONE,
+    text::{sanitize_title, truncate_text},
+    timezone::TimeZone,
+};
+
+/// The origin relative links on Kleinanzeigen pages are resolved against, and search URLs built on.
+pub const KLEINANZEIGEN_ORIGIN: &str = "https://www.kleinanzeigen.de";
+
+/// Text fragments that only appear on Kleinanzeigen's CAPTCHA and blocking pages (lowercase).
+const BLOCKED_PAGE_MARKERS: &[&str] = &[
+    "captcha",
+    "access denied",
+    "zugriff verweigert",
+    "ungewöhnliche aktivität",
+    "sicherheitsüberprüfung",
+];
+
+/// Real result pages are far larger than this, even without any ads on them.
+const MIN_RESULT_PAGE_BYTES: usize = 5_000;
+
+/// Kleinanzeigen answered with a CAPTCHA or blocking page instead of search results.
+///
+/// Returned instead of an empty result, so callers can back off and alert
+/// rather than mistaking the block for "no new ads".
+#[derive(Debug)]
+pub struct BlockedError {
+    /// What gave the blocking page away.
+    pub reason: String,
+}
+
+impl Display for BlockedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Kleinanzeigen blockiert die Anfrage ({})", self.reason)
+    }
+}
+
+impl Error for BlockedError {}
+
+/// The response was cut off or isn't valid UTF-8, so it can't be trusted to list all ads.
+///
+/// Returned instead of an empty result, so the page is fetched again rather than
+/// mistaken for "no ads".
+#[derive(Debug)]
+pub struct MalformedPageError {
+    /// What was wrong with the response.
+    pub reason: String,
+}
+
+impl Display for MalformedPageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Unvollständige oder fehlerhafte Antwort von Kleinanzeigen ({})",
+            self.reason
+        )
+    }
+}
+
+impl Error for MalformedPageError {}
+
+/// Decodes a response body, checking that it arrived completely and is valid UTF-8.
+///
+/// # Arguments
+/// * `content_length` - The length announced in the `Content-Length` header, if any.
+/// * `bytes` - The received body.
+///
+/// # Returns
+/// The body as text, or an error if it is shorter than announced or not valid UTF-8.
+fn decode_body(content_length: Option<u64>, bytes: &[u8]) -> Result<String, MalformedPageError> {
+    if let Some(expected) = content_length
+        && (bytes.len() as u64) < expected
+    {
+        return Err(MalformedPageError {
+            reason: format!("nur {} von {} Bytes empfangen", bytes.len(), expected),
+        });
+    }
+    String::from_utf8(bytes.to_vec()).map_err(|e| MalformedPageError {
+        reason: format!(
+            "kein gültiges UTF-8 ab Byte {}",
+            e.utf8_error().valid_up_to()
+        ),
+    })
+}
+
+/// Checks that a result page ends like a complete HTML document.
+///
+/// # Returns
+/// The reason the page looks cut off, or `None` if it is complete.
+fn detect_truncated_page(body: &str) -> Option<String> {
+    if body.to_lowercase().contains("</html>") {
+        None
+    } else {
+        Some(format!(
+            "Dokument mit {} Bytes endet ohne </html>",
+            body.len()
+        ))
+    }
+}
+
+/// Checks a response for signs of a CAPTCHA or blocking page.
+///
+/// # Returns
+/// The reason the page looks blocked, or `None` if it looks like a real result page.
+fn detect_blocked_page(status: StatusCode, body: &str) -> Option<String> {
+    if status == StatusCode::FORBIDDEN || status == StatusCode::TOO_MANY_REQUESTS {
+        return Some(format!("HTTP {}", status));
+    }
+    // Scripts and markup of real pages may mention a CAPTCHA too, so only the text is checked.
+    let text = visible_text(&Html::parse_document(body)).to_lowercase();
+    if let Some(marker) = BLOCKED_PAGE_MARKERS
+        .iter()
+        .find(|marker| text.contains(*marker))
+    {
+        return Some(format!("Seite enthält \"{}\"", marker));
+    }
+    if body.len() < MIN_RESULT_PAGE_BYTES {
+        return Some(format!("Antwort mit nur {} Bytes", body.len()));
+    }
+    None
+}
+
+/// Collects the text a visitor sees on a page, including its title, but not scripts or styles.
+fn visible_text(document: &Html) -> String {
+    document
+        .root_element()
+        .descendants()
+        .filter(|node| {
+            !node.ancestors().any(|ancestor| {
+                ancestor.value().as_element().is_some_and(|element| {
+                    matches!(element.name(), "script" | "style" | "noscript" | "template")
+                })
+            })
+        })
+        .filter_map(|node| node.value().as_text().map(|text| &**text))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A page at least this large clearly has content, so finding no ads on it is suspicious.
+const SUBSTANTIAL_PAGE_BYTES: usize = 50_000;
+
+/// Text shown on a real search page that has no results (lowercase).
+const NO_RESULTS_MARKERS: &[&str] = &["keine ergebnisse", "leider keine"];
+
+/// The ads found on a page, plus a warning if the page structure looked unexpected.
+pub struct ScrapedPage {
+    /// All ads found on the page.
+    pub ads: Vec<Ad>,
+    /// Why our selectors seem to no longer match the page, if they do.
+    pub layout_warning: Option<String>,
+}
+
+/// Checks whether the selectors matched as expected on a page that has content.
+///
+/// # Arguments
+/// * `body` - The HTML of the page.
+/// * `articles` - How many `article.aditem` containers were found.
+/// * `parsed` - How many of them yielded an ad.
+///
+/// # Returns
+/// A description of the suspected layout change, or `None` if the page looks normal.
+fn detect_layout_change(body: &str, articles: usize, parsed: usize) -> Option<String> {
+    if articles > 0 && parsed == 0 {
+        return Some(format!(
+            "{} Anzeigen-Container (article.aditem) gefunden, aber keiner mit ID und Titel-Link (a.ellipsis)",
+            articles
+        ));
+    }
+    if articles == 0 && body.len() >= SUBSTANTIAL_PAGE_BYTES {
+        let lower = body.to_lowercase();
+        if !NO_RESULTS_MARKERS
+            .iter()
+            .any(|marker| lower.contains(marker))
+        {
+            return Some(format!(
+                "keine Anzeigen-Container (article.aditem) auf einer Seite mit {} Bytes",
+                body.len()
+            ));
+        }
+    }
+    None
+}
+
+/// Picks the highest-resolution image URL from a `srcset` attribute.
+///
+/// Candidates are separated by commas, but a comma only ends a candidate when it
+/// follows the URL's whitespace or descriptor, so URLs containing commas stay intact.
+/// The candidate with the largest width descriptor (`w`) wins; if no widths are given,
+/// the largest pixel density (`x`) is used instead.
+///
+/// # Arguments
+/// * `srcset` - The raw value of the `srcset` attribute.
+///
+/// # Returns
+/// The chosen URL, or `None` if the attribute contains no usable candidate.
+fn best_srcset_candidate(srcset: &str) -> Option<String> {
+    let mut best: Option<(&str, f64, f64)> = None;
+    let mut rest = srcset;
+
+    loop {
+        // Skip leading whitespace and stray separators between candidates.
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() {
+            break;
+        }
+
+        // The URL runs until the next whitespace.
+        let url_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let raw_url = &rest[..url_end];
+        rest = &rest[url_end..];
+
+        // Trailing commas directly after the URL end the candidate without a descriptor.
+        let url = raw_url.trim_end_matches(',');
+        let descriptor = if url.len() < raw_url.len() {
+            ""
+        } else {
+            let descriptor_end = rest.find(',').unwrap_or(rest.len());
+            let descriptor = rest[..descriptor_end].trim();
+            rest = &rest[descriptor_end..];
+            descriptor
+        };
+
+        if url.is_empty() {
+            continue;
+        }
+
+        // A missing descriptor is equivalent to a density of 1x.
+        let (width, density) = if let Some(w) = descriptor.strip_suffix('w') {
+            (w.trim().parse().unwrap_or(0.0), 0.0)
+        } else if let Some(x) = descriptor.strip_suffix('x') {
+            (0.0, x.trim().parse().unwrap_or(1.0))
+        } else {
+            (0.0, 1.0)
+        };
+
+        let is_better = match best {
+            None => true,
+            Some((_, best_width, best_density)) => {
+                width > best_width || (width == best_width && density >= best_density)
+            }
+        };
+        if is_better {
+            best = Some((url, width, density));
+        }
+    }
+
+    best.map(|(url, _, _)| url.to_string())
+}
+
+/// Reads an ad's ID from the `data-adid` attribute of its card.
+///
+/// # Returns
+/// The ID, or `None` if the attribute is missing, empty or not a number, since the ad
+/// couldn't be told apart from others or linked to then.
+fn card_ad_id(article: ElementRef<'_>) -> Option<&str> {
+    article
+        .value()
+        .attr("data-adid")
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.bytes().all(|byte| byte.is_ascii_digit()))
+}
+
+/// Turns an ad's `href` into an absolute link to the listing page.
+///
+/// Relative paths (`/s-anzeige/...`) are resolved against the Kleinanzeigen origin,
+/// protocol-relative links (`//www.kleinanzeigen.de/...`) get `https:`, and absolute
+/// URLs are kept as they are instead of having the origin prepended a second time.
+///
+/// # Arguments
+/// * `href` - The raw `href` attribute of the ad's title link.
+///
+/// # Returns
+/// The absolute link, or `None` if `href` doesn't point to a Kleinanzeigen listing.
+pub fn normalize_ad_link(href: &str) -> Option<String> {
+    let base = Url::parse(KLEINANZEIGEN_ORIGIN).ok()?;
+    let url = base.join(href.trim()).ok()?;
+
+    // We only care about actual ad links, not other miscellaneous links.
+    let is_kleinanzeigen = url
+        .host_str()
+        .is_some_and(|host| host == "kleinanzeigen.de" || host.ends_with(".kleinanzeigen.de"));
+    if !matches!(url.scheme(), "http" | "https")
+        || !is_kleinanzeigen
+        || !url.path().starts_with("/s-anzeige/")
+    {
+        return None;
+    }
+
+    Some(url.to_string())
+}
+
+/// Builds a link to a listing from its ID, for cards without a title link.
+///
+/// Kleinanzeigen links follow `/s-anzeige/<slug>/<id>`, where the slug is derived from the title
+/// but not checked, so any slug reaches the listing.
+///
+/// # Arguments
+/// * `id` - The ad ID from the card's `data-adid`.
+/// * `title` - The title, if the card shows one, to make the slug readable.
+pub fn fallback_ad_link(id: &str, title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.to_lowercase().chars() {
+        match c {
+            'ä' => slug.push_str("ae"),
+            'ö' => slug.push_str("oe"),
+            'ü' => slug.push_str("ue"),
+            'ß' => slug.push_str("ss"),
+            c if c.is_ascii_alphanumeric() => slug.push(c),
+            _ if !slug.ends_with('-') => slug.push('-'),
+            _ => {}
+        }
+    }
+    let slug = slug.trim_matches('-');
+    let slug = if slug.is_empty() { "anzeige" } else { slug };
+    format!("{}/s-anzeige/{}/{}", KLEINANZEIGEN_ORIGIN, slug, id)
+}
+
+/// Reads the ad ID from a listing link, e.g. "2876543210" from
+/// "https://www.kleinanzeigen.de/s-anzeige/sofa/2876543210-88-3331".
+///
+/// # Returns
+/// The ID, or `None` if the link's last path segment doesn't start with one.
+pub fn parse_ad_id(link: &str) -> Option<String> {
+    let url = Url::parse(link).ok()?;
+    let segment = url.path_segments()?.rfind(|segment| !segment.is_empty())?;
+    let id = segment.split('-').next()?;
+    (!id.is_empty() && id.bytes().all(|byte| byte.is_ascii_digit())).then(|| id.to_string())
+}
+
+/// Parses the posting time Kleinanzeigen shows on a listing card.
+///
+/// Recent ads use relative dates like "Heute, 14:32" or "Gestern, 09:10",
+/// older ones an absolute date like "12.10.2026" without a time.
+///
+/// # Arguments
+/// * `raw` - The posting time text as scraped from the page.
+/// * `now` - The current local time the relative dates refer to.
+///
+/// # Returns
+/// The local posting time, or `None` if the text doesn't match a known format.
+pub fn parse_posting_time(raw: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let raw = raw.trim();
+    if let Some((day, time)) = raw.split_once(',') {
+        let time = NaiveTime::parse_from_str(time.trim(), "%H:%M").ok()?;
+        let date = match day.trim() {
+            "Heute" => now.date(),
+            "Gestern" => now.date() - Duration::days(1),
+            _ => return None,
+        };
+        return Some(date.and_time(time));
+    }
+
+    NaiveDate::parse_from_str(raw, "%d.%m.%Y")
+        .ok()
+        .map(|date| date.and_time(NaiveTime::MIN))
+}
+
+/// Turns the posting time shown on the page into a point in time, see `parse_posting_time`.
+///
+/// # Arguments
+/// * `raw` - The posting time text as scraped from the page.
+/// * `now` - The current time the relative dates refer to.
+/// * `timezone` - The time zone the page shows its times in.
+///
+/// # Returns
+/// The posting time, midnight for ads that only show a date, or `None` if the text doesn't
+/// match a known format.
+pub fn parse_posted_at(raw: &str, now: DateTime<Utc>, timezone: TimeZone) -> Option<DateTime<Utc>> {
+    timezone.to_utc(parse_posting_time(raw, timezone.wall_clock(now))?)
+}
+
+/// Reads the shipping availability from the text of an ad card's price or tag block.
+///
+/// # Returns
+/// `Some(true)` for "Versand möglich", `Some(false)` for "Nur Abholung",
+/// or `None` if the text mentions neither.
+pub fn parse_shipping(text: &str) -> Option<bool> {
+    let text = text.to_lowercase();
+    if text.contains("versand möglich") {
+        Some(true)
+    } else if text.contains("nur abholung") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// The item conditions Kleinanzeigen shows as tags, in the spelling used in captions.
+const CONDITIONS: &[&str] = &[
+    "Neu",
+    "Neuwertig",
+    "Sehr gut",
+    "Gut",
+    "In Ordnung",
+    "Gebraucht",
+    "Defekt",
+];
+
+/// Reads the item condition from a single tag of an ad card, e.g. "Gebraucht".
+///
+/// # Returns
+/// The condition as listed in `CONDITIONS`, or `None` if the tag is something else.
+pub fn parse_condition(tag: &str) -> Option<String> {
+    let tag = tag.split_whitespace().collect::<Vec<_>>().join(" ");
+    CONDITIONS
+        .iter()
+        .find(|condition| condition.eq_ignore_ascii_case(&tag))
+        .map(|condition| condition.to_string())
+}
+
+/// Reads the view count from the text of an ad card's tag block, e.g. "123 Aufrufe".
+///
+/// # Returns
+/// The count, or `None` if the card doesn't show one.
+pub fn parse_views(text: &str) -> Option<u32> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    words.windows(2).find_map(|pair| {
+        let (number, word) = if pair[0].to_lowercase().starts_with("aufruf") {
+            (pair[1], pair[0])
+        } else {
+            (pair[0], pair[1])
+        };
+        word.to_lowercase()
+            .starts_with("aufruf")
+            .then(|| number.trim_end_matches(':').replace('.', "").parse().ok())
+            .flatten()
+    })
+}
+
+/// The image size scraped ads link to.
+// Only the variant chosen in the configuration is ever constructed.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageResolution {
+    /// A small preview, the cheapest to load on metered connections.
+    Thumbnail,
+    /// A medium-sized image.
+    Medium,
+    /// The largest image Kleinanzeigen serves.
+    Full,
+}
+
+impl ImageResolution {
+    /// Returns the resolution rule Kleinanzeigen's image server expects for this size.
+    pub fn rule(self) -> &'static str {
+        match self {
+            ImageResolution::Thumbnail => "$_2.AUTO",
+            ImageResolution::Medium => "$_57.AUTO",
+            ImageResolution::Full => "$_59.AUTO",
+        }
+    }
+}
+
+/// Replaces the query of an image URL with a resolution rule, e.g. "$_59.AUTO".
+fn with_resolution_rule(image_url: &str, rule: &str) -> String {
+    // Get the base URL by splitting at the '?' and taking the first part.
+    let base_url = image_url.split('?').next().unwrap_or(image_url);
+    format!("{}?rule={}", base_url, rule)
+}
+
+/// Lists the URLs to try for an ad's image, followed by the fallbacks of `IMAGE_RESOLUTION_RULES`.
+///
+/// The given URL always comes first, so the resolution chosen while scraping is tried before the others.
+pub fn image_url_variants(image_url: &str) -> Vec<String> {
+    let mut variants = vec![image_url.to_string()];
+    for rule in IMAGE_RESOLUTION_RULES {
+        let variant = with_resolution_rule(image_url, rule);
+        if !variants.contains(&variant) {
+            variants.push(variant);
+        }
+    }
+    variants
+}
+
+/// Parses a price as shown on Kleinanzeigen, e.g. "1.200 € VB" or "12,50 €".
+///
+/// # Returns
+/// The price in cents, or `None` if the text contains no amount (e.g. "Zu verschenken").
+pub fn parse_price(text: &str) -> Option<u64> {
+    let amount = text
+        .split_whitespace()
+        .find(|part| part.starts_with(|c: char| c.is_ascii_digit()))?
+        .trim_end_matches('€');
+
+    // Dots group thousands, a comma separates the cents.
+    let (euros, cents) = match amount.split_once(',') {
+        Some((euros, cents)) => (euros, cents),
+        None => (amount, "0"),
+    };
+    let euros: u64 = euros.replace('.', "").parse().ok()?;
+    let cents: u64 = match cents.len() {
+        1 => cents.parse::<u64>().ok()? * 10,
+        2 => cents.parse().ok()?,
+        _ => return None,
+    };
+    Some(euros * 100 + cents)
+}
+
+/// Checks whether the badge text of an ad card marks a paid promotion, "Top" or "Highlight".
+///
+/// # Returns
+/// `true` for a promotion badge, `false` for other badges or none at all.
+pub fn parse_promoted(text: &str) -> bool {
+    text.split_whitespace().any(|part| {
+        let part = part.to_lowercase();
+        part == "top" || part == "highlight"
+    })
+}
+
+/// Checks whether a price text marks the price as negotiable, e.g. "1.200 € VB" or just "VB".
+///
+/// # Returns
+/// `true` if the text contains the "VB" marker. Free items ("Zu verschenken") and ads
+/// without a price text are never negotiable.
+pub fn parse_negotiable(text: &str) -> bool {
+    text.split_whitespace()
+        .any(|part| part.eq_ignore_ascii_case("VB"))
+}
+
+/// The title and price of a single listing, read from its detail page.
+pub struct ListingPrice {
+    /// The title of the listing, if found.
+    pub title: Option<String>,
+    /// The price in cents, or `None` if the listing shows no amount.
+    pub price: Option<u64>,
+}
+
+/// Fetches the detail page of a listing and reads its title and price.
+///
+/// # Arguments
+/// * `client` - The `reqwest::Client` used for scraping.
+/// * `url` - The full URL of the listing.
+///
+/// # Returns
+/// The title and price, or an error if the request fails.
+pub async fn scrape_listing_price(
+    client: &Client,
+    url: &str,
+) -> Result<ListingPrice, Box<dyn Error>> {
+    log!("Prüfe Preis: {}", url);
+    let response = client.get(url).send().await?.text().await?;
+    let document = Html::parse_document(&response);
+
+    let title_selector = Selector::parse("#viewad-title").unwrap();
+    let price_selector = Selector::parse("#viewad-price").unwrap();
+    let title = document
+        .select(&title_selector)
+        .next()
+        .map(|element| sanitize_title(&element.text().collect::<String>()))
+        .filter(|title| !title.is_empty());
+    let price = document
+        .select(&price_selector)
+        .next()
+        .and_then(|element| parse_price(&element.text().collect::<String>()));
+    Ok(ListingPrice { title, price })
+}
+
+/// Fetches the detail page of a listing and reads the start of its description.
+///
+/// # Arguments
+/// * `client` - The `reqwest::Client` used for scraping.
+/// * `url` - The full URL of the listing.
+/// * `max_chars` - The maximum length of the returned snippet.
+///
+/// # Returns
+/// The cleaned-up snippet, `None` if the listing has no description, or an error if the request fails.
+pub async fn scrape_description(
+    client: &Client,
+    url: &str,
+    max_chars: usize,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let response = client.get(url).send().await?.text().await?;
+    let document = Html::parse_document(&response);
+
+    let description_selector = Selector::parse("#viewad-description-text").unwrap();
+    let description = document
+        .select(&description_selector)
+        .next()
+        .map(|element| sanitize_title(&element.text().collect::<Vec<_>>().join(" ")))
+        .filter(|description| !description.is_empty())
+        .map(|description| truncate_text(&description, max_chars));
+    Ok(description)
+}
+
+/// Fetches the detail page of a single listing and reads it into an `Ad`, like a card of a
+/// result page would have been.
+///
+/// The detail page shows no promotion badge or view count, so those are left unset.
+///
+/// # Arguments
+/// * `client` - The `reqwest::Client` used for scraping.
+/// * `url` - The link to the listing, e.g. as copied from the browser.
+///
+/// # Returns
+/// The ad, or an error if the URL isn't a Kleinanzeigen listing, the request fails or is blocked,
+/// or the listing doesn't exist (anymore).
+pub async fn scrape_ad(client: &Client, url: &str) -> Result<Ad, Box<dyn Error>> {
+    let (Some(link), Some(id)) = (normalize_ad_link(url), parse_ad_id(url)) else {
+        return Err(format!("Keine gültige Adresse einer Kleinanzeigen-Anzeige: {}", url).into());
+    };
+
+    log!("Lade Anzeige: {}", link);
+    let response = client.get(&link).send().await?;
+    let status = response.status();
+    let content_length = response.content_length();
+    let body = decode_body(content_length, &response.bytes().await?)?;
+    if let Some(reason) = detect_blocked_page(status, &body) {
+        return Err(Box::new(BlockedError { reason }));
+    }
+    if !status.is_success() {
+        return Err(format!("Anzeige nicht gefunden (HTTP {}): {}", status, link).into());
+    }
+    let document = Html::parse_document(&body);
+
+    let title_selector = Selector::parse("#viewad-title").unwrap();
+    let image_selector = Selector::parse("#viewad-image, .galleryimage-element img").unwrap();
+    let posted_selector = Selector::parse("#viewad-extra-info span").unwrap();
+    let location_selector = Selector::parse("#viewad-locality").unwrap();
+    let shipping_selector = Selector::parse(".boxedarticle--details--shipping").unwrap();
+    let price_selector = Selector::parse("#viewad-price").unwrap();
+    let condition_selector = Selector::parse(".addetailslist--detail--value").unwrap();
+
+    // Deleted listings redirect to a page without the title.
+    let raw_title = document
+        .select(&title_selector)
+        .next()
+        .map(|element| element.text().collect::<String>())
+        .filter(|title| !title.trim().is_empty())
+        .ok_or_else(|| format!("Anzeige nicht gefunden oder nicht mehr online: {}", link))?;
+    let title = if SANITIZE_TITLES {
+        sanitize_title(&raw_title)
+    } else {
+        raw_title.trim().to_string()
+    };
+
+    // The first gallery image is the one a result card shows.
+    let image_src = document.select(&image_selector).next().and_then(|img| {
+        img.value()
+            .attr("srcset")
+            .and_then(best_srcset_candidate)
+            .or_else(|| img.value().attr("src").map(String::from))
+    });
+    let image_url = image_src
+        .as_deref()
+        .map(|src| with_resolution_rule(src, IMAGE_RESOLUTION.rule()));
+    let full_image_url = image_src
+        .as_deref()
+        .map(|src| with_resolution_rule(src, ImageResolution::Full.rule()));
+
+    // The detail page shows the posting date, e.g. "12.10.2026", among the extra info.
+    let posted = document
+        .select(&posted_selector)
+        .map(|element| element.text().collect::<String>().trim().to_string())
+        .find(|text| NaiveDate::parse_from_str(text, "%d.%m.%Y").is_ok());
+    let posted_at = posted
+        .as_deref()
+        .and_then(|posted| parse_posted_at(posted, Utc::now(), TIMEZONE));
+
+    let location = document
+        .select(&location_selector)
+        .next()
+        .map(|element| {
+            element
+                .text()
+                .collect::<String>()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|text| !text.is_empty());
+
+    // Shipping reads e.g. "+ Versand ab 6,99 €" or "Nur Abholung" here.
+    let shipping = document
+        .select(&shipping_selector)
+        .next()
+        .and_then(|element| {
+            let text = element.text().collect::<String>();
+            parse_shipping(&text)
+                .or_else(|| text.to_lowercase().contains("versand").then_some(true))
+        });
+
+    let negotiable = document
+        .select(&price_selector)
+        .next()
+        .is_some_and(|element| parse_negotiable(&element.text().collect::<String>()));
+
+    let condition = document
+        .select(&condition_selector)
+        .find_map(|element| parse_condition(&element.text().collect::<String>()));
+
+    Ok(Ad {
+        id,
+        title,
+        link,
+        image_url,
+        full_image_url,
+        posted,
+        posted_at,
+        location,
+        shipping,
+        negotiable,
+        promoted: false,
+        views: None,
+        condition,
+        search: "",
+        labels: Vec::new(),
+        prefix: None,
+        description: None,
+        top_match: false,
+    })
+}
+
+/// The seller of a listing, as linked from its detail page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Seller {
+    /// The absolute URL of the seller's profile page, which identifies them across listings.
+    pub url: String,
+    /// The seller's user ID from the profile URL, e.g. "12345678".
+    pub id: Option<String>,
+    /// The name shown in the seller box, e.g. "Anna", if any.
+    pub name: Option<String>,
+}
+
+impl Seller {
+    /// Checks whether a `BLOCKED_SELLERS` entry names this seller: exactly by ID, or by name
+    /// regardless of case.
+    pub fn matches(&self, entry: &str) -> bool {
+        self.id.as_deref() == Some(entry)
+            || self
+                .name
+                .as_deref()
+                .is_some_and(|name| name.to_lowercase() == entry.trim().to_lowercase())
+    }
+}
+
+/// Reads the user ID from a seller's profile URL, e.g. "12345678" from "...?userId=12345678".
+pub fn parse_seller_id(url: &str) -> Option<String> {
+    Url::parse(url)
+        .ok()?
+        .query_pairs()
+        .find(|(key, _)| key == "userId")
+        .map(|(_, id)| id.into_owned())
+        .filter(|id| !id.is_empty())
+}
+
+/// Fetches the detail page of a listing and reads the link to the seller's other listings.
+///
+/// # Arguments
+/// * `client` - The `reqwest::Client` used for scraping.
+/// * `url` - The full URL of the listing.
+///
+/// # Returns
+/// The seller with the absolute URL of their profile page, `None` if the page links none,
+/// or an error if the request fails.
+pub async fn scrape_seller(client: &Client, url: &str) -> Result<Option<Seller>, Box<dyn Error>> {
+    let response = client.get(url).send().await?.text().await?;
+    Ok(parse_seller(&Html::parse_document(&response)))
+}
+
+/// Reads the seller from the contact box of a listing's detail page.
+///
+/// The profile URL comes from the first link to the seller's listings. The name is taken from
+/// the seller-name element only, since other links there read e.g. "Weitere Anzeigen".
+fn parse_seller(document: &Html) -> Option<Seller> {
+    let link_selector =
+        Selector::parse("#viewad-contact a[href*='userId='], .userprofile-vip a[href*='userId=']")
+            .unwrap();
+    let name_selector = Selector::parse(".userprofile-vip").unwrap();
+    let href = document
+        .select(&link_selector)
+        .find_map(|element| element.value().attr("href"))?;
+    let url = String::from(Url::parse(KLEINANZEIGEN_ORIGIN).ok()?.join(href).ok()?);
+    let name = document
+        .select(&name_selector)
+        .map(|element| {
+            element
+                .text()
+                .collect::<String>()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .find(|name| !name.is_empty());
+    Some(Seller {
+        id: parse_seller_id(&url),
+        name,
+        url,
+    })
+}
+
+/// Reads the number of active listings from a profile header, e.g. "12 Anzeigen online".
+///
+/// # Returns
+/// The count, or `None` if the text shows none.
+pub fn parse_listing_count(text: &str) -> Option<usize> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    words.windows(2).find_map(|pair| {
+        pair[1]
+            .to_lowercase()
+            .starts_with("anzeige")
+            .then(|| pair[0].replace('.', "").parse().ok())
+            .flatten()
+    })
+}
+
+/// Fetches a seller's profile page and counts their active listings.
+///
+/// # Arguments
+/// * `client` - The `reqwest::Client` used for scraping.
+/// * `url` - The URL of the profile page, see `scrape_seller`.
+///
+/// # Returns
+/// The count from the profile header, falling back to the listings on the page,
+/// or an error if the request fails.
+pub async fn scrape_seller_listing_count(
+    client: &Client,
+    url: &str,
+) -> Result<Option<usize>, Box<dyn Error>> {
+    log!("Prüfe Verkäufer: {}", url);
+    let response = client.get(url).send().await?.text().await?;
+    let document = Html::parse_document(&response);
+
+    let header_selector = Selector::parse(".userprofile--header, .userprofile-details").unwrap();
+    let ad_selector = Selector::parse("article.aditem").unwrap();
+    let count = document
+        .select(&header_selector)
+        .find_map(|element| parse_listing_count(&element.text().collect::<Vec<_>>().join(" ")));
+    let listed = document.select(&ad_selector).count();
+    Ok(count.or((listed > 0).then_some(listed)))
+}
+
+/// Where a scan gets its result pages and ad details from.
+///
+/// Implemented by `reqwest::Client` for the real site, so tests can script the pages instead.
+pub trait PageSource {
+    /// Fetches a result page, see `scrape_kleinanzeigen_page`.
+    async fn fetch_page(&self, url: &str) -> Result<ScrapedPage, Box<dyn Error>>;
+
+    /// Fetches the description snippet of a listing, see `scrape_description`.
+    async fn fetch_description(
+        &self,
+        url: &str,
+        max_chars: usize,
+    ) -> Result<Option<String>, Box<dyn Error>>;
+
+    /// Fetches the seller of a listing, see `scrape_seller`.
+    async fn fetch_seller(&self, url: &str) -> Result<Option<Seller>, Box<dyn Error>>;
+
+    /// Fetches the number of active listings of a seller, see `scrape_seller_listing_count`.
+    async fn fetch_seller_listing_count(&self, url: &str) -> Result<Option<usize>, Box<dyn Error>>;
+}
+
+impl PageSource for Client {
+    /// Malformed responses are fetched again up to `MALFORMED_PAGE_RETRIES` times.
+    async fn fetch_page(&self, url: &str) -> Result<ScrapedPage, Box<dyn Error>> {
+        let mut attempt = 0;
+        loop {
+            match scrape_kleinanzeigen_page(self, url).await {
+                Err(e) if attempt < MALFORMED_PAGE_RETRIES && e.is::<MalformedPageError>() => {
+                    attempt += 1;
+                    log!(
+                        "Warnung: {}. Neuer Versuch {} von {} in {} Sekunden.",
+                        e,
+                        attempt,
+                        MALFORMED_PAGE_RETRIES,
+                        MALFORMED_PAGE_RETRY_DELAY_SECS
+                    );
+                    sleep(std::time::Duration::from_secs(
+                        MALFORMED_PAGE_RETRY_DELAY_SECS,
+                    ))
+                    .await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn fetch_description(
+        &self,
+        url: &str,
+        max_chars: usize,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        scrape_description(self, url, max_chars).await
+    }
+
+    async fn fetch_seller(&self, url: &str) -> Result<Option<Seller>, Box<dyn Error>> {
+        scrape_seller(self, url).await
+    }
+
+    async fn fetch_seller_listing_count(&self, url: &str) -> Result<Option<usize>, Box<dyn Error>> {
+        scrape_seller_listing_count(self, url).await
+    }
+}
+
+/// Scrapes a specific Kleinanzeigen page for free listings.
+///
+/// # Arguments
+/// * `client` - The `reqwest::Client` to use for the HTTP request.
+/// * `url` - The exact URL of the Kleinanzeigen page to scrape.
+///
+/// # Returns
+/// All ads found on the page together with a warning if the layout seems to have changed,
+/// or an error if the request fails, the page is blocked, or the response is truncated or garbled.
+pub async fn scrape_kleinanzeigen_page(
+    client: &Client,
+    url: &str,
+) -> Result<ScrapedPage, Box<dyn Error>> {
+    log!("Scrape URL: {}", url);
+    let response = client.get(url).send().await?;
+    let status = response.status();
+    let content_length = response.content_length();
+    let body = decode_body(content_length, &response.bytes().await?)?;
+    if let Some(reason) = detect_blocked_page(status, &body) {
+        return Err(Box::new(BlockedError { reason }));
+    }
+    if let Some(reason) = detect_truncated_page(&body) {
+        return Err(Box::new(MalformedPageError { reason }));
+    }
+    let document = Html::parse_document(&body);
+
+    // Define CSS selectors to find the necessary elements on the page.
+    let ad_selector = Selector::parse("article.aditem").unwrap();
+    let title_link_selector = Selector::parse("a.ellipsis").unwrap();
+    let heading_selector = Selector::parse("h2, .text-module-begin").unwrap();
+    let image_selector = Selector::parse(".aditem-image img").unwrap();
+    let posted_selector = Selector::parse(".aditem-main--top--right").unwrap();
+    let location_selector = Selector::parse(".aditem-main--top--left").unwrap();
+    let shipping_selector =
+        Selector::parse(".aditem-main--middle--price-shipping, .aditem-main--bottom").unwrap();
+    let price_selector = Selector::parse(".aditem-main--middle--price-shipping--price").unwrap();
+    let tag_selector = Selector::parse(".aditem-main--bottom .simpletag").unwrap();
+    let badge_selector =
+        Selector::parse(".aditem-image--badges, .badge-topad, .badge-highlight").unwrap();
+    let mut listings = Vec::new();
+    let mut articles = 0;
+    let mut without_id = 0;
+    let now = Utc::now();
+
+    // Iterate over each ad container found on the page.
+    for article in document.select(&ad_selector) {
+        articles += 1;
+
+        // Extract the unique ad ID from the 'data-adid' attribute.
+        // Without a valid one, the ad can't be told apart from others, so it is dropped.
+        let Some(ad_id) = card_ad_id(article) else {
+            without_id += 1;
+            continue;
+        };
+
+        // Find the primary link within the ad, which contains the title.
+        let link_element = article.select(&title_link_selector).next();
+        let linked = link_element.and_then(|element| {
+            let href = element.value().attr("href")?;
+            Some((element, normalize_ad_link(href)?))
+        });
+        let (raw_title, full_link) = match linked {
+            Some((element, full_link)) => (element.text().collect::<String>(), full_link),
+            // Rather than losing the ad, link to it by its ID.
+            None if FALLBACK_AD_LINKS => {
+                let raw_title = link_element
+                    .or_else(|| article.select(&heading_selector).next())
+                    .map(|element| element.text().collect::<String>())
+                    .filter(|title| !title.trim().is_empty())
+                    .or_else(|| {
+                        let alt = article
+                            .select(&image_selector)
+                            .next()?
+                            .value()
+                            .attr("alt")?;
+                        Some(alt.to_string())
+                    })
+                    .unwrap_or_else(|| format!("Anzeige {}", ad_id));
+                let full_link = fallback_ad_link(ad_id, &sanitize_title(&raw_title));
+                log!(
+                    ad = ad_id;
+                    "Anzeige {} ohne Titellink, verwende {}",
+                    ad_id,
+                    full_link
+                );
+                (raw_title, full_link)
+            }
+            None => continue,
+        };
+        let title = if SANITIZE_TITLES {
+            sanitize_title(&raw_title)
+        } else {
+            raw_title.trim().to_string()
+        };
+
+        // --- IMPROVED IMAGE QUALITY FIX ---
+        // Prioritize `srcset` for the best quality image, then fall back to `src`.
+        let image_src = article.select(&image_selector).next().and_then(|img| {
+            // `srcset` provides multiple image sizes. We take the widest one.
+            img.value()
+                .attr("srcset")
+                .and_then(best_srcset_candidate)
+                // Fallback to the `src` attribute if `srcset` is not available.
+                .or_else(|| img.value().attr("src").map(String::from))
+        });
+        // Use the configured resolution; the fallback rules are tried at send time.
+        let image_url = image_src
+            .as_deref()
+            .map(|src| with_resolution_rule(src, IMAGE_RESOLUTION.rule()));
+        // The full resolution is only linked, so it costs nothing until opened.
+        let full_image_url = image_src
+            .as_deref()
+            .map(|src| with_resolution_rule(src, ImageResolution::Full.rule()));
+
+        // The posting time, e.g. "Heute, 14:32", sits in the card's top right corner.
+        let posted = article
+            .select(&posted_selector)
+            .next()
+            .map(|element| element.text().collect::<String>().trim().to_string())
+            .filter(|text| !text.is_empty());
+        let posted_at = posted
+            .as_deref()
+            .and_then(|posted| parse_posted_at(posted, now, TIMEZONE));
+
+        // The location, e.g. "04105 Leipzig", sits in the card's top left corner.
+        // Whitespace is collapsed because the distance is often on its own line.
+        let location = article
+            .select(&location_selector)
+            .next()
+            .map(|element| {
+                element
+                    .text()
+                    .collect::<String>()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|text| !text.is_empty());
+
+        // "Versand möglich" or "Nur Abholung" sits next to the price or among the tags below.
+        let shipping = article
+            .select(&shipping_selector)
+            .find_map(|element| parse_shipping(&element.text().collect::<String>()));
+
+        // The price, e.g. "1.200 € VB", carries the negotiable marker.
+        // Ads without a price element are treated as fixed price.
+        let negotiable = article
+            .select(&price_selector)
+            .next()
+            .is_some_and(|element| parse_negotiable(&element.text().collect::<String>()));
+
+        // Some cards show how often the ad was viewed among the tags below.
+        let views = article
+            .select(&shipping_selector)
+            .find_map(|element| parse_views(&element.text().collect::<Vec<_>>().join(" ")));
+
+        // Where the seller chose one, the condition is one of the tags below.
+        let condition = article
+            .select(&tag_selector)
+            .find_map(|element| parse_condition(&element.text().collect::<String>()));
+
+        // Paid promotions carry a "Top" or "Highlight" badge on the image,
+        // or are rendered in an `is-topad` list item.
+        let promoted = article
+            .select(&badge_selector)
+            .any(|element| parse_promoted(&element.text().collect::<String>()))
+            || article
+                .parent()
+                .and_then(|parent| parent.value().as_element())
+                .is_some_and(|parent| {
+                    parent.has_class("is-topad", CaseSensitivity::AsciiCaseInsensitive)
+                });
+
+        listings.push(Ad {
+            id: ad_id.to_string(),
+            title,
+            link: full_link,
+            image_url,
+            full_image_url,
+            posted,
+            posted_at,
+            location,
+            shipping,
+            negotiable,
+            promoted,
+            views,
+            condition,
+            search: "",
+            labels: Vec::new(),
+            prefix: None,
+            description: None,
+            top_match: false,
+        });
+    }
+
+    if without_id > 0 {
+        log!(
+            "Warnung: {} Anzeige(n) ohne gültige Anzeigen-ID auf {} übersprungen.",
+            without_id,
+            url
+        );
+    }
+
+    // A page that loaded but where our selectors miss points to a layout change.
+    let layout_warning = detect_layout_change(&body, articles, listings.len());
+    if let Some(warning) = &layout_warning {
+        log!("Warnung: Layoutänderung vermutet auf {}: {}", url, warning);
+    }
+
+    // Return the scraped ads
+    Ok(ScrapedPage {
+        ads: listings,
+        layout_warning,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cards_without_a_numeric_ad_id_are_rejected() {
+        let document = Html::parse_document(
+            r#"<article class="aditem" data-adid="3012345678"></article>
+            <article class="aditem" data-adid=""></article>
+            <article class="aditem" data-adid="abc123"></article>
+            <article class="aditem"></article>"#,
+        );
+        let selector = Selector::parse("article.aditem").unwrap();
+        let ids: Vec<_> = document.select(&selector).map(card_ad_id).collect();
+        assert_eq!(ids, [Some("3012345678"), None, None, None]);
+    }
+
+    #[test]
+    fn srcset_picks_largest_width() {
+        let srcset = "https://img.example.de/a.jpg?rule=$_2.JPG 300w, https://img.example.de/b.jpg?rule=$_57.JPG 1200w, https://img.example.de/c.jpg 600w";
+        assert_eq!(
+            best_srcset_candidate(srcset).as_deref(),
+            Some("https://img.example.de/b.jpg?rule=$_57.JPG")
+        );
+    }
+
+    #[test]
+    fn srcset_keeps_commas_inside_urls() {
+        let srcset = "https://img.example.de/api/images/ab,cd.jpg?rule=$_2,JPG 320w,https://img.example.de/api/images/ef,gh.jpg?rule=$_57,JPG 640w";
+        assert_eq!(
+            best_srcset_candidate(srcset).as_deref(),
+            Some("https://img.example.de/api/images/ef,gh.jpg?rule=$_57,JPG")
+        );
+    }
+
+    #[test]
+    fn srcset_ignores_trailing_commas_and_whitespace() {
+        let srcset =
+            "  https://img.example.de/small.jpg 1x,  https://img.example.de/large.jpg 2x , ,";
+        assert_eq!(
+            best_srcset_candidate(srcset).as_deref(),
+            Some("https://img.example.de/large.jpg")
+        );
+    }
+
+    #[test]
+    fn srcset_without_descriptors_uses_last_candidate() {
+        let srcset = "https://img.example.de/first.jpg, https://img.example.de/second.jpg,";
+        assert_eq!(
+            best_srcset_candidate(srcset).as_deref(),
+            Some("https://img.example.de/second.jpg")
+        );
+    }
+
+    #[test]
+    fn srcset_empty_returns_none() {
+        assert_eq!(best_srcset_candidate(" , "), None);
+    }
+
+    #[test]
+    fn ad_link_prepends_origin_to_relative_paths() {
+        assert_eq!(
+            normalize_ad_link("/s-anzeige/sofa/3170997111-88-4257").as_deref(),
+            Some("https://www.kleinanzeigen.de/s-anzeige/sofa/3170997111-88-4257")
+        );
+    }
+
+    #[test]
+    fn ad_link_keeps_absolute_urls() {
+        assert_eq!(
+            normalize_ad_link("https://www.kleinanzeigen.de/s-anzeige/sofa/3170997111-88-4257")
+                .as_deref(),
+            Some("https://www.kleinanzeigen.de/s-anzeige/sofa/3170997111-88-4257")
+        );
+    }
+
+    #[test]
+    fn ad_link_completes_protocol_relative_urls() {
+        assert_eq!(
+            normalize_ad_link("//www.kleinanzeigen.de/s-anzeige/sofa/3170997111-88-4257")
+                .as_deref(),
+            Some("https://www.kleinanzeigen.de/s-anzeige/sofa/3170997111-88-4257")
+        );
+    }
+
+    #[test]
+    fn ad_link_rejects_non_ad_and_foreign_links() {
+        assert_eq!(normalize_ad_link("/s-zu-verschenken-tauschen/c272"), None);
+        assert_eq!(
+            normalize_ad_link("https://example.com/s-anzeige/sofa/3170997111"),
+            None
+        );
+        assert_eq!(normalize_ad_link("javascript:void(0)"), None);
+    }
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn posting_time_parses_relative_and_absolute_dates() {
+        let now = at("2026-10-14", "18:00");
+        assert_eq!(
+            parse_posting_time("Heute, 14:32", now),
+            Some(at("2026-10-14", "14:32"))
+        );
+        assert_eq!(
+            parse_posting_time(" Gestern, 09:10 ", now),
+            Some(at("2026-10-13", "09:10"))
+        );
+        assert_eq!(
+            parse_posting_time("01.10.2026", now),
+            Some(at("2026-10-01", "00:00"))
+        );
+        assert_eq!(parse_posting_time("Vorgestern", now), None);
+    }
+
+    #[test]
+    fn posted_at_is_an_absolute_time_in_utc() {
+        let now = at("2026-10-14", "16:00").and_utc();
+        let posted_at = |raw: &str| {
+            parse_posted_at(raw, now, TimeZone::EuropeBerlin)
+                .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        };
+
+        // Berlin is two hours ahead of UTC in summer time, one hour in winter.
+        assert_eq!(
+            posted_at("Heute, 14:32").as_deref(),
+            Some("2026-10-14 12:32")
+        );
+        assert_eq!(
+            posted_at("Gestern, 00:10").as_deref(),
+            Some("2026-10-12 22:10")
+        );
+        assert_eq!(posted_at("01.12.2025").as_deref(), Some("2025-11-30 23:00"));
+        assert_eq!(posted_at("Vorgestern"), None);
+
+        // Shortly after midnight in Berlin, "Heute" is already the next day there.
+        let after_midnight = at("2026-10-14", "22:30").and_utc();
+        assert_eq!(
+            parse_posted_at("Heute, 00:15", after_midnight, TimeZone::EuropeBerlin),
+            Some(at("2026-10-14", "22:15").and_utc())
+        );
+    }
+
+    #[test]
+    fn shipping_indicator_is_parsed() {
+        assert_eq!(parse_shipping("  0 €\n  Versand möglich "), Some(true));
+        assert_eq!(parse_shipping("Zu verschenken Nur Abholung"), Some(false));
+        assert_eq!(parse_shipping("Zu verschenken"), None);
+    }
+
+    #[test]
+    fn prices_are_parsed_in_cents() {
+        assert_eq!(parse_price("1.200 € VB"), Some(120_000));
+        assert_eq!(parse_price("12,50 €"), Some(1250));
+        assert_eq!(parse_price("5€"), Some(500));
+        assert_eq!(parse_price("Zu verschenken"), None);
+    }
+
+    #[test]
+    fn promotion_badges_are_detected() {
+        assert!(parse_promoted("TOP"));
+        assert!(parse_promoted(" Highlight "));
+        assert!(!parse_promoted("3 Bilder"));
+        assert!(!parse_promoted(""));
+    }
+
+    #[test]
+    fn negotiable_marker_is_detected() {
+        assert!(parse_negotiable("1.200 € VB"));
+        assert!(parse_negotiable("VB"));
+        assert!(!parse_negotiable("12,50 €"));
+        assert!(!parse_negotiable("Zu verschenken"));
+        assert!(!parse_negotiable(""));
+    }
+
+    #[test]
+    fn seller_listing_count_is_parsed() {
+        assert_eq!(
+            parse_listing_count("Privater Nutzer 12 Anzeigen online"),
+            Some(12)
+        );
+        assert_eq!(parse_listing_count("1.204 Anzeigen"), Some(1204));
+        assert_eq!(parse_listing_count("1 Anzeige online"), Some(1));
+        assert_eq!(parse_listing_count("Aktiv seit 01.01.2020"), None);
+    }
+
+    #[test]
+    fn fallback_link_is_built_from_the_id() {
+        assert_eq!(
+            fallback_ad_link("2876543210", "Sofa, grau – 3-Sitzer für Größe"),
+            "https://www.kleinanzeigen.de/s-anzeige/sofa-grau-3-sitzer-fuer-groesse/2876543210"
+        );
+        assert_eq!(
+            fallback_ad_link("2876543210", " !! "),
+            "https://www.kleinanzeigen.de/s-anzeige/anzeige/2876543210"
+        );
+        assert_eq!(
+            parse_ad_id(&fallback_ad_link("2876543210", "Sofa")).as_deref(),
+            Some("2876543210")
+        );
+    }
+
+    #[test]
+    fn ad_id_is_read_from_the_listing_link() {
+        assert_eq!(
+            parse_ad_id("https://www.kleinanzeigen.de/s-anzeige/sofa-grau/2876543210-88-3331")
+                .as_deref(),
+            Some("2876543210")
+        );
+        assert_eq!(
+            parse_ad_id("https://www.kleinanzeigen.de/s-anzeige/sofa-grau/2876543210-88-3331/")
+                .as_deref(),
+            Some("2876543210")
+        );
+        assert_eq!(
+            parse_ad_id("https://www.kleinanzeigen.de/s-anzeige/sofa-grau"),
+            None
+        );
+        assert_eq!(parse_ad_id("kein link"), None);
+    }
+
+    #[test]
+    fn seller_name_comes_from_the_name_element() {
+        let document = Html::parse_document(
+            r#"<div id="viewad-contact">
+                <a href="/s-bestandsliste.html?userId=12345678">Weitere Anzeigen</a>
+                <span class="userprofile-vip">
+                    <a href="/s-bestandsliste.html?userId=12345678"> Trödel  Paul </a>
+                </span>
+            </div>"#,
+        );
+        assert_eq!(
+            parse_seller(&document),
+            Some(Seller {
+                url: "https://www.kleinanzeigen.de/s-bestandsliste.html?userId=12345678"
+                    .to_string(),
+                id: Some("12345678".to_string()),
+                name: Some("Trödel Paul".to_string()),
+            })
+        );
+
+        let document = Html::parse_document(
+            r#"<div id="viewad-contact">
+                <a href="/s-bestandsliste.html?userId=12345678">Weitere Anzeigen</a>
+            </div>"#,
+        );
+        assert_eq!(parse_seller(&document).and_then(|seller| seller.name), None);
+    }
+
+    #[test]
+    fn blocked_sellers_match_by_id_or_name() {
+        let url = "https://www.kleinanzeigen.de/s-bestandsliste.html?userId=12345678";
+        assert_eq!(parse_seller_id(url).as_deref(), Some("12345678"));
+        assert_eq!(
+            parse_seller_id("https://www.kleinanzeigen.de/s-bestandsliste.html"),
+            None
+        );
+
+        let seller = Seller {
+            url: url.to_string(),
+            id: parse_seller_id(url),
+            name: Some("Trödel Paul".to_string()),
+        };
+        assert!(seller.matches("12345678"));
+        assert!(!seller.matches("1234567"));
+        assert!(seller.matches("trödel paul"));
+        assert!(!seller.matches("Paul"));
+    }
+
+    #[test]
+    fn view_counts_are_parsed() {
+        assert_eq!(parse_views("Versand möglich 123 Aufrufe"), Some(123));
+        assert_eq!(parse_views("Aufrufe: 1.024"), Some(1024));
+        assert_eq!(parse_views("1 Aufruf"), Some(1));
+        assert_eq!(parse_views("Nur Abholung"), None);
+    }
+
+    #[test]
+    fn conditions_are_read_from_tags() {
+        assert_eq!(parse_condition("Gebraucht"), Some("Gebraucht".to_string()));
+        assert_eq!(
+            parse_condition(" sehr   GUT\n"),
+            Some("Sehr gut".to_string())
+        );
+        assert_eq!(parse_condition("Versand möglich"), None);
+    }
+
+    #[test]
+    fn blocking_pages_are_detected() {
+        let captcha = format!("<html>{}Bitte löse das CAPTCHA</html>", " ".repeat(10_000));
+        assert!(detect_blocked_page(StatusCode::OK, &captcha).is_some());
+        assert!(detect_blocked_page(StatusCode::FORBIDDEN, "").is_some());
+        assert!(detect_blocked_page(StatusCode::OK, "<html></html>").is_some());
+
+        let results = format!(
+            "<html>{}</html>",
+            "<article class='aditem'></article>".repeat(200)
+        );
+        assert!(detect_blocked_page(StatusCode::OK, &results).is_none());
+
+        // A result page whose scripts and markup mention a CAPTCHA isn't blocked.
+        let with_script = format!(
+            "<html><head><title>Kleinanzeigen</title><script>loadCaptcha();</script></head><body><div data-captcha-key='x'></div>{}</body></html>",
+            "<article class='aditem'>Sofa</article>".repeat(200)
+        );
+        assert!(detect_blocked_page(StatusCode::OK, &with_script).is_none());
+        let captcha_title = format!(
+            "<html><head><title>Sicherheitsüberprüfung</title></head><body>{}</body></html>",
+            " ".repeat(10_000)
+        );
+        assert!(detect_blocked_page(StatusCode::OK, &captcha_title).is_some());
+    }
+
+    #[test]
+    fn truncated_and_garbled_bodies_are_detected() {
+        assert!(decode_body(Some(100), b"<html>").is_err());
+        assert!(decode_body(None, b"<html>\xff\xfe</html>").is_err());
+        assert_eq!(
+            decode_body(Some(13), b"<html></html>").unwrap(),
+            "<html></html>"
+        );
+
+        assert!(detect_truncated_page("<html><body><article class='aditem'>").is_some());
+        assert!(detect_truncated_page("<html><body></body></HTML>\n").is_none());
+    }
+
+    #[test]
+    fn image_variants_follow_the_rule_list() {
+        let variants = image_url_variants("https://img.example.de/a.jpg?rule=$_59.AUTO");
+        assert_eq!(variants[0], "https://img.example.de/a.jpg?rule=$_59.AUTO");
+        assert_eq!(variants.len(), IMAGE_RESOLUTION_RULES.len());
+        assert!(IMAGE_RESOLUTION_RULES.iter().all(|rule| {
+            variants.contains(&format!("https://img.example.de/a.jpg?rule={}", rule))
+        }));
+    }
+
+    #[test]
+    fn layout_change_is_suspected_when_selectors_miss() {
+        let big_page = "x".repeat(SUBSTANTIAL_PAGE_BYTES);
+        assert!(detect_layout_change(&big_page, 0, 0).is_some());
+        assert!(detect_layout_change("<html></html>", 3, 0).is_some());
+
+        let no_results = format!("{}Leider keine Ergebnisse gefunden", big_page);
+        assert!(detect_layout_change(&no_results, 0, 0).is_none());
+        assert!(detect_layout_change(&big_page, 25, 25).is_none());
+    }
+}
@@ -0,0 +1,390 @@
+use std::{fmt::Write as _, time::Duration};
+
+use crate::{POLL_INTERVAL_SECS, SEARCH_SORT, scrape::KLEINANZEIGEN_ORIGIN};
+
+/// The order Kleinanzeigen is asked to return search results in.
+// Only the variant chosen in the configuration is ever constructed.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    /// The newest ads first, which stopping at the first seen ad relies on.
+    Newest,
+    /// The cheapest ads first.
+    PriceAscending,
+    /// The most expensive ads first.
+    PriceDescending,
+    /// Whatever the search URL says, or Kleinanzeigen's default if it says nothing.
+    Unchanged,
+}
+
+impl SortOrder {
+    /// Returns the path segment selecting this order, e.g. "sortierung:neueste".
+    fn segment(self) -> Option<&'static str> {
+        match self {
+            SortOrder::Newest => Some("sortierung:neueste"),
+            SortOrder::PriceAscending => Some("sortierung:preis"),
+            SortOrder::PriceDescending => Some("sortierung:preis-absteigend"),
+            SortOrder::Unchanged => None,
+        }
+    }
+}
+
+/// A search by term, whose URL the bot builds instead of a copied one.
+#[derive(Debug, Clone, Copy)]
+pub struct KeywordSearch {
+    /// The search term, e.g. "fahrrad" or "sofa 3-sitzer".
+    pub keyword: &'static str,
+    /// The postal code searched around, e.g. "04105". If `None`, all of Germany is searched.
+    pub postal_code: Option<&'static str>,
+    /// Kleinanzeigen's ID of the place searched around, e.g. `4257` for Leipzig, the number after
+    /// "l" in a copied search URL like ".../c0l4257r10". Only needed together with `radius_km`.
+    pub location_id: Option<u32>,
+    /// The radius around the place in kilometres. Kleinanzeigen only applies it together with
+    /// `location_id`; if `None`, its default applies.
+    pub radius_km: Option<u32>,
+}
+
+/// A Kleinanzeigen search that is scanned for new ads, together with where its ads are sent.
+pub struct Search {
+    /// A short name used in log messages.
+    pub name: &'static str,
+    /// The search URL up to where the page number is inserted,
+    /// e.g. "https://www.kleinanzeigen.de/s-zu-verschenken-tauschen".
+    /// If `url_suffix` is empty, the whole search URL, split by `split_search_url`.
+    pub base_url: &'static str,
+    /// The rest of the search URL after the page number, e.g. "/04105/c272l4257r10".
+    pub url_suffix: &'static str,
+    /// A search term to build the URL from. If set, `base_url` and `url_suffix` are ignored.
+    pub keyword: Option<KeywordSearch>,
+    /// The Telegram chats this search's ads are sent to.
+    /// If empty, they go to the chat of the profile the search belongs to.
+    pub chat_ids: &'static [&'static str],
+    /// The maximum number of result pages scanned per run.
+    /// If `None`, `MAX_PAGES_TO_SCAN` applies.
+    pub max_pages: Option<u32>,
+    /// An emoji or short text put in front of the caption, e.g. "🛋️" for furniture.
+    /// If `None`, `CAPTION_PREFIX` applies.
+    pub prefix: Option<&'static str>,
+    /// The seconds between two scans of this search in watch mode.
+    /// If `None`, `POLL_INTERVAL_SECS` applies.
+    pub poll_interval_secs: Option<u64>,
+}
+
+impl Search {
+    /// Builds the URL of a result page of this search, sorted by `SEARCH_SORT`.
+    ///
+    /// # Arguments
+    /// * `page` - The page number, starting at 1.
+    pub fn page_url(&self, page: u32) -> String {
+        match self.keyword {
+            Some(search) => build_keyword_search_url(&search, page),
+            None => self.page_url_sorted(page, SEARCH_SORT),
+        }
+    }
+
+    /// Builds the URL of a result page of this search in the given order.
+    ///
+    /// A sort order already in `base_url` is kept as it is.
+    fn page_url_sorted(&self, page: u32, sort: SortOrder) -> String {
+        let (base, rest) = match self.url_suffix {
+            "" => split_search_url(self.base_url),
+            suffix => (self.base_url, suffix),
+        };
+        join_search_url(base, page, sort, rest)
+    }
+
+    /// Returns the sort order written into `base_url`, if it isn't the newest ads first.
+    ///
+    /// Such an order overrides `SEARCH_SORT`, see `page_url_sorted`.
+    pub fn fixed_sort_order(&self) -> Option<&'static str> {
+        self.base_url
+            .split('/')
+            .find(|segment| segment.starts_with("sortierung:"))
+            .filter(|&segment| Some(segment) != SortOrder::Newest.segment())
+    }
+
+    /// Returns how long watch mode waits between two scans of this search.
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval_secs.unwrap_or(POLL_INTERVAL_SECS))
+    }
+
+    /// Returns the Telegram chats this search's ads are sent to, falling back to `default_chats`.
+    pub fn target_chats(&self, default_chats: &'static [&'static str]) -> &'static [&'static str] {
+        if self.chat_ids.is_empty() {
+            default_chats
+        } else {
+            self.chat_ids
+        }
+    }
+}
+
+/// Builds the URL of a result page of a search by term, sorted by `SEARCH_SORT`.
+///
+/// The search term is encoded by `encode_keyword`. The radius is only written together with the
+/// location ID, as in ".../fahrrad/k0l4257r10", since Kleinanzeigen ignores it otherwise.
+///
+/// # Arguments
+/// * `search` - The search term and the place searched around.
+/// * `page` - The page number, starting at 1.
+pub fn build_keyword_search_url(search: &KeywordSearch, page: u32) -> String {
+    keyword_search_url_sorted(search, page, SEARCH_SORT)
+}
+
+/// Builds the URL of a result page of a search by term in the given order.
+fn keyword_search_url_sorted(search: &KeywordSearch, page: u32, sort: SortOrder) -> String {
+    let mut base = format!("{}/s-", KLEINANZEIGEN_ORIGIN);
+    let mut rest = format!("/{}/k0", encode_keyword(search.keyword));
+    if let Some(postal_code) = search.postal_code {
+        base.push_str(postal_code.trim());
+    }
+    if let Some(location_id) = search.location_id {
+        let _ = write!(rest, "l{}", location_id);
+        if let Some(radius) = search.radius_km {
+            let _ = write!(rest, "r{}", radius);
+        }
+    }
+    join_search_url(&base, page, sort, &rest)
+}
+
+/// Turns a search term into the path segment Kleinanzeigen uses for it.
+///
+/// The term is lowercased and its words are joined with "-", as in "sofa-3-sitzer".
+/// Anything but ASCII letters, digits, "-", "." and "_" is percent-encoded, e.g. "ö" as "%C3%B6".
+pub fn encode_keyword(keyword: &str) -> String {
+    let slug = keyword
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+    let mut encoded = String::with_capacity(slug.len());
+    for byte in slug.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_') {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{:02X}", byte);
+        }
+    }
+    encoded
+}
+
+/// Puts the sort order and page number between the two parts of a search URL.
+///
+/// # Arguments
+/// * `base` - The URL up to where they go.
+/// * `page` - The page number, starting at 1.
+/// * `sort` - The order asked for. One already in `base` is kept as it is.
+/// * `rest` - The rest of the URL.
+fn join_search_url(base: &str, page: u32, sort: SortOrder, rest: &str) -> String {
+    let mut segments = Vec::new();
+    if let Some(segment) = sort.segment()
+        && !base.contains("sortierung:")
+    {
+        segments.push(segment.to_string());
+    }
+    // The first page has no page number in its URL.
+    if page > 1 {
+        segments.push(format!("seite:{}", page));
+    }
+
+    // Without a category or location, the segments follow "/s-" directly, as in "/s-seite:2/fahrrad/k0".
+    let mut url = base.to_string();
+    for segment in segments {
+        if !url.ends_with("/s-") {
+            url.push('/');
+        }
+        url.push_str(&segment);
+    }
+    if url.ends_with("/s-") {
+        url.push_str(rest.trim_start_matches('/'));
+    } else {
+        if !rest.is_empty() && !rest.starts_with(['/', '?']) {
+            url.push('/');
+        }
+        url.push_str(rest);
+    }
+    url
+}
+
+/// Splits a whole search URL where the sort order and page number go.
+///
+/// Kleinanzeigen puts them after the category and location, but before the search term and the
+/// closing code of categories and area, e.g. "c272l4257r10", "c0l4257r10" or "k0".
+///
+/// # Returns
+/// The URL up to the insertion point and the rest, or the whole URL and an empty rest if it's no
+/// search URL.
+fn split_search_url(url: &str) -> (&str, &str) {
+    let Some(path_start) = url
+        .find("://")
+        .and_then(|scheme_end| url[scheme_end + 3..].find('/').map(|i| scheme_end + 3 + i))
+    else {
+        return (url, "");
+    };
+    let path_end = url.find('?').unwrap_or(url.len());
+    let Some(path) = url[path_start..path_end].strip_prefix("/s-") else {
+        return (url, "");
+    };
+    let segments: Vec<&str> = path.split('/').collect();
+    let is_code = |segment: &str| {
+        let bytes = segment.as_bytes();
+        segment.starts_with("k0")
+            || (bytes.len() > 1 && matches!(bytes[0], b'c' | b'l') && bytes[1].is_ascii_digit())
+    };
+    let mut insert_at = match segments.last() {
+        Some(&last) if is_code(last) => segments.len() - 1,
+        _ => segments.len(),
+    };
+    // A keyword search ends in "k0", right after the search term.
+    if insert_at < segments.len() && segments[insert_at].starts_with("k0") && insert_at > 0 {
+        insert_at -= 1;
+    }
+
+    let mut split = path_start + "/s-".len();
+    if insert_at == segments.len() {
+        split = path_end;
+    } else if insert_at > 0 {
+        split += segments[..insert_at]
+            .iter()
+            .map(|segment| segment.len() + 1)
+            .sum::<usize>()
+            - 1;
+    }
+    url.split_at(split)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(base_url: &'static str) -> Search {
+        Search {
+            name: "Test",
+            base_url,
+            url_suffix: "/04105/c272l4257r10",
+            keyword: None,
+            chat_ids: &[],
+            max_pages: None,
+            prefix: None,
+            poll_interval_secs: None,
+        }
+    }
+
+    #[test]
+    fn page_urls_carry_the_sort_order() {
+        let base = search("https://www.kleinanzeigen.de/s-zu-verschenken-tauschen");
+        assert_eq!(
+            base.page_url_sorted(1, SortOrder::Newest),
+            "https://www.kleinanzeigen.de/s-zu-verschenken-tauschen/sortierung:neueste/04105/c272l4257r10"
+        );
+        assert_eq!(
+            base.page_url_sorted(3, SortOrder::Newest),
+            "https://www.kleinanzeigen.de/s-zu-verschenken-tauschen/sortierung:neueste/seite:3/04105/c272l4257r10"
+        );
+        assert_eq!(
+            base.page_url_sorted(2, SortOrder::Unchanged),
+            "https://www.kleinanzeigen.de/s-zu-verschenken-tauschen/seite:2/04105/c272l4257r10"
+        );
+
+        let sorted =
+            search("https://www.kleinanzeigen.de/s-zu-verschenken-tauschen/sortierung:preis");
+        assert_eq!(
+            sorted.page_url_sorted(2, SortOrder::Newest),
+            "https://www.kleinanzeigen.de/s-zu-verschenken-tauschen/sortierung:preis/seite:2/04105/c272l4257r10"
+        );
+        assert_eq!(sorted.fixed_sort_order(), Some("sortierung:preis"));
+        assert_eq!(base.fixed_sort_order(), None);
+        assert_eq!(
+            search("https://www.kleinanzeigen.de/s-zu-verschenken-tauschen/sortierung:neueste")
+                .fixed_sort_order(),
+            None
+        );
+    }
+
+    #[test]
+    fn whole_search_urls_get_the_page_in_the_right_place() {
+        let page_url = |url: &'static str, page| {
+            let search = Search {
+                url_suffix: "",
+                ..search(url)
+            };
+            search.page_url_sorted(page, SortOrder::Newest)
+        };
+        // Category and location
+        assert_eq!(
+            page_url(
+                "https://www.kleinanzeigen.de/s-zu-verschenken-tauschen/04105/c272l4257r10",
+                2
+            ),
+            "https://www.kleinanzeigen.de/s-zu-verschenken-tauschen/04105/sortierung:neueste/seite:2/c272l4257r10"
+        );
+        // Location only, across all categories
+        assert_eq!(
+            page_url("https://www.kleinanzeigen.de/s-04105/c0l4257r10", 3),
+            "https://www.kleinanzeigen.de/s-04105/sortierung:neueste/seite:3/c0l4257r10"
+        );
+        assert_eq!(
+            page_url("https://www.kleinanzeigen.de/s-04105/l4257r10", 1),
+            "https://www.kleinanzeigen.de/s-04105/sortierung:neueste/l4257r10"
+        );
+        // Keyword, with and without location
+        assert_eq!(
+            page_url("https://www.kleinanzeigen.de/s-fahrrad/k0", 2),
+            "https://www.kleinanzeigen.de/s-sortierung:neueste/seite:2/fahrrad/k0"
+        );
+        assert_eq!(
+            page_url("https://www.kleinanzeigen.de/s-04105/fahrrad/k0l4257r10", 2),
+            "https://www.kleinanzeigen.de/s-04105/sortierung:neueste/seite:2/fahrrad/k0l4257r10"
+        );
+        // Without a sort order, the first page stays as configured.
+        let unsorted = Search {
+            url_suffix: "",
+            ..search("https://www.kleinanzeigen.de/s-fahrrad/k0")
+        };
+        assert_eq!(
+            unsorted.page_url_sorted(1, SortOrder::Unchanged),
+            "https://www.kleinanzeigen.de/s-fahrrad/k0"
+        );
+    }
+
+    #[test]
+    fn keyword_search_urls_are_built_and_encoded() {
+        assert_eq!(encode_keyword("  Sofa   3-Sitzer "), "sofa-3-sitzer");
+        assert_eq!(encode_keyword("Möbel & Co/"), "m%C3%B6bel-%26-co%2F");
+        let fahrrad = KeywordSearch {
+            keyword: "fahrrad",
+            postal_code: None,
+            location_id: None,
+            radius_km: Some(10),
+        };
+        assert_eq!(
+            keyword_search_url_sorted(&fahrrad, 1, SortOrder::Newest),
+            "https://www.kleinanzeigen.de/s-sortierung:neueste/fahrrad/k0"
+        );
+        let damen = KeywordSearch {
+            keyword: "Fahrrad Damen",
+            radius_km: None,
+            ..fahrrad
+        };
+        assert_eq!(
+            keyword_search_url_sorted(&damen, 2, SortOrder::Unchanged),
+            "https://www.kleinanzeigen.de/s-seite:2/fahrrad-damen/k0"
+        );
+        // Without the location ID, Kleinanzeigen ignores the radius, so it isn't written.
+        let postal_code_only = KeywordSearch {
+            postal_code: Some("04105"),
+            ..fahrrad
+        };
+        assert_eq!(
+            keyword_search_url_sorted(&postal_code_only, 1, SortOrder::Newest),
+            "https://www.kleinanzeigen.de/s-04105/sortierung:neueste/fahrrad/k0"
+        );
+        let leipzig = KeywordSearch {
+            location_id: Some(4257),
+            ..postal_code_only
+        };
+        assert_eq!(
+            keyword_search_url_sorted(&leipzig, 3, SortOrder::Newest),
+            "https://www.kleinanzeigen.de/s-04105/sortierung:neueste/seite:3/fahrrad/k0l4257r10"
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    fs::{read_to_string, write},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string_pretty};
+
+/// How the first run seeds the seen list.
+// Only the variant chosen in the configuration is ever constructed.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirstRunMode {
+    /// Send the newest `FIRST_RUN_LIMIT` ads and mark them as seen.
+    Backfill,
+    /// Mark the ads of the first result page and the overlap pages after it as seen without
+    /// sending anything.
+    StartLive,
+}
+
+/// Whether the first run, which seeds the seen list, got through, see `FIRST_RUN_FILE`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirstRun {
+    /// `false` from the start of the first run until every search was seeded.
+    pub complete: bool,
+    /// The names of the searches that got through at least once since the first run started.
+    #[serde(default)]
+    pub seeded: BTreeSet<String>,
+    /// How many runs the first run took so far.
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+impl FirstRun {
+    /// Returns whether the search still seeds the seen list with the rules of the first run.
+    pub fn is_seeding(&self, search: &str) -> bool {
+        !self.complete && !self.seeded.contains(search)
+    }
+
+    /// Records a run of the first run, which completes it once every search was seeded or after
+    /// `max_attempts` runs, so a search that keeps failing doesn't keep the others seeding.
+    ///
+    /// # Arguments
+    /// * `seeded` - The searches that got through in this run.
+    /// * `searches` - The names of all searches of the profile.
+    /// * `max_attempts` - The number of runs after which the first run completes regardless.
+    pub fn record(&mut self, seeded: &[&str], searches: &[&str], max_attempts: u32) {
+        self.seeded
+            .extend(seeded.iter().map(|search| search.to_string()));
+        self.attempts += 1;
+        self.complete = self.attempts >= max_attempts
+            || searches.iter().all(|search| self.seeded.contains(*search));
+    }
+}
+
+/// Loads the first-run marker.
+///
+/// # Returns
+/// The marker, or `None` if the file does not exist or contains invalid data.
+pub fn load_first_run(path: &str) -> Option<FirstRun> {
+    let content = read_to_string(path).ok()?;
+    from_str(&content)
+        .inspect_err(|e| log!("Fehler beim Parsen der Datei {}: {}", path, e))
+        .ok()
+}
+
+/// Saves the first-run marker.
+pub fn save_first_run(path: &str, first_run: &FirstRun) -> Result<(), Box<dyn Error>> {
+    write(path, to_string_pretty(first_run)?)?;
+    Ok(())
+}
+
+/// Decides whether a run seeds the seen list, with the rules of the first run.
+///
+/// # Arguments
+/// * `marker` - The first-run marker, if there is one.
+/// * `store_is_empty` - Whether no ad was seen yet.
+///
+/// # Returns
+/// `true` if nothing was seen yet, e.g. after deleting the seen list, or while an interrupted
+/// first run is continued. Seen lists from before the marker existed count as seeded.
+pub fn is_seeding(marker: Option<&FirstRun>, store_is_empty: bool) -> bool {
+    store_is_empty || marker.is_some_and(|first_run| !first_run.complete)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_interrupted_first_run_is_continued() {
+        let started = Some(&FirstRun::default());
+        let done = Some(&FirstRun {
+            complete: true,
+            ..FirstRun::default()
+        });
+        assert!(is_seeding(None, true));
+        assert!(!is_seeding(None, false));
+        // Some ads were seen before the first run broke off.
+        assert!(is_seeding(started, false));
+        assert!(!is_seeding(done, false));
+        assert!(is_seeding(done, true));
+    }
+
+    #[test]
+    fn a_search_that_keeps_failing_ends_the_first_run_after_max_attempts() {
+        let searches = ["sofa", "tisch"];
+        let mut first_run = FirstRun::default();
+        // "tisch" fails every time, "sofa" is only seeded once.
+        first_run.record(&["sofa"], &searches, 3);
+        assert!(!first_run.complete);
+        assert!(!first_run.is_seeding("sofa"));
+        assert!(first_run.is_seeding("tisch"));
+        first_run.record(&["sofa"], &searches, 3);
+        assert!(!first_run.complete);
+        first_run.record(&[], &searches, 3);
+        assert!(first_run.complete);
+        assert!(!first_run.is_seeding("tisch"));
+
+        // Once every search got through, the first run is complete right away.
+        let mut first_run = FirstRun::default();
+        first_run.record(&["tisch"], &searches, 3);
+        first_run.record(&["sofa"], &searches, 3);
+        assert!(first_run.complete);
+    }
+}
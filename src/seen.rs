@@ -0,0 +1,506 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    error::Error,
+    fmt::{self, Display, Formatter},
+    fs::{copy, read, write},
+    io::{ErrorKind, Read, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, from_str, from_value, to_string, to_string_pretty};
+
+use crate::{Ad, COMPACT_SEEN_ADS, bloom::fnv1a};
+
+/// The first two bytes of every gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The format version written by `save_seen_ads`.
+///
+/// * `1` - A bare list of ad IDs (the original format).
+/// * `2` - A list of `SeenAd` entries with a timestamp each.
+const SEEN_ADS_FORMAT_VERSION: u32 = 2;
+
+/// An ad we've already notified about, together with when that happened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeenAd {
+    /// The dedup key of the ad, which is its ID unless another `DedupStrategy` is active.
+    pub id: String,
+    /// When the ad was marked as seen, in seconds since the Unix epoch.
+    pub seen_at: u64,
+}
+
+impl SeenAd {
+    /// Creates an entry for `id` stamped with the current time.
+    pub fn now(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            seen_at: unix_now(),
+        }
+    }
+}
+
+/// Selects how the list of seen ads is kept from growing without bound.
+// Only the variant chosen in the configuration is ever constructed.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PruneMode {
+    /// Keep only the newest `MAX_SEEN_ADS` entries.
+    Count,
+    /// Drop entries older than `MAX_SEEN_AGE_DAYS`, regardless of how many there are.
+    MaxAge,
+}
+
+/// Selects what makes two ads the same, and thus which keys the seen list stores.
+// Only the variant chosen in the configuration is ever constructed.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DedupStrategy {
+    /// Only the ad ID counts.
+    #[default]
+    IdOnly,
+    /// The ad ID together with its URL, so an ID reused for another listing is announced again.
+    IdAndUrl,
+    /// A hash of the normalized title and location, so reposts under a new ID aren't announced again.
+    ContentHash,
+}
+
+impl DedupStrategy {
+    /// Returns the key an ad is remembered by under this strategy.
+    pub fn key(self, ad: &Ad) -> String {
+        match self {
+            DedupStrategy::IdOnly => ad.id.clone(),
+            DedupStrategy::IdAndUrl => format!("{}|{}", ad.id, ad.link),
+            DedupStrategy::ContentHash => content_key(&ad.title, ad.location.as_deref()),
+        }
+    }
+}
+
+/// Hashes the title and location of an ad, ignoring case, punctuation and spacing.
+fn content_key(title: &str, location: Option<&str>) -> String {
+    let normalized: Vec<String> = [title, location.unwrap_or_default()]
+        .iter()
+        .flat_map(|text| text.split(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    format!("h:{:016x}", fnv1a(&normalized.join(" "), 0))
+}
+
+/// The versioned envelope the seen ads are persisted in.
+#[derive(Deserialize)]
+struct VersionedFile {
+    /// The format version of `data`.
+    version: u32,
+    /// The strategy the keys in `data` were made with. Files from before strategies existed hold IDs.
+    #[serde(default)]
+    strategy: DedupStrategy,
+    /// The payload, interpreted according to `version`.
+    data: Value,
+}
+
+/// The serializable counterpart of `VersionedFile`, borrowing the queue.
+#[derive(Serialize)]
+struct VersionedFileRef<'a> {
+    version: u32,
+    strategy: DedupStrategy,
+    data: &'a VecDeque<SeenAd>,
+}
+
+/// The on-disk formats the seen-ads file may be stored in.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SeenAdsFile {
+    /// The current format, wrapped in a versioned envelope.
+    Versioned(VersionedFile),
+    /// Version 2 entries written before the envelope was introduced.
+    Entries(VecDeque<SeenAd>),
+    /// The original format, a bare list of ad IDs.
+    Legacy(VecDeque<String>),
+}
+
+/// Upgrades the payload of a given format version to the current representation.
+///
+/// # Returns
+/// The migrated queue, or an error if the version is unknown or the payload doesn't match it.
+fn migrate(version: u32, data: Value) -> Result<VecDeque<SeenAd>, Box<dyn Error>> {
+    match version {
+        1 => Ok(migrate_ids(from_value(data)?)),
+        2 => Ok(from_value(data)?),
+        _ => Err(format!(
+            "Unbekannte Formatversion {} (unterstützt bis Version {})",
+            version, SEEN_ADS_FORMAT_VERSION
+        )
+        .into()),
+    }
+}
+
+/// Upgrades a bare list of IDs (version 1) into timestamped entries.
+///
+/// Every entry is stamped with the current time so none of them expires immediately.
+fn migrate_ids(ids: VecDeque<String>) -> VecDeque<SeenAd> {
+    let now = unix_now();
+    ids.into_iter()
+        .map(|id| SeenAd { id, seen_at: now })
+        .collect()
+}
+
+/// Parses the content of a seen-ads file in any known format.
+///
+/// # Returns
+/// The strategy the keys were made with, and the entries.
+fn parse_seen_ads(content: &str) -> Result<(DedupStrategy, VecDeque<SeenAd>), Box<dyn Error>> {
+    match from_str(content)? {
+        SeenAdsFile::Versioned(file) => Ok((file.strategy, migrate(file.version, file.data)?)),
+        SeenAdsFile::Entries(queue) => Ok((DedupStrategy::IdOnly, queue)),
+        SeenAdsFile::Legacy(ids) => Ok((DedupStrategy::IdOnly, migrate_ids(ids))),
+    }
+}
+
+/// Turns the bytes of a seen-ads file into its JSON text, decompressing gzip-compressed files.
+fn decode_seen_file(bytes: Vec<u8>) -> Result<String, Box<dyn Error>> {
+    let bytes = if bytes.starts_with(&GZIP_MAGIC) {
+        let mut decompressed = Vec::new();
+        GzDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed)?;
+        decompressed
+    } else {
+        bytes
+    };
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Returns the file to load the seen ads from: `path`, or if it doesn't exist, the same file
+/// with or without the ".gz" extension, so switching compression on or off keeps the list.
+fn existing_seen_file(path: &str) -> String {
+    let counterpart = match path.strip_suffix(".gz") {
+        Some(uncompressed) => uncompressed.to_string(),
+        None => format!("{}.gz", path),
+    };
+    if !Path::new(path).exists() && Path::new(&counterpart).exists() {
+        log!("Die Datei {} fehlt, übernehme {}.", path, counterpart);
+        return counterpart;
+    }
+    path.to_string()
+}
+
+/// Copies a seen-ads file that can't be used to `<path>.bak`, so it isn't lost when the queue is saved next.
+fn back_up(path: &str) {
+    let backup_path = format!("{}.bak", path);
+    match copy(path, &backup_path) {
+        Ok(_) => log!("Die alte Datei wurde nach {} gesichert.", backup_path),
+        Err(e) => log!("Fehler beim Sichern nach {}: {}", backup_path, e),
+    }
+}
+
+/// Returns the current time in seconds since the Unix epoch.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// The seen-ads file exists but can't be read or parsed, so starting over would announce every
+/// live ad again.
+#[derive(Debug)]
+pub struct CorruptSeenFile {
+    /// The path of the file.
+    pub path: String,
+    /// Why reading or parsing failed.
+    pub reason: String,
+}
+
+impl Display for CorruptSeenFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Die Datei {} ist beschädigt ({}). Der Durchlauf wird abgebrochen, damit nicht alle Anzeigen erneut gemeldet werden. Bitte die Datei reparieren oder mit --force-reseed neu aufbauen",
+            self.path, self.reason
+        )
+    }
+}
+
+impl Error for CorruptSeenFile {}
+
+/// Loads the queue of already-seen ads from a JSON file, which may be gzip-compressed.
+///
+/// If the file does not exist or is empty, it returns an empty queue. A missing file is also
+/// looked for with or without ".gz", see `existing_seen_file`.
+/// Older format versions are migrated to the current one. A file whose keys were made with
+/// another `strategy` is copied to `<path>.bak` before starting over with an empty queue, so it
+/// isn't lost when the queue is saved next. A file that can't be read or parsed is treated the
+/// same only with `force_reseed`.
+/// A VecDeque is used to efficiently remove old items from the front.
+///
+/// # Returns
+/// The queue and whether it starts over because the keys were made with another strategy, or
+/// `CorruptSeenFile` if the file can't be read or parsed and `force_reseed` isn't set.
+pub fn load_seen_ads(
+    path: &str,
+    strategy: DedupStrategy,
+    force_reseed: bool,
+) -> Result<(VecDeque<SeenAd>, bool), CorruptSeenFile> {
+    let path = &existing_seen_file(path);
+    let mut strategy_changed = false;
+    let queue = match read(path) {
+        Ok(bytes) if bytes.iter().all(u8::is_ascii_whitespace) => {
+            log!("Die Datei {} ist leer.", path);
+            VecDeque::new()
+        }
+        Ok(bytes) => match decode_seen_file(bytes).and_then(|content| parse_seen_ads(&content)) {
+            Ok((file_strategy, queue)) if file_strategy == strategy => queue,
+            Ok((file_strategy, _)) => {
+                log!(
+                    "Warnung: Die Datei {} wurde mit der Strategie {:?} angelegt, aktiv ist {:?}. Die Liste der gesehenen Anzeigen wird neu aufgebaut, ohne die aktuellen Anzeigen erneut zu melden.",
+                    path,
+                    file_strategy,
+                    strategy
+                );
+                back_up(path);
+                strategy_changed = true;
+                VecDeque::new()
+            }
+            Err(e) if force_reseed => {
+                log!(
+                    "Fehler beim Parsen der Datei {}: {}. Sie wird wegen --force-reseed neu aufgebaut.",
+                    path,
+                    e
+                );
+                back_up(path);
+                VecDeque::new()
+            }
+            Err(e) => {
+                return Err(CorruptSeenFile {
+                    path: path.to_string(),
+                    reason: e.to_string(),
+                });
+            }
+        },
+        Err(e) if e.kind() == ErrorKind::NotFound => VecDeque::new(),
+        // A file that exists but can't be read is no reason to announce every live ad again.
+        Err(e) if force_reseed => {
+            log!(
+                "Fehler beim Lesen der Datei {}: {}. Sie wird wegen --force-reseed neu aufgebaut.",
+                path,
+                e
+            );
+            VecDeque::new()
+        }
+        Err(e) => {
+            return Err(CorruptSeenFile {
+                path: path.to_string(),
+                reason: e.to_string(),
+            });
+        }
+    };
+    Ok((queue, strategy_changed))
+}
+
+/// Reads a seen-ads file in any known format, failing instead of starting over.
+///
+/// Used for imports, where a broken file must not be mistaken for an empty one.
+/// Keys made with another strategy than `strategy` can't be merged and are rejected.
+pub fn read_seen_ads(
+    path: &str,
+    strategy: DedupStrategy,
+) -> Result<VecDeque<SeenAd>, Box<dyn Error>> {
+    let (file_strategy, queue) = parse_seen_ads(&decode_seen_file(read(path)?)?)?;
+    if file_strategy != strategy {
+        return Err(format!(
+            "Die Datei wurde mit der Strategie {:?} angelegt, aktiv ist {:?}",
+            file_strategy, strategy
+        )
+        .into());
+    }
+    Ok(queue)
+}
+
+/// Saves the provided queue of seen ads to a JSON file.
+///
+/// The data is wrapped in a versioned envelope, together with the strategy the keys were made
+/// with, and pretty-printed for human readability unless `COMPACT_SEEN_ADS` is set. A path
+/// ending in ".gz" is written gzip-compressed, and always compact.
+pub fn save_seen_ads(
+    path: &str,
+    seen_ads: &VecDeque<SeenAd>,
+    strategy: DedupStrategy,
+) -> Result<(), Box<dyn Error>> {
+    let file = VersionedFileRef {
+        version: SEEN_ADS_FORMAT_VERSION,
+        strategy,
+        data: seen_ads,
+    };
+    let compressed = path.ends_with(".gz");
+    let content = if COMPACT_SEEN_ADS || compressed {
+        to_string(&file)?
+    } else {
+        to_string_pretty(&file)?
+    };
+    if compressed {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content.as_bytes())?;
+        write(path, encoder.finish()?)?;
+    } else {
+        write(path, content)?;
+    }
+    Ok(())
+}
+
+/// Merges imported entries into the queue without duplicating IDs.
+///
+/// For IDs present in both, the earlier timestamp is kept. The result is ordered
+/// oldest first, so pruning afterwards drops the oldest entries of either side.
+///
+/// # Returns
+/// The number of IDs that were not in the queue before.
+pub fn merge_seen_ads(seen_ads: &mut VecDeque<SeenAd>, imported: VecDeque<SeenAd>) -> usize {
+    let mut index_by_id: HashMap<String, usize> = seen_ads
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| (entry.id.clone(), index))
+        .collect();
+    let mut added = 0;
+    for entry in imported {
+        match index_by_id.get(&entry.id) {
+            Some(&index) => {
+                let existing = &mut seen_ads[index];
+                existing.seen_at = existing.seen_at.min(entry.seen_at);
+            }
+            None => {
+                index_by_id.insert(entry.id.clone(), seen_ads.len());
+                seen_ads.push_back(entry);
+                added += 1;
+            }
+        }
+    }
+
+    // A stable sort keeps the existing order among entries with the same timestamp.
+    seen_ads
+        .make_contiguous()
+        .sort_by_key(|entry| entry.seen_at);
+    added
+}
+
+/// Removes old entries from the queue according to the selected mode.
+///
+/// # Arguments
+/// * `seen_ads` - The queue to prune, oldest entries first.
+/// * `mode` - Whether to prune by count or by age.
+/// * `max_entries` - The number of entries to keep in `PruneMode::Count`.
+/// * `max_age_days` - The maximum entry age in `PruneMode::MaxAge`.
+///
+/// # Returns
+/// The number of removed entries.
+pub fn prune_seen_ads(
+    seen_ads: &mut VecDeque<SeenAd>,
+    mode: PruneMode,
+    max_entries: usize,
+    max_age_days: u64,
+) -> usize {
+    let before = seen_ads.len();
+    match mode {
+        PruneMode::Count => {
+            // If the queue is larger than the limit, remove the oldest items from the front.
+            while seen_ads.len() > max_entries {
+                seen_ads.pop_front();
+            }
+        }
+        PruneMode::MaxAge => {
+            let cutoff = unix_now().saturating_sub(max_age_days * 24 * 60 * 60);
+            seen_ads.retain(|entry| entry.seen_at >= cutoff);
+        }
+    }
+    before - seen_ads.len()
+}
+
+/// Drops the entries older than `max_age_days`, except those of ads that are still online.
+///
+/// # Arguments
+/// * `seen_ads` - The queue to expire entries from, oldest entries first.
+/// * `max_age_days` - The age after which an entry expires.
+/// * `live` - The keys of the ads on the result pages scanned in this run, which are kept.
+///
+/// # Returns
+/// The number of removed entries.
+pub fn expire_seen_ads(
+    seen_ads: &mut VecDeque<SeenAd>,
+    max_age_days: u64,
+    live: &HashSet<String>,
+) -> usize {
+    let before = seen_ads.len();
+    let cutoff = unix_now().saturating_sub(max_age_days * 24 * 60 * 60);
+    seen_ads.retain(|entry| entry.seen_at >= cutoff || live.contains(&entry.id));
+    before - seen_ads.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_key_ignores_case_and_punctuation() {
+        assert_eq!(
+            content_key("Sofa, grau – 3-Sitzer!", Some("04105 Leipzig")),
+            content_key("sofa grau 3 sitzer", Some("04105  leipzig"))
+        );
+        assert_ne!(
+            content_key("Sofa grau", Some("04105 Leipzig")),
+            content_key("Sofa grau", Some("04107 Leipzig"))
+        );
+    }
+
+    #[test]
+    fn files_without_strategy_hold_ids() {
+        let (strategy, queue) =
+            parse_seen_ads(r#"{"version": 2, "data": [{"id": "1", "seen_at": 5}]}"#).unwrap();
+        assert_eq!(strategy, DedupStrategy::IdOnly);
+        assert_eq!(queue[0].id, "1");
+
+        let (strategy, _) = parse_seen_ads(r#"["1", "2"]"#).unwrap();
+        assert_eq!(strategy, DedupStrategy::IdOnly);
+
+        let (strategy, _) =
+            parse_seen_ads(r#"{"version": 2, "strategy": "ContentHash", "data": []}"#).unwrap();
+        assert_eq!(strategy, DedupStrategy::ContentHash);
+    }
+
+    #[test]
+    fn compressed_files_load_unchanged() {
+        let path = std::env::temp_dir().join(format!("seen-{}.json.gz", std::process::id()));
+        let path = path.to_str().unwrap();
+        let queue: VecDeque<SeenAd> = (0..200u64)
+            .map(|i| SeenAd {
+                id: (2876543210 + i * 7919).to_string(),
+                seen_at: 1760000000 + i * 613,
+            })
+            .collect();
+        save_seen_ads(path, &queue, DedupStrategy::IdOnly).unwrap();
+        let bytes = read(path).unwrap();
+        assert!(bytes.starts_with(&GZIP_MAGIC));
+        assert!(bytes.len() < to_string(&queue).unwrap().len() / 3);
+
+        let (loaded, _) = load_seen_ads(path, DedupStrategy::IdOnly, false).unwrap();
+        let _ = std::fs::remove_file(path);
+        assert_eq!(to_string(&loaded).unwrap(), to_string(&queue).unwrap());
+
+        let mut corrupt = bytes.clone();
+        let middle = corrupt.len() / 2;
+        corrupt[middle] ^= 0xff;
+        assert!(decode_seen_file(corrupt).is_err());
+    }
+
+    #[test]
+    fn only_a_missing_file_starts_empty() {
+        let missing =
+            std::env::temp_dir().join(format!("seen-missing-{}.json", std::process::id()));
+        let (queue, _) =
+            load_seen_ads(missing.to_str().unwrap(), DedupStrategy::IdOnly, false).unwrap();
+        assert!(queue.is_empty());
+
+        // A directory exists but can't be read as a file.
+        let unreadable = std::env::temp_dir();
+        assert!(load_seen_ads(unreadable.to_str().unwrap(), DedupStrategy::IdOnly, false).is_err());
+    }
+}
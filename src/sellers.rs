@@ -0,0 +1,194 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    error::Error,
+    fs::{read_to_string, write},
+    mem::take,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string_pretty};
+
+use crate::Ad;
+
+/// An ad held back because its seller was notified about too recently.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QueuedAd {
+    /// The ad, as it would have been sent.
+    pub ad: Ad,
+    /// The name of the search that found the ad.
+    pub search: String,
+    /// The chats the ad goes to.
+    pub chat_ids: Vec<String>,
+    /// The seller's profile URL, which identifies them across listings.
+    pub seller: String,
+}
+
+/// When each seller was last notified about, and the ads waiting for their turn,
+/// see `SELLER_MIN_INTERVAL_SECS`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SellerQueue {
+    /// When an ad of each seller was last delivered, in seconds since the Unix epoch.
+    #[serde(default)]
+    last_sent: BTreeMap<String, u64>,
+    /// The held-back ads, oldest first.
+    #[serde(default)]
+    queued: Vec<QueuedAd>,
+    /// The sellers with an ad scheduled in this run, so a second one waits even before the first
+    /// is delivered.
+    #[serde(skip)]
+    scheduled: HashSet<String>,
+}
+
+impl SellerQueue {
+    /// Loads the queue from `path`.
+    ///
+    /// If the file does not exist or contains invalid data, it starts with an empty queue.
+    pub fn load(path: &str) -> Self {
+        match read_to_string(path) {
+            Ok(content) => from_str(&content).unwrap_or_else(|e| {
+                log!("Fehler beim Parsen der Datei {}: {}", path, e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Saves the queue to a JSON file.
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        write(path, to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Checks whether an ad of this seller may be scheduled now, and if so, reserves their turn.
+    ///
+    /// # Arguments
+    /// * `seller` - The seller's profile URL.
+    /// * `now` - The current time in seconds since the Unix epoch.
+    /// * `interval` - The minimum number of seconds between two ads of one seller.
+    pub fn try_schedule(&mut self, seller: &str, now: u64, interval: u64) -> bool {
+        let waited = self
+            .last_sent
+            .get(seller)
+            .is_none_or(|&sent| now.saturating_sub(sent) >= interval);
+        waited && self.scheduled.insert(seller.to_string())
+    }
+
+    /// Holds an ad back until its seller's interval has passed.
+    pub fn enqueue(&mut self, ad: QueuedAd) {
+        self.queued.push(ad);
+    }
+
+    /// Takes the queued ads whose seller may be notified about again, at most one per seller.
+    ///
+    /// # Returns
+    /// The released ads, oldest first, with their seller's turn reserved.
+    pub fn release(&mut self, now: u64, interval: u64) -> Vec<QueuedAd> {
+        let mut released = Vec::new();
+        let mut kept = Vec::new();
+        for queued in take(&mut self.queued) {
+            if self.try_schedule(&queued.seller, now, interval) {
+                released.push(queued);
+            } else {
+                kept.push(queued);
+            }
+        }
+        self.queued = kept;
+        released
+    }
+
+    /// Returns released ads that weren't delivered to the front of the queue, in their order.
+    pub fn put_back(&mut self, ads: Vec<QueuedAd>) {
+        self.queued.splice(0..0, ads);
+    }
+
+    /// Remembers that an ad of this seller was delivered, and forgets sellers whose interval
+    /// has long passed.
+    pub fn record_sent(&mut self, seller: &str, now: u64, interval: u64) {
+        self.last_sent
+            .retain(|_, &mut sent| now.saturating_sub(sent) < interval);
+        self.last_sent.insert(seller.to_string(), now);
+    }
+
+    /// Returns the number of ads waiting for their turn.
+    pub fn len(&self) -> usize {
+        self.queued.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(id: &str, seller: &str) -> QueuedAd {
+        QueuedAd {
+            ad: Ad {
+                id: id.to_string(),
+                title: String::new(),
+                link: String::new(),
+                image_url: None,
+                full_image_url: None,
+                posted: None,
+                posted_at: None,
+                location: None,
+                shipping: None,
+                negotiable: false,
+                promoted: false,
+                views: None,
+                condition: None,
+                labels: Vec::new(),
+                prefix: None,
+                description: None,
+                top_match: false,
+                search: "",
+            },
+            search: "Sofa".to_string(),
+            chat_ids: vec!["42".to_string()],
+            seller: seller.to_string(),
+        }
+    }
+
+    #[test]
+    fn ads_of_one_seller_are_spaced_out() {
+        let mut queue = SellerQueue::default();
+        assert!(queue.try_schedule("a", 1000, 600));
+        assert!(!queue.try_schedule("a", 1000, 600));
+        assert!(queue.try_schedule("b", 1000, 600));
+        queue.record_sent("a", 1000, 600);
+        queue.enqueue(queued("1", "a"));
+        queue.enqueue(queued("2", "a"));
+
+        // The next run comes too early, so both keep waiting.
+        let mut queue = from_str::<SellerQueue>(&to_string_pretty(&queue).unwrap()).unwrap();
+        assert!(queue.release(1300, 600).is_empty());
+        assert_eq!(queue.len(), 2);
+
+        // Once the interval has passed, one ad per run is released.
+        let mut queue = from_str::<SellerQueue>(&to_string_pretty(&queue).unwrap()).unwrap();
+        let released: Vec<String> = queue
+            .release(1600, 600)
+            .into_iter()
+            .map(|queued| queued.ad.id)
+            .collect();
+        assert_eq!(released, ["1"]);
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn undelivered_ads_go_back_to_the_front() {
+        let mut queue = SellerQueue::default();
+        queue.enqueue(queued("1", "a"));
+        queue.enqueue(queued("2", "b"));
+        queue.enqueue(queued("3", "a"));
+        let released = queue.release(1000, 600);
+        assert_eq!(released.len(), 2);
+
+        // Sending failed, so the released ads wait again ahead of the one still queued.
+        queue.put_back(released);
+        let ids: Vec<&str> = queue
+            .queued
+            .iter()
+            .map(|queued| queued.ad.id.as_str())
+            .collect();
+        assert_eq!(ids, ["1", "2", "3"]);
+    }
+}
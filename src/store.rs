@@ -0,0 +1,232 @@
+mod redis;
+
+use std::{
+    collections::{HashSet, VecDeque},
+    error::Error,
+};
+
+use crate::seen::{
+    CorruptSeenFile, DedupStrategy, PruneMode, SeenAd, expire_seen_ads, load_seen_ads,
+    prune_seen_ads, save_seen_ads,
+};
+
+pub use redis::RedisStore;
+
+/// Where the seen ads are kept.
+// Only the variant chosen in the configuration is ever constructed.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeenBackend {
+    /// A local JSON file, see `JsonFileStore`.
+    JsonFile,
+    /// A shared Redis instance, see `RedisStore`.
+    Redis,
+}
+
+/// A backend that remembers which ads were already handled, by their dedup key.
+///
+/// `JsonFileStore` keeps them in a local file, `RedisStore` in a Redis instance shared by
+/// several bot instances. Other backends only need to implement these methods.
+pub trait SeenStore {
+    /// Checks whether an ad with this key was already handled.
+    ///
+    /// # Returns
+    /// Whether it was, or an error if the backend couldn't be asked. The scan of the search then
+    /// fails, so the ad is neither sent twice nor taken as seen by mistake.
+    async fn contains(&self, key: &str) -> Result<bool, Box<dyn Error>>;
+
+    /// Remembers an ad with this key as handled.
+    async fn insert(&mut self, key: &str);
+
+    /// Removes old entries according to the selected mode.
+    ///
+    /// # Returns
+    /// The number of removed entries.
+    fn prune(&mut self, mode: PruneMode, max_entries: usize, max_age_days: u64) -> usize;
+
+    /// Removes entries older than `max_age_days` on top of `prune`, keeping those whose key is
+    /// in `live`, see `expire_seen_ads`.
+    ///
+    /// # Returns
+    /// The number of removed entries.
+    fn expire(&mut self, max_age_days: u64, live: &HashSet<String>) -> usize;
+
+    /// Writes the entries to the backing storage, so the next run sees them.
+    fn persist(&self) -> Result<(), Box<dyn Error>>;
+
+    /// Returns the number of remembered entries, or `None` if the backend can't tell cheaply.
+    fn len(&self) -> Option<usize>;
+
+    /// Checks whether nothing is remembered yet, i.e. this is the first run.
+    async fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+}
+
+/// Keeps the seen ads in memory only, oldest first.
+///
+/// Used on its own in tests and with `--no-persist`, and as the working copy of `JsonFileStore`.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    /// The entries in the order they were handled.
+    entries: VecDeque<SeenAd>,
+    /// The keys of `entries`, for fast lookups.
+    keys: HashSet<String>,
+}
+
+impl MemoryStore {
+    /// Creates a store holding the given entries.
+    pub fn from_entries(entries: VecDeque<SeenAd>) -> Self {
+        let keys = entries.iter().map(|entry| entry.id.clone()).collect();
+        Self { entries, keys }
+    }
+
+    /// Returns the entries, oldest first.
+    pub fn entries(&self) -> &VecDeque<SeenAd> {
+        &self.entries
+    }
+}
+
+impl SeenStore for MemoryStore {
+    async fn contains(&self, key: &str) -> Result<bool, Box<dyn Error>> {
+        Ok(self.keys.contains(key))
+    }
+
+    async fn insert(&mut self, key: &str) {
+        if self.keys.insert(key.to_string()) {
+            self.entries.push_back(SeenAd::now(key));
+        }
+    }
+
+    fn prune(&mut self, mode: PruneMode, max_entries: usize, max_age_days: u64) -> usize {
+        let removed = prune_seen_ads(&mut self.entries, mode, max_entries, max_age_days);
+        if removed > 0 {
+            self.keys = self.entries.iter().map(|entry| entry.id.clone()).collect();
+        }
+        removed
+    }
+
+    fn expire(&mut self, max_age_days: u64, live: &HashSet<String>) -> usize {
+        let removed = expire_seen_ads(&mut self.entries, max_age_days, live);
+        if removed > 0 {
+            self.keys = self.entries.iter().map(|entry| entry.id.clone()).collect();
+        }
+        removed
+    }
+
+    fn persist(&self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn len(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// Keeps the seen ads in a versioned JSON file, see `load_seen_ads` and `save_seen_ads`.
+pub struct JsonFileStore {
+    /// The file the entries are loaded from and saved to.
+    path: String,
+    /// The strategy the keys are made with, recorded in the file.
+    strategy: DedupStrategy,
+    /// The entries loaded from the file, plus the ones handled since.
+    memory: MemoryStore,
+    /// Whether the file was started over because its keys were made with another strategy.
+    strategy_changed: bool,
+}
+
+impl JsonFileStore {
+    /// Loads the store from `path`, starting over if it is missing, see `load_seen_ads`.
+    pub fn load(
+        path: String,
+        strategy: DedupStrategy,
+        force_reseed: bool,
+    ) -> Result<Self, CorruptSeenFile> {
+        let (entries, strategy_changed) = load_seen_ads(&path, strategy, force_reseed)?;
+        Ok(Self {
+            memory: MemoryStore::from_entries(entries),
+            path,
+            strategy,
+            strategy_changed,
+        })
+    }
+
+    /// Checks whether the file was started over because its keys were made with another
+    /// strategy, so the ads listed now have to be seeded without announcing them.
+    pub fn strategy_changed(&self) -> bool {
+        self.strategy_changed
+    }
+
+    /// Returns the entries, oldest first.
+    pub fn entries(&self) -> &VecDeque<SeenAd> {
+        self.memory.entries()
+    }
+}
+
+impl SeenStore for JsonFileStore {
+    async fn contains(&self, key: &str) -> Result<bool, Box<dyn Error>> {
+        self.memory.contains(key).await
+    }
+
+    async fn insert(&mut self, key: &str) {
+        self.memory.insert(key).await;
+    }
+
+    fn prune(&mut self, mode: PruneMode, max_entries: usize, max_age_days: u64) -> usize {
+        self.memory.prune(mode, max_entries, max_age_days)
+    }
+
+    fn expire(&mut self, max_age_days: u64, live: &HashSet<String>) -> usize {
+        self.memory.expire(max_age_days, live)
+    }
+
+    fn persist(&self) -> Result<(), Box<dyn Error>> {
+        save_seen_ads(&self.path, self.memory.entries(), self.strategy)
+    }
+
+    fn len(&self) -> Option<usize> {
+        self.memory.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seen::unix_now;
+
+    #[tokio::test]
+    async fn pruned_keys_are_forgotten() {
+        let mut store = MemoryStore::default();
+        for key in ["1", "2", "3", "2"] {
+            store.insert(key).await;
+        }
+        assert_eq!(store.len(), Some(3));
+
+        assert_eq!(store.prune(PruneMode::Count, 2, 0), 1);
+        assert!(!store.contains("1").await.unwrap());
+        assert!(store.contains("2").await.unwrap() && store.contains("3").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn expired_keys_are_forgotten_unless_still_live() {
+        let day = 24 * 60 * 60;
+        let now = unix_now();
+        let mut store = MemoryStore::from_entries(
+            [
+                ("old", now - 10 * day),
+                ("live", now - 10 * day),
+                ("new", now),
+            ]
+            .map(|(id, seen_at)| SeenAd {
+                id: id.to_string(),
+                seen_at,
+            })
+            .into(),
+        );
+
+        let live = HashSet::from(["live".to_string()]);
+        assert_eq!(store.expire(7, &live), 1);
+        assert!(!store.contains("old").await.unwrap());
+        assert!(store.contains("live").await.unwrap() && store.contains("new").await.unwrap());
+    }
+}
@@ -0,0 +1,108 @@
+use std::{
+    error::Error,
+    fs::{read_to_string, write},
+};
+
+use chrono::{NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string_pretty};
+
+/// The counts collected since the last daily summary, kept across restarts of watch mode.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DailyStats {
+    /// The number of new ads found since the last summary.
+    #[serde(default)]
+    pub seen: usize,
+    /// The number of ads delivered since the last summary.
+    #[serde(default)]
+    pub sent: usize,
+    /// The day the last summary was sent, e.g. "2026-06-01", so it goes out once a day.
+    #[serde(default)]
+    pub last_summary: Option<String>,
+}
+
+impl DailyStats {
+    /// Adds the outcome of a scan to the counts.
+    pub fn record(&mut self, seen: usize, sent: usize) {
+        self.seen += seen;
+        self.sent += sent;
+    }
+
+    /// Checks whether the summary is due: the configured time has passed today and
+    /// today's summary wasn't sent yet.
+    ///
+    /// # Arguments
+    /// * `now` - The current wall-clock time in the configured time zone.
+    /// * `at` - The time of day the summary is sent at.
+    pub fn is_due(&self, now: NaiveDateTime, at: NaiveTime) -> bool {
+        now.time() >= at && self.last_summary.as_deref() != Some(&day(now))
+    }
+
+    /// Formats the summary message, e.g. "📊 Heute: 12 neue Anzeige(n) gesehen, 9 gesendet."
+    pub fn message(&self) -> String {
+        format!(
+            "📊 Heute: {} neue Anzeige(n) gesehen, {} gesendet.",
+            self.seen, self.sent
+        )
+    }
+
+    /// Starts counting anew after the summary for the day of `now` was sent.
+    pub fn reset(&mut self, now: NaiveDateTime) {
+        self.seen = 0;
+        self.sent = 0;
+        self.last_summary = Some(day(now));
+    }
+}
+
+/// Formats the day of a wall-clock time as stored in `DailyStats::last_summary`.
+fn day(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%d").to_string()
+}
+
+/// Loads the counts collected since the last summary.
+///
+/// If the file does not exist or contains invalid data, it returns empty counts.
+pub fn load_daily_stats(path: &str) -> DailyStats {
+    match read_to_string(path) {
+        Ok(content) => from_str(&content).unwrap_or_else(|e| {
+            log!("Fehler beim Parsen der Datei {}: {}", path, e);
+            DailyStats::default()
+        }),
+        Err(_) => DailyStats::default(),
+    }
+}
+
+/// Saves the counts, so a restart doesn't lose them.
+pub fn save_daily_stats(path: &str, stats: &DailyStats) -> Result<(), Box<dyn Error>> {
+    write(path, to_string_pretty(stats)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn summary_is_due_once_a_day_after_the_configured_time() {
+        let at = NaiveTime::from_hms_opt(20, 0, 0).unwrap();
+        let mut stats = DailyStats::default();
+        stats.record(5, 3);
+        stats.record(2, 2);
+        assert_eq!(
+            stats.message(),
+            "📊 Heute: 7 neue Anzeige(n) gesehen, 5 gesendet."
+        );
+
+        assert!(!stats.is_due(time("2026-06-01 19:59"), at));
+        assert!(stats.is_due(time("2026-06-01 20:00"), at));
+        stats.reset(time("2026-06-01 20:00"));
+        assert_eq!((stats.seen, stats.sent), (0, 0));
+        assert!(!stats.is_due(time("2026-06-01 23:30"), at));
+        assert!(!stats.is_due(time("2026-06-02 08:00"), at));
+        assert!(stats.is_due(time("2026-06-02 20:05"), at));
+    }
+}
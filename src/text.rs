@@ -0,0 +1,138 @@
+/// Cleans up a title scraped from the DOM for display.
+///
+/// Decodes HTML entities left in the text (e.g. "&amp;" → "&"), strips control
+/// characters and collapses runs of whitespace into single spaces.
+pub fn sanitize_title(raw: &str) -> String {
+    decode_html_entities(raw)
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Decodes named and numeric HTML entities, leaving anything unrecognized untouched.
+fn decode_html_entities(raw: &str) -> String {
+    let mut decoded = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        // Entities are short, so don't look for the semicolon too far ahead.
+        let entity = rest
+            .char_indices()
+            .take(12)
+            .find(|&(_, c)| c == ';')
+            .and_then(|(end, _)| decode_entity(&rest[1..end]).map(|c| (c, end)));
+        match entity {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Decodes the name of a single entity, without the surrounding `&` and `;`.
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        _ => None,
+    }
+}
+
+/// Shortens text to at most `max_chars` characters, cutting at a word boundary where possible.
+///
+/// An ellipsis is appended if anything was cut off, and counts towards the limit.
+pub fn truncate_text(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    let cut = match cut.rfind(' ') {
+        Some(end) if end > 0 => &cut[..end],
+        _ => &cut,
+    };
+    format!("{}…", cut.trim_end())
+}
+
+/// Escapes text for Telegram's HTML parse mode, including use inside quoted attributes.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_decodes_entities_and_collapses_whitespace() {
+        assert_eq!(
+            sanitize_title("  Tisch &amp;  Stühle\n\t&#8211; &quot;gratis&quot; "),
+            "Tisch & Stühle – \"gratis\""
+        );
+    }
+
+    #[test]
+    fn sanitize_strips_control_characters() {
+        assert_eq!(sanitize_title("Sofa\u{7}\u{0}grau"), "Sofa grau");
+    }
+
+    #[test]
+    fn sanitize_keeps_unknown_entities_and_lone_ampersands() {
+        assert_eq!(sanitize_title("A & B &foo; C"), "A & B &foo; C");
+    }
+
+    #[test]
+    fn decoded_title_is_escaped_exactly_once() {
+        let title = sanitize_title("Kinder&amp;amp;Baby &lt;3");
+        assert_eq!(title, "Kinder&amp;Baby <3");
+        assert_eq!(escape_html(&title), "Kinder&amp;amp;Baby &lt;3");
+        assert_eq!(
+            escape_html(&sanitize_title("Bett &amp; Matratze")),
+            "Bett &amp; Matratze"
+        );
+    }
+
+    #[test]
+    fn truncate_cuts_at_word_boundary() {
+        assert_eq!(truncate_text("Kurz", 10), "Kurz");
+        assert_eq!(
+            truncate_text("Sofa neuwertig, leichte Gebrauchsspuren", 20),
+            "Sofa neuwertig,…"
+        );
+        assert_eq!(truncate_text("Überlänge", 5), "Über…");
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::{BuildHasher, Hasher, RandomState},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use reqwest::Url;
+use tokio::time::sleep;
+
+/// A sliding-window rate limiter that caps how many messages may be sent per key.
+///
+/// Each key (usually a chat ID) keeps the timestamps of its recent sends. A caller
+/// waits in `acquire` until fewer than `max_per_window` sends happened within the
+/// last `window`, so bursts are spread out instead of tripping the API's limits.
+pub struct Throttle {
+    /// The maximum number of sends allowed per key within `window`. `0` disables the limit.
+    max_per_window: usize,
+    /// The length of the sliding window.
+    window: Duration,
+    /// The send timestamps seen within the current window, per key.
+    sent: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl Throttle {
+    /// Creates a throttle allowing at most `max_per_minute` sends per key and minute.
+    pub fn per_minute(max_per_minute: usize) -> Self {
+        Self {
+            max_per_window: max_per_minute,
+            window: Duration::from_secs(60),
+            sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Waits until another send for `key` fits into the window, then records it.
+    pub async fn acquire(&self, key: &str) {
+        if self.max_per_window == 0 {
+            return;
+        }
+
+        loop {
+            let wait = {
+                let mut sent = self.sent.lock().unwrap();
+                let timestamps = sent.entry(key.to_string()).or_default();
+                let now = Instant::now();
+
+                // Forget sends that have left the window.
+                while timestamps
+                    .front()
+                    .is_some_and(|&t| now.duration_since(t) >= self.window)
+                {
+                    timestamps.pop_front();
+                }
+
+                if timestamps.len() < self.max_per_window {
+                    timestamps.push_back(now);
+                    return;
+                }
+
+                // Wait until the oldest send in the window expires.
+                self.window - now.duration_since(timestamps[0])
+            };
+
+            log!(
+                "Sendelimit von {} Nachrichten pro Minute erreicht. Warte {} Sekunden.",
+                self.max_per_window,
+                wait.as_secs_f32().ceil()
+            );
+            sleep(wait).await;
+        }
+    }
+}
+
+/// The request budget for one host, shared by every request sent to it.
+pub struct HostBudget {
+    /// The host name the budget applies to, e.g. "www.kleinanzeigen.de".
+    pub host: &'static str,
+    /// The minimum time between the start of two requests.
+    pub min_interval: Duration,
+    /// The maximum number of requests per minute. `0` disables the cap.
+    pub max_per_minute: usize,
+}
+
+/// Slows down a burst of sends the longer it gets, on top of the host's `min_interval`.
+///
+/// The first `fast_sends` go out at the normal pace, then each further send waits
+/// `step` longer than the one before, up to `max_extra`.
+pub struct DelayRamp {
+    /// How many sends of a burst go out without extra delay.
+    pub fast_sends: usize,
+    /// How much the delay grows with each further send. Zero disables the ramp.
+    pub step: Duration,
+    /// The largest extra delay between two sends.
+    pub max_extra: Duration,
+}
+
+impl DelayRamp {
+    /// Returns the extra delay before the send at `index` of a burst, counting from 0.
+    pub fn extra_delay(&self, index: usize) -> Duration {
+        let Some(steps) = (index + 1).checked_sub(self.fast_sends) else {
+            return Duration::ZERO;
+        };
+        self.step
+            .saturating_mul(u32::try_from(steps).unwrap_or(u32::MAX))
+            .min(self.max_extra)
+    }
+}
+
+/// Drips sends out slowly right after startup, e.g. when a restart after downtime finds a backlog.
+///
+/// At startup each send waits `extra` on top of the normal pace; the extra delay shrinks
+/// evenly until it is gone once `window` has passed.
+pub struct StartupThrottle {
+    /// How long after startup sends are slowed down. Zero disables the throttle.
+    pub window: Duration,
+    /// The extra delay between two sends right at startup.
+    pub extra: Duration,
+}
+
+impl StartupThrottle {
+    /// Returns the extra delay before a send `uptime` after startup.
+    pub fn extra_delay(&self, uptime: Duration) -> Duration {
+        let Some(remaining) = self.window.checked_sub(uptime) else {
+            return Duration::ZERO;
+        };
+        if remaining.is_zero() {
+            return Duration::ZERO;
+        }
+        self.extra
+            .mul_f64(remaining.as_secs_f64() / self.window.as_secs_f64())
+    }
+}
+
+/// Spaces out and caps requests per host, so scraping and sending follow one policy.
+///
+/// Each request reserves the next free slot of its host before it starts, so
+/// concurrent callers queue up instead of sending in bursts. Hosts without a
+/// budget are not limited.
+pub struct RateGovernor {
+    /// The configured budgets.
+    budgets: &'static [HostBudget],
+    /// The earliest time the next request may start, per host.
+    next_slot: Mutex<HashMap<&'static str, Instant>>,
+    /// The per-minute caps, one throttle keyed by host.
+    throttles: Vec<Throttle>,
+    /// When the governor was created, i.e. the bot started.
+    started: Instant,
+}
+
+impl RateGovernor {
+    /// Creates a governor enforcing the given budgets.
+    pub fn new(budgets: &'static [HostBudget]) -> Self {
+        Self {
+            budgets,
+            next_slot: Mutex::new(HashMap::new()),
+            throttles: budgets
+                .iter()
+                .map(|budget| Throttle::per_minute(budget.max_per_minute))
+                .collect(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Returns how long ago the governor was created, see `StartupThrottle`.
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Logs the configured budgets, to help tuning them.
+    pub fn log_budgets(&self) {
+        for budget in self.budgets {
+            let cap = if budget.max_per_minute == 0 {
+                "unbegrenzt".to_string()
+            } else {
+                format!("höchstens {} pro Minute", budget.max_per_minute)
+            };
+            log!(
+                "Anfragebudget für {}: alle {:.1} Sekunden, {}.",
+                budget.host,
+                budget.min_interval.as_secs_f32(),
+                cap
+            );
+        }
+    }
+
+    /// Waits until a request to the host of `url` fits into its budget.
+    pub async fn acquire_url(&self, url: &str) {
+        if let Ok(url) = Url::parse(url)
+            && let Some(host) = url.host_str()
+        {
+            self.acquire(host).await;
+        }
+    }
+
+    /// Waits until a request to `host` fits into its budget, then records it.
+    pub async fn acquire(&self, host: &str) {
+        let Some(index) = self.budgets.iter().position(|budget| budget.host == host) else {
+            return;
+        };
+        let budget = &self.budgets[index];
+
+        // Reserve the next slot right away, so concurrent callers line up behind each other.
+        let wait = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let now = Instant::now();
+            let slot = next_slot.get(budget.host).copied().unwrap_or(now).max(now);
+            next_slot.insert(budget.host, slot + budget.min_interval);
+            slot - now
+        };
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+        self.throttles[index].acquire(budget.host).await;
+    }
+
+    /// Keeps the next request to `host` an `extra` delay further away than its budget asks for,
+    /// e.g. `DelayRamp::extra_delay` of the next send.
+    pub fn hold_back(&self, host: &str, extra: Duration) {
+        let Some(budget) = self.budgets.iter().find(|budget| budget.host == host) else {
+            return;
+        };
+        let mut next_slot = self.next_slot.lock().unwrap();
+        let now = Instant::now();
+        let slot = next_slot.get(budget.host).copied().unwrap_or(now).max(now);
+        next_slot.insert(budget.host, slot + extra);
+    }
+}
+
+/// Adds a random share of up to `max_jitter` to a wait.
+///
+/// Senders that were rate-limited together would otherwise all retry at the same moment and
+/// collide again.
+pub fn with_jitter(wait: Duration, max_jitter: Duration) -> Duration {
+    let max_nanos = max_jitter.as_nanos() as u64;
+    if max_nanos == 0 {
+        return wait;
+    }
+    // The randomly keyed hasher of the standard library is random enough to spread retries.
+    let random = RandomState::new().build_hasher().finish();
+    wait + Duration::from_nanos(random % (max_nanos + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ramp_grows_after_the_fast_sends_up_to_its_cap() {
+        let ramp = DelayRamp {
+            fast_sends: 2,
+            step: Duration::from_secs(1),
+            max_extra: Duration::from_secs(3),
+        };
+        let delays: Vec<u64> = (0..7).map(|i| ramp.extra_delay(i).as_secs()).collect();
+        assert_eq!(delays, [0, 0, 1, 2, 3, 3, 3]);
+    }
+
+    #[test]
+    fn startup_delay_shrinks_to_nothing_over_the_window() {
+        let throttle = StartupThrottle {
+            window: Duration::from_secs(600),
+            extra: Duration::from_secs(20),
+        };
+        let delays: Vec<u64> = [0, 150, 300, 600, 900]
+            .map(|secs| throttle.extra_delay(Duration::from_secs(secs)).as_secs())
+            .to_vec();
+        assert_eq!(delays, [20, 15, 10, 0, 0]);
+
+        let disabled = StartupThrottle {
+            window: Duration::ZERO,
+            extra: Duration::from_secs(20),
+        };
+        assert!(disabled.extra_delay(Duration::ZERO).is_zero());
+    }
+
+    #[test]
+    fn jitter_stays_within_its_bound() {
+        let wait = Duration::from_secs(3);
+        for _ in 0..100 {
+            let jittered = with_jitter(wait, Duration::from_millis(500));
+            assert!(jittered >= wait && jittered <= wait + Duration::from_millis(500));
+        }
+        assert_eq!(with_jitter(wait, Duration::ZERO), wait);
+    }
+}
@@ -0,0 +1,133 @@
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone as _, Utc, Weekday,
+};
+
+/// The time zone relative posting times such as "Heute, 14:32" are read in.
+// Only the variant chosen in the configuration is ever constructed.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeZone {
+    /// German local time, which Kleinanzeigen shows, with summer time from the last Sunday
+    /// of March to the last Sunday of October.
+    EuropeBerlin,
+    /// Coordinated Universal Time.
+    Utc,
+    /// Whatever the server is set to.
+    System,
+}
+
+impl TimeZone {
+    /// Returns the current wall-clock time in this time zone.
+    pub fn now(self) -> NaiveDateTime {
+        self.wall_clock(Utc::now())
+    }
+
+    /// Converts a point in time to the wall-clock time of this time zone.
+    pub fn wall_clock(self, time: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            TimeZone::EuropeBerlin => {
+                let utc = time.naive_utc();
+                utc + Duration::hours(if berlin_summer_time(utc) { 2 } else { 1 })
+            }
+            TimeZone::Utc => time.naive_utc(),
+            TimeZone::System => time.with_timezone(&Local).naive_local(),
+        }
+    }
+
+    /// Converts a wall-clock time of this time zone to a point in time.
+    ///
+    /// A wall-clock time that occurs twice when summer time ends is read as the earlier one.
+    ///
+    /// # Returns
+    /// The point in time, or `None` if the wall-clock time doesn't exist in `System`'s time zone.
+    pub fn to_utc(self, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            TimeZone::EuropeBerlin => {
+                let summer = local - Duration::hours(2);
+                let utc = if berlin_summer_time(summer) {
+                    summer
+                } else {
+                    local - Duration::hours(1)
+                };
+                Some(utc.and_utc())
+            }
+            TimeZone::Utc => Some(local.and_utc()),
+            TimeZone::System => Local
+                .from_local_datetime(&local)
+                .earliest()
+                .map(|time| time.with_timezone(&Utc)),
+        }
+    }
+}
+
+/// Returns the last Sunday of a month.
+fn last_sunday(year: i32, month: u32) -> NaiveDate {
+    let mut day = NaiveDate::from_ymd_opt(year, month + 1, 1).unwrap() - Duration::days(1);
+    while day.weekday() != Weekday::Sun {
+        day -= Duration::days(1);
+    }
+    day
+}
+
+/// Checks whether summer time applies in Germany at a UTC time.
+///
+/// It starts and ends at 01:00 UTC on the last Sunday of March and October.
+fn berlin_summer_time(utc: NaiveDateTime) -> bool {
+    let start = last_sunday(utc.year(), 3).and_hms_opt(1, 0, 0).unwrap();
+    let end = last_sunday(utc.year(), 10).and_hms_opt(1, 0, 0).unwrap();
+    utc >= start && utc < end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M")
+            .unwrap()
+            .and_utc()
+    }
+
+    fn wall_clock(time: &str) -> String {
+        TimeZone::EuropeBerlin
+            .wall_clock(utc(time))
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    }
+
+    #[test]
+    fn berlin_switches_at_the_summer_time_transitions() {
+        assert_eq!(wall_clock("2026-01-15 12:00"), "2026-01-15 13:00");
+        assert_eq!(wall_clock("2026-03-29 00:59"), "2026-03-29 01:59");
+        assert_eq!(wall_clock("2026-03-29 01:00"), "2026-03-29 03:00");
+        assert_eq!(wall_clock("2026-10-25 00:59"), "2026-10-25 02:59");
+        assert_eq!(wall_clock("2026-10-25 01:00"), "2026-10-25 02:00");
+        assert_eq!(wall_clock("2026-12-31 23:30"), "2027-01-01 00:30");
+    }
+
+    #[test]
+    fn berlin_wall_clock_converts_back_to_utc() {
+        let to_utc = |local: &str| {
+            TimeZone::EuropeBerlin
+                .to_utc(utc(local).naive_utc())
+                .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        };
+        assert_eq!(
+            to_utc("2026-01-15 13:00").as_deref(),
+            Some("2026-01-15 12:00")
+        );
+        assert_eq!(
+            to_utc("2026-07-01 14:32").as_deref(),
+            Some("2026-07-01 12:32")
+        );
+        // 02:30 occurs twice on the last Sunday of October; the summer time one comes first.
+        assert_eq!(
+            to_utc("2026-10-25 02:30").as_deref(),
+            Some("2026-10-25 00:30")
+        );
+        assert_eq!(
+            to_utc("2026-10-25 03:00").as_deref(),
+            Some("2026-10-25 02:00")
+        );
+    }
+}
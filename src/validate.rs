@@ -0,0 +1,376 @@
+use std::collections::HashSet;
+
+use reqwest::{Proxy, Url};
+
+use crate::{
+    ACTIVE_SCHEDULE, DAILY_SUMMARY_AT, DEDUP_BY_IMAGE, DESCRIPTION_SNIPPET_CHARS,
+    DISCORD_WEBHOOK_URL, ENABLE_EVER_SEEN_FILTER, EVER_SEEN_CAPACITY,
+    EVER_SEEN_FALSE_POSITIVE_RATE, FILE_ID_TTL_DAYS, HOST_BUDGETS, IMAGE_RESOLUTION_RULES,
+    MATRIX_ACCESS_TOKEN, MATRIX_HOMESERVER, MATRIX_ROOM_ID, MAX_CONCURRENT_IMAGE_DOWNLOADS,
+    MAX_FILE_IDS, MAX_IMAGE_DOWNLOAD_BYTES, MAX_PAGES_TO_SCAN, MAX_RECENT_IMAGES,
+    MAX_SELLER_LISTINGS, MIN_SELLER_LISTINGS, NTFY_SERVER, NTFY_TOPIC, POLL_INTERVAL_SECS,
+    PROFILES, PRUNE_MODE, REDIS_SEEN_TTL_DAYS, REDIS_URL, REUSE_FILE_IDS, SEEN_BACKEND,
+    SEEN_TTL_DAYS, SEND_CONCURRENCY, TELEGRAM_SOCKS5_PROXY, TOP_MATCH_SCORE, UPLOAD_PHOTO_FALLBACK,
+    WISHLIST_MIN_SCORE, profile::Profile, schedule::Schedule, scrape_headers, search::Search,
+    seen::PruneMode, store::SeenBackend,
+};
+
+/// The outcome of `--validate-config`: every check made, with the problem it found, if any.
+#[derive(Debug, Default)]
+pub struct ConfigReport {
+    /// What was checked, and why it failed.
+    checks: Vec<(String, Option<String>)>,
+}
+
+impl ConfigReport {
+    /// Records a check, passed if `problem` is `None`.
+    fn check(&mut self, what: impl Into<String>, problem: Option<String>) {
+        self.checks.push((what.into(), problem));
+    }
+
+    /// Checks whether every check passed.
+    pub fn is_ok(&self) -> bool {
+        self.checks.iter().all(|(_, problem)| problem.is_none())
+    }
+
+    /// Prints one line per check to stdout, followed by the overall result.
+    pub fn print(&self) {
+        for (what, problem) in &self.checks {
+            match problem {
+                None => println!("✅ {}", what),
+                Some(problem) => println!("❌ {}: {}", what, problem),
+            }
+        }
+        let failed = self.checks.iter().filter(|(_, p)| p.is_some()).count();
+        if failed == 0 {
+            println!("Konfiguration gültig ({} Prüfungen).", self.checks.len());
+        } else {
+            println!(
+                "Konfiguration ungültig: {} von {} Prüfungen fehlgeschlagen.",
+                failed,
+                self.checks.len()
+            );
+        }
+    }
+}
+
+/// Checks that `url` is an absolute http(s) URL with a host.
+fn url_problem(url: &str) -> Option<String> {
+    match Url::parse(url) {
+        Ok(url) if !matches!(url.scheme(), "http" | "https") => {
+            Some(format!("'{}' ist keine http(s)-Adresse", url))
+        }
+        Ok(url) if url.host_str().is_none() => Some(format!("'{}' enthält keinen Host", url)),
+        Ok(_) => None,
+        Err(e) => Some(format!("'{}' ist keine gültige Adresse ({})", url, e)),
+    }
+}
+
+/// Checks a search's URL pieces and limits.
+///
+/// # Returns
+/// The problems found, empty if the search is fine.
+fn search_problems(search: &Search) -> Vec<String> {
+    let mut problems = Vec::new();
+    if search.name.trim().is_empty() {
+        problems.push("Der Name ist leer".to_string());
+    }
+    for page in [1, 2] {
+        let url = search.page_url(page);
+        if let Some(problem) = url_problem(&url) {
+            problems.push(problem);
+        } else if !url.contains("kleinanzeigen.de/") {
+            problems.push(format!("'{}' führt nicht zu Kleinanzeigen", url));
+        }
+    }
+    if !search.url_suffix.is_empty() && !search.url_suffix.starts_with('/') {
+        problems.push(format!(
+            "url_suffix '{}' muss mit '/' beginnen",
+            search.url_suffix
+        ));
+    }
+    if let Some(keyword) = search.keyword {
+        if !keyword.keyword.chars().any(char::is_alphanumeric) {
+            problems.push("Das Suchwort ist leer".to_string());
+        }
+        if let Some(postal_code) = keyword.postal_code
+            && (postal_code.len() != 5 || !postal_code.bytes().all(|b| b.is_ascii_digit()))
+        {
+            problems.push(format!(
+                "Die Postleitzahl '{}' hat nicht fünf Ziffern",
+                postal_code
+            ));
+        }
+        if keyword.radius_km.is_some() && keyword.location_id.is_none() {
+            problems.push("radius_km wirkt nur zusammen mit location_id".to_string());
+        }
+    }
+    if search.poll_interval_secs == Some(0) {
+        problems.push("poll_interval_secs ist 0, die Suche würde ohne Pause gescannt".to_string());
+    }
+    if search.max_pages == Some(0) {
+        problems.push("max_pages ist 0, es würde keine Seite gescannt".to_string());
+    }
+    if search
+        .chat_ids
+        .iter()
+        .any(|chat_id| chat_id.trim().is_empty())
+    {
+        problems.push("chat_ids enthält eine leere Chat-ID".to_string());
+    }
+    problems
+}
+
+/// Describes a problem if `failed` is true.
+fn problem_if(failed: bool, problem: &str) -> Option<String> {
+    failed.then(|| problem.to_string())
+}
+
+/// Checks the whole configuration without scraping or sending anything.
+pub fn validate_config() -> ConfigReport {
+    let mut report = ConfigReport::default();
+
+    // Credentials.
+    let other_channels_configured =
+        DISCORD_WEBHOOK_URL.is_some() || MATRIX_HOMESERVER.is_some() || NTFY_TOPIC.is_some();
+    report.check(
+        "Mindestens ein Kanal eingerichtet",
+        problem_if(
+            !PROFILES.iter().any(Profile::telegram_configured) && !other_channels_configured,
+            "Platzhalter für TELEGRAM_BOT_TOKEN und TELEGRAM_CHAT_ID, kein anderer Kanal",
+        ),
+    );
+    report.check("SCRAPE_HEADERS", scrape_headers().err());
+    if let Some(expression) = ACTIVE_SCHEDULE {
+        report.check(
+            "ACTIVE_SCHEDULE",
+            Schedule::parse(expression).err().map(|e| e.problem),
+        );
+    }
+    if let Some(proxy) = TELEGRAM_SOCKS5_PROXY {
+        report.check(
+            "TELEGRAM_SOCKS5_PROXY",
+            Proxy::all(proxy).err().map(|e| e.to_string()),
+        );
+    }
+    if let Some(url) = DISCORD_WEBHOOK_URL {
+        report.check("DISCORD_WEBHOOK_URL", url_problem(url));
+    }
+    if let Some(homeserver) = MATRIX_HOMESERVER {
+        report.check("MATRIX_HOMESERVER", url_problem(homeserver));
+        report.check(
+            "MATRIX_ACCESS_TOKEN / MATRIX_ROOM_ID",
+            problem_if(
+                MATRIX_ACCESS_TOKEN == "YOUR_MATRIX_ACCESS_TOKEN"
+                    || MATRIX_ROOM_ID == "YOUR_MATRIX_ROOM_ID",
+                "Platzhalter nicht ersetzt",
+            ),
+        );
+    }
+    if NTFY_TOPIC.is_some() {
+        report.check("NTFY_SERVER", url_problem(NTFY_SERVER));
+    }
+    if SEEN_BACKEND == SeenBackend::Redis {
+        report.check(
+            "REDIS_URL",
+            match Url::parse(REDIS_URL) {
+                Ok(url) if url.scheme() != "redis" => {
+                    Some("Die Adresse muss mit 'redis://' beginnen".to_string())
+                }
+                Ok(url) if url.host_str().is_none() => Some("Kein Host angegeben".to_string()),
+                Ok(_) => None,
+                Err(e) => Some(e.to_string()),
+            },
+        );
+        // Redis drops every entry after its TTL by itself, so the settings for pruning the
+        // seen list would be silently ignored.
+        report.check(
+            "PRUNE_MODE / SEEN_TTL_DAYS",
+            problem_if(
+                PRUNE_MODE == PruneMode::MaxAge || SEEN_TTL_DAYS.is_some(),
+                "Wirken nicht mit SeenBackend::Redis, dort läuft jeder Eintrag nach REDIS_SEEN_TTL_DAYS ab",
+            ),
+        );
+        report.check(
+            "REDIS_SEEN_TTL_DAYS",
+            problem_if(REDIS_SEEN_TTL_DAYS == 0, "Muss mindestens 1 sein"),
+        );
+    }
+
+    // Profiles and their searches.
+    report.check(
+        "PROFILES",
+        problem_if(PROFILES.is_empty(), "Kein Profil eingerichtet"),
+    );
+    let mut profile_names = HashSet::new();
+    for profile in PROFILES {
+        let label = match profile.name {
+            "" => String::new(),
+            name => format!("Profil '{}': ", name),
+        };
+        // The state files are named after the profile, so two profiles would share them.
+        report.check(
+            format!("{}Name", label),
+            problem_if(
+                !profile_names.insert(profile.name),
+                "Der Name wird von einem anderen Profil schon verwendet",
+            ),
+        );
+        if profile.telegram_configured() {
+            report.check(
+                format!("{}Telegram-Bot-Token", label),
+                problem_if(
+                    !profile.telegram_bot_token.contains(':'),
+                    "Ein Bot-Token hat die Form '123456:ABC...'",
+                ),
+            );
+        }
+        report.check(
+            format!("{}Suchen", label),
+            problem_if(profile.searches.is_empty(), "Keine Suche eingerichtet"),
+        );
+        let mut names = HashSet::new();
+        for search in profile.searches {
+            let mut problems = search_problems(search);
+            if !names.insert(search.name) {
+                // The cursors are keyed by name, so two searches would overwrite each other's.
+                problems.push("Der Name wird von einer anderen Suche schon verwendet".to_string());
+            }
+            report.check(
+                format!("{}Suche '{}'", label, search.name),
+                (!problems.is_empty()).then(|| problems.join("; ")),
+            );
+        }
+    }
+
+    // Numeric ranges.
+    report.check(
+        "MAX_PAGES_TO_SCAN",
+        problem_if(MAX_PAGES_TO_SCAN == 0, "Muss mindestens 1 sein"),
+    );
+    report.check(
+        "SEND_CONCURRENCY",
+        problem_if(SEND_CONCURRENCY == 0, "Muss mindestens 1 sein"),
+    );
+    report.check(
+        "POLL_INTERVAL_SECS",
+        problem_if(POLL_INTERVAL_SECS == 0, "Muss mindestens 1 sein"),
+    );
+    if let Some((hour, minute)) = DAILY_SUMMARY_AT {
+        report.check(
+            "DAILY_SUMMARY_AT",
+            problem_if(hour > 23 || minute > 59, "Keine gültige Uhrzeit"),
+        );
+    }
+    report.check(
+        "WISHLIST_MIN_SCORE",
+        problem_if(
+            !(0.0..=1.0).contains(&WISHLIST_MIN_SCORE),
+            "Muss zwischen 0 und 1 liegen",
+        ),
+    );
+    if let Some(score) = TOP_MATCH_SCORE {
+        report.check(
+            "TOP_MATCH_SCORE",
+            problem_if(
+                !(WISHLIST_MIN_SCORE..=1.0).contains(&score),
+                "Muss zwischen WISHLIST_MIN_SCORE und 1 liegen",
+            ),
+        );
+    }
+    if ENABLE_EVER_SEEN_FILTER {
+        report.check(
+            "EVER_SEEN_CAPACITY / EVER_SEEN_FALSE_POSITIVE_RATE",
+            problem_if(
+                EVER_SEEN_CAPACITY == 0
+                    || !(EVER_SEEN_FALSE_POSITIVE_RATE > 0.0 && EVER_SEEN_FALSE_POSITIVE_RATE < 1.0),
+                "Die Kapazität muss mindestens 1 und die Fehlerquote zwischen 0 und 1 (exklusiv) sein",
+            ),
+        );
+    }
+    if UPLOAD_PHOTO_FALLBACK {
+        report.check(
+            "MAX_CONCURRENT_IMAGE_DOWNLOADS / MAX_IMAGE_DOWNLOAD_BYTES",
+            problem_if(
+                MAX_CONCURRENT_IMAGE_DOWNLOADS == 0 || MAX_IMAGE_DOWNLOAD_BYTES == 0,
+                "Müssen mindestens 1 sein",
+            ),
+        );
+    }
+    if DEDUP_BY_IMAGE {
+        report.check(
+            "MAX_RECENT_IMAGES",
+            problem_if(MAX_RECENT_IMAGES == 0, "Muss mindestens 1 sein"),
+        );
+    }
+    if REUSE_FILE_IDS {
+        report.check(
+            "MAX_FILE_IDS / FILE_ID_TTL_DAYS",
+            problem_if(
+                MAX_FILE_IDS == 0 || FILE_ID_TTL_DAYS <= 0,
+                "Müssen mindestens 1 sein",
+            ),
+        );
+    }
+    if let (Some(min), Some(max)) = (MIN_SELLER_LISTINGS, MAX_SELLER_LISTINGS) {
+        report.check(
+            "MIN_SELLER_LISTINGS / MAX_SELLER_LISTINGS",
+            problem_if(min > max, "Das Minimum liegt über dem Maximum"),
+        );
+    }
+    report.check(
+        "DESCRIPTION_SNIPPET_CHARS",
+        problem_if(DESCRIPTION_SNIPPET_CHARS == 0, "Muss mindestens 1 sein"),
+    );
+    report.check(
+        "IMAGE_RESOLUTION_RULES",
+        problem_if(
+            IMAGE_RESOLUTION_RULES.iter().any(|rule| rule.is_empty()),
+            "Enthält eine leere Regel",
+        ),
+    );
+    for budget in HOST_BUDGETS {
+        report.check(
+            format!("HOST_BUDGETS '{}'", budget.host),
+            problem_if(
+                budget.host.is_empty() || budget.host.contains('/'),
+                "Nur der Hostname ohne Protokoll und Pfad, z. B. 'www.kleinanzeigen.de'",
+            ),
+        );
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::KeywordSearch;
+
+    #[test]
+    fn malformed_searches_are_reported() {
+        assert!(search_problems(&PROFILES[0].searches[0]).is_empty());
+
+        let broken = Search {
+            name: "",
+            base_url: "www.kleinanzeigen.de/s-zu-verschenken-tauschen",
+            url_suffix: "c272",
+            keyword: None,
+            chat_ids: &[" "],
+            max_pages: Some(0),
+            prefix: None,
+            poll_interval_secs: None,
+        };
+        assert_eq!(search_problems(&broken).len(), 6);
+
+        let keyword = Search {
+            keyword: Some(KeywordSearch {
+                keyword: " & ",
+                postal_code: Some("4105"),
+                location_id: None,
+                radius_km: Some(10),
+            }),
+            ..PROFILES[0].searches[0]
+        };
+        assert_eq!(search_problems(&keyword).len(), 3);
+    }
+}